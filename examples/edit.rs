@@ -17,12 +17,9 @@ fn main() -> ExitCode {
 
     // Get the editor
     let mut edit = run_editor::edit();
-    if let Some(variable) = &opts.variable {
-        edit.with_editor_variable(variable);
-    }
-    if let Some(editor) = &opts.editor {
-        edit.with(editor);
-    }
+    edit.with_editor_variable_opt(opts.variable.as_deref())
+        .with_opt(opts.editor.as_deref())
+        .use_dev_tty(opts.tty);
 
     // Execute action
     let result = match opts.action {
@@ -54,6 +51,7 @@ struct Opts {
     action: Action,
     variable: Option<OsString>,
     editor: Option<OsString>,
+    tty: bool,
 }
 
 enum Action {
@@ -82,7 +80,8 @@ fn usage(arg0: &Path) {
             "<options> is an optional list of options:\n",
             "  --var <var-name>     -- read the editor from given environment \
              variable\n",
-            "  --editor <editor>    -- use given editor command\n"
+            "  --editor <editor>    -- use given editor command\n",
+            "  --tty                -- connect editor to /dev/tty\n"
         ),
         arg0.display()
     )
@@ -90,7 +89,7 @@ fn usage(arg0: &Path) {
 
 
 fn parse_args(mut args: std::env::ArgsOs) -> Result<Opts, ()> {
-    let (mut variable, mut editor) = (None, None);
+    let (mut variable, mut editor, mut tty) = (None, None, false);
 
     let arg = loop {
         let arg = args.next().ok_or(())?;
@@ -98,6 +97,8 @@ fn parse_args(mut args: std::env::ArgsOs) -> Result<Opts, ()> {
             variable = Some(args.next().ok_or(())?);
         } else if arg == "--editor" {
             editor = Some(args.next().ok_or(())?);
+        } else if arg == "--tty" {
+            tty = true;
         } else {
             break arg;
         }
@@ -135,5 +136,5 @@ fn parse_args(mut args: std::env::ArgsOs) -> Result<Opts, ()> {
         return Err(());
     };
 
-    Ok(Opts { action, variable, editor })
+    Ok(Opts { action, variable, editor, tty })
 }
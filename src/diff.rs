@@ -0,0 +1,271 @@
+//! Line-based comparison of edited content.
+
+
+/// A line of a diff between original and edited content; see
+/// [`crate::Edit::file_review`].
+///
+/// Each line includes its terminating new line character unless it’s the last
+/// line of a file which doesn’t end with one.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DiffLine {
+    /// Line present in both original and edited content.
+    Unchanged(Vec<u8>),
+    /// Line only present in the original content.
+    Removed(Vec<u8>),
+    /// Line only present in the edited content.
+    Added(Vec<u8>),
+}
+
+/// Fate of a line of a buffer edited with
+/// [`crate::Edit::buffer_lines_tracked`].
+///
+/// `old` fields are indexes of the lines in the original buffer while `line`
+/// fields hold text of the line after editing, except for deleted lines where
+/// it’s the original text.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LineChange {
+    /// Original line has been kept unchanged.
+    Kept { old: usize, line: String },
+    /// Original line has been replaced by a different one.
+    Modified { old: usize, line: String },
+    /// New line has been added.
+    Added { line: String },
+    /// Original line has been deleted.
+    Deleted { old: usize, line: String },
+}
+
+/// Range of bytes in an edited buffer; see [`crate::Edit::buffer_ranges`].
+pub type ByteRange = std::ops::Range<usize>;
+
+/// Single step of transforming one sequence into another; see [`diff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Op {
+    /// Element at given indexes of the old and new sequence is kept.
+    Keep(usize, usize),
+    /// Element at given index of the old sequence is removed.
+    Remove(usize),
+    /// Element at given index of the new sequence is added.
+    Add(usize),
+}
+
+/// Computes the shortest sequence of operations transforming `old` into `new`.
+///
+/// The operations are ordered such that they walk both sequences from start
+/// to end with removals preceding additions.  This uses the longest common
+/// subsequence algorithm whose time and memory complexity is quadratic
+/// (excluding common prefix and suffix) which is acceptable for human-edited
+/// text.
+#[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+pub(super) fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) =
+        (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    // lcs[i][j] is length of the longest common subsequence of a[i..] and
+    // b[j..].
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = (0..prefix).map(|n| Op::Keep(n, n)).collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push(Op::Keep(prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if i < a.len() &&
+            (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1])
+        {
+            ops.push(Op::Remove(prefix + i));
+            i += 1;
+        } else {
+            ops.push(Op::Add(prefix + j));
+            j += 1;
+        }
+    }
+    let (old_end, new_end) = (prefix + a.len(), prefix + b.len());
+    ops.extend((0..suffix).map(|n| Op::Keep(old_end + n, new_end + n)));
+    ops
+}
+
+/// Computes line diff between two buffers.
+#[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+pub(super) fn diff_lines(old: &[u8], new: &[u8]) -> Vec<DiffLine> {
+    let old = old.split_inclusive(|&ch| ch == b'\n').collect::<Vec<_>>();
+    let new = new.split_inclusive(|&ch| ch == b'\n').collect::<Vec<_>>();
+    diff(&old, &new)
+        .into_iter()
+        .map(|op| match op {
+            Op::Keep(i, _) => DiffLine::Unchanged(old[i].to_vec()),
+            Op::Remove(i) => DiffLine::Removed(old[i].to_vec()),
+            Op::Add(j) => DiffLine::Added(new[j].to_vec()),
+        })
+        .collect()
+}
+
+/// Classifies lines of edited buffer with respect to the original.
+///
+/// Within each changed region, deleted lines are paired with added lines in
+/// order and each such pair is reported as a modification.  Remaining lines of
+/// the region are reported as deleted or added.
+#[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+pub(super) fn track_lines(
+    old: Vec<String>,
+    new: Vec<String>,
+) -> Vec<LineChange> {
+    fn flush(
+        removed: &mut Vec<(usize, String)>,
+        added: &mut Vec<String>,
+        changes: &mut Vec<LineChange>,
+    ) {
+        let mut removed = removed.drain(..);
+        let mut added = added.drain(..);
+        loop {
+            changes.push(match (removed.next(), added.next()) {
+                (Some((old, _)), Some(line)) => {
+                    LineChange::Modified { old, line }
+                }
+                (Some((old, line)), None) => LineChange::Deleted { old, line },
+                (None, Some(line)) => LineChange::Added { line },
+                (None, None) => break,
+            });
+        }
+    }
+
+    let ops = diff(&old, &new);
+    let mut old = old.into_iter().map(Some).collect::<Vec<_>>();
+    let mut new = new.into_iter().map(Some).collect::<Vec<_>>();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let mut changes = Vec::with_capacity(ops.len());
+    for op in ops {
+        match op {
+            Op::Keep(i, j) => {
+                flush(&mut removed, &mut added, &mut changes);
+                let line = new[j].take().unwrap();
+                changes.push(LineChange::Kept { old: i, line });
+            }
+            Op::Remove(i) => removed.push((i, old[i].take().unwrap())),
+            Op::Add(j) => added.push(new[j].take().unwrap()),
+        }
+    }
+    flush(&mut removed, &mut added, &mut changes);
+    changes
+}
+
+/// Returns range of `new` which differs from `old`.
+///
+/// The range is what remains after stripping common prefix and suffix; it’s
+/// empty if bytes have only been removed.  Returns `None` if the buffers are
+/// equal.
+#[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+pub(super) fn changed_range(old: &[u8], new: &[u8]) -> Option<ByteRange> {
+    if old == new {
+        return None;
+    }
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some(prefix..new.len() - suffix)
+}
+
+
+#[test]
+fn test_diff() {
+    fn test(old: &str, new: &str, want: &str) {
+        let (old, new) = (old.as_bytes(), new.as_bytes());
+        let got = diff(old, new)
+            .into_iter()
+            .map(|op| match op {
+                Op::Keep(i, j) => {
+                    assert_eq!(old[i], new[j]);
+                    char::from(old[i])
+                }
+                Op::Remove(i) => char::from(old[i]).to_ascii_uppercase(),
+                Op::Add(j) => char::from(new[j]).to_ascii_uppercase(),
+            })
+            .collect::<String>();
+        assert_eq!(want, got, "old: {old:?}; new: {new:?}");
+    }
+
+    test("", "", "");
+    test("abc", "abc", "abc");
+    test("abc", "", "ABC");
+    test("", "abc", "ABC");
+    test("abc", "axc", "aBXc");
+    test("abcd", "acd", "aBcd");
+    test("acd", "abcd", "aBcd");
+    test("abcabba", "cbabac", "ABcAbAbaC");
+}
+
+#[test]
+fn test_diff_lines() {
+    use DiffLine::*;
+
+    let line = |line: &str| line.as_bytes().to_vec();
+    assert_eq!(
+        vec![
+            Unchanged(line("foo\n")),
+            Removed(line("bar\n")),
+            Added(line("baz\n")),
+            Unchanged(line("qux")),
+        ],
+        diff_lines(b"foo\nbar\nqux", b"foo\nbaz\nqux")
+    );
+    assert_eq!(
+        vec![Removed(line("foo")), Added(line("foo\n"))],
+        diff_lines(b"foo", b"foo\n")
+    );
+    assert_eq!(Vec::<DiffLine>::new(), diff_lines(b"", b""));
+}
+
+#[test]
+fn test_track_lines() {
+    use LineChange::*;
+
+    let lines = |lines: &[&str]| {
+        lines.iter().copied().map(String::from).collect::<Vec<_>>()
+    };
+    let line = String::from;
+    assert_eq!(
+        vec![
+            Kept { old: 0, line: line("foo") },
+            Modified { old: 1, line: line("BAR") },
+            Added { line: line("qux") },
+            Kept { old: 2, line: line("baz") },
+            Deleted { old: 3, line: line("quux") },
+        ],
+        track_lines(
+            lines(&["foo", "bar", "baz", "quux"]),
+            lines(&["foo", "BAR", "qux", "baz"])
+        )
+    );
+    assert_eq!(Vec::<LineChange>::new(), track_lines(vec![], vec![]));
+}
+
+#[test]
+fn test_changed_range() {
+    assert_eq!(None, changed_range(b"", b""));
+    assert_eq!(None, changed_range(b"foo", b"foo"));
+    assert_eq!(Some(4..7), changed_range(b"foo bar baz", b"foo qux baz"));
+    assert_eq!(Some(4..4), changed_range(b"foo bar qux", b"foo qux"));
+    assert_eq!(Some(0..3), changed_range(b"", b"foo"));
+    assert_eq!(Some(3..4), changed_range(b"aaa", b"aaaa"));
+}
@@ -6,30 +6,150 @@ pub struct Error(pub(super) Inner);
 /// Actual error enum.  Create as separate type so that [`Error`] can be made
 /// opaque to the user.
 pub(super) enum Inner {
+    /// Editing has been cancelled by the application.
+    Cancelled { editor: std::ffi::OsString },
     /// Error spawning shell to execute editor.
     CmdError { error: std::io::Error },
+    /// File has been modified by someone else while user was editing it.
+    #[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+    ConcurrentModification {
+        path: std::path::PathBuf,
+        edited: std::path::PathBuf,
+    },
     /// Failure returned from the editor command.
     EditorError { editor: std::ffi::OsString, status: std::process::ExitStatus },
+    /// Editor command has been rejected by editor filter.
+    EditorRejected { reason: String },
+    /// Program of the editor command cannot be found.
+    EditorNotFound { program: std::ffi::OsString },
+    /// File is locked by another process.
+    Locked { path: std::path::PathBuf },
+    /// File doesn’t contain marker line delimiting region to edit.
+    #[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+    MissingMarker { path: std::path::PathBuf, marker: String },
+    /// Editor command has unbalanced quotes or a trailing backslash.
+    MalformedCommand { editor: std::ffi::OsString },
+    /// Editor command is empty or consists of white space only.
+    EmptyCommand,
+    /// No editor command is configured and there is no default.
+    NotConfigured,
+    /// Editor command requires a shell which has been forbidden.
+    ShellForbidden { editor: std::ffi::OsString },
+    /// Editor command runs a program which cannot save files.
+    ReadonlyEditor { editor: std::ffi::OsString },
+    /// Error returned by a buffer filter.
+    #[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+    FilterError { error: std::io::Error },
+    /// Path which should point to a file refers to a directory.
+    #[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+    IsADirectory { path: std::path::PathBuf },
+    /// Error writing edited data into output writer.
+    #[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+    OutputError { error: std::io::Error },
+    /// File to edit is not writable by the user.
+    PermissionDenied { path: std::path::PathBuf },
     /// IO error with path context.
     PathError { path: std::path::PathBuf, error: std::io::Error },
+    /// Editor has been killed because it didn’t finish in time.
+    TimedOut { editor: std::ffi::OsString, timeout: std::time::Duration },
+    /// File to edit is larger than configured limit.
+    TooLarge { path: std::path::PathBuf, size: u64, limit: u64 },
+    /// Edited content has been rejected by validation.
+    #[cfg_attr(not(feature = "with_tempfile"), allow(dead_code))]
+    Invalid { message: String },
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.0 {
+            Inner::Cancelled { editor } => {
+                let editor = std::path::Path::new(editor).display();
+                write!(fmt, "{}: killed because editing was cancelled", editor)
+            }
             Inner::CmdError { error } => {
                 write!(fmt, "sh: {}", error)
             }
+            Inner::ConcurrentModification { path, edited } => write!(
+                fmt,
+                "{}: file modified while being edited; edits saved in {}",
+                path.display(),
+                edited.display()
+            ),
             Inner::EditorError { editor, status } => {
-                debug_assert!(!status.success());
                 let editor = std::path::Path::new(editor).display();
                 let preposition =
                     if status.code().is_some() { "with" } else { "by" };
                 write!(fmt, "{}: terminated {} {}", editor, preposition, status)
             }
+            Inner::EditorRejected { reason } => {
+                write!(fmt, "editor rejected: {}", reason)
+            }
+            Inner::EditorNotFound { program } => {
+                let program = std::path::Path::new(program).display();
+                write!(fmt, "{}: editor not found", program)
+            }
+            Inner::Locked { path } => write!(
+                fmt,
+                "{}: file is being edited by another process",
+                path.display()
+            ),
+            Inner::MissingMarker { path, marker } => write!(
+                fmt,
+                "{}: region marker not found: {}",
+                path.display(),
+                marker
+            ),
+            Inner::MalformedCommand { editor } => {
+                let editor = std::path::Path::new(editor).display();
+                write!(
+                    fmt,
+                    "{}: malformed editor command: unterminated quote or \
+                     trailing backslash",
+                    editor
+                )
+            }
+            Inner::EmptyCommand => fmt.write_str("editor command is empty"),
+            Inner::NotConfigured => fmt.write_str(
+                "no editor configured; set VISUAL or EDITOR environment \
+                 variable",
+            ),
+            Inner::ShellForbidden { editor } => {
+                let editor = std::path::Path::new(editor).display();
+                write!(fmt, "{}: editor command requires a shell", editor)
+            }
+            Inner::ReadonlyEditor { editor } => {
+                let editor = std::path::Path::new(editor).display();
+                write!(
+                    fmt,
+                    "{}: not a text editor; it cannot save files",
+                    editor
+                )
+            }
+            Inner::FilterError { error } => write!(fmt, "filter: {}", error),
+            Inner::IsADirectory { path } => {
+                write!(fmt, "{}: is a directory", path.display())
+            }
+            Inner::OutputError { error } => write!(fmt, "output: {}", error),
+            Inner::PermissionDenied { path } => {
+                write!(fmt, "{}: file is not writable", path.display())
+            }
             Inner::PathError { path, error } => {
                 write!(fmt, "{}: {}", path.display(), error)
             }
+            Inner::TimedOut { editor, timeout } => {
+                let editor = std::path::Path::new(editor).display();
+                write!(fmt, "{}: killed after timeout of {:?}", editor, timeout)
+            }
+            Inner::TooLarge { path, size, limit } => write!(
+                fmt,
+                "{}: file too large ({} bytes, limit is {} bytes)",
+                path.display(),
+                size,
+                limit
+            ),
+            Inner::Invalid { message } => {
+                write!(fmt, "invalid content: {}", message)
+            }
         }
     }
 }
@@ -42,6 +162,225 @@ impl std::fmt::Debug for Error {
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Returns category of the error.
+    ///
+    /// This allows handling different failures differently, for example
+    /// retrying if the editor crashed but respecting user’s intention if the
+    /// editor deliberately exited with non-zero status.
+    ///
+    /// ```no_run
+    /// use run_editor::ErrorKind;
+    ///
+    /// let path = "/home/lex/.shellrc";
+    /// let edit = run_editor::edit();
+    /// let mut res = edit.file(path);
+    /// if matches!(&res, Err(err) if err.kind() == ErrorKind::EditorCrashed) {
+    ///     res = edit.file(path);
+    /// }
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match &self.0 {
+            Inner::Cancelled { .. } => ErrorKind::Cancelled,
+            Inner::CmdError { .. } => ErrorKind::SpawnFailed,
+            Inner::ConcurrentModification { .. } => {
+                ErrorKind::ConcurrentModification
+            }
+            Inner::EditorError { status, .. } if status.code().is_none() => {
+                ErrorKind::EditorCrashed
+            }
+            Inner::EditorError { .. } => ErrorKind::EditorFailed,
+            Inner::EditorRejected { .. } => ErrorKind::EditorRejected,
+            Inner::EditorNotFound { .. } => ErrorKind::EditorNotFound,
+            Inner::Locked { .. } => ErrorKind::Locked,
+            Inner::MissingMarker { .. } => ErrorKind::MissingMarker,
+            Inner::MalformedCommand { .. } => ErrorKind::MalformedCommand,
+            Inner::EmptyCommand => ErrorKind::EmptyCommand,
+            Inner::NotConfigured => ErrorKind::NotConfigured,
+            Inner::ShellForbidden { .. } => ErrorKind::ShellForbidden,
+            Inner::ReadonlyEditor { .. } => ErrorKind::ReadonlyEditor,
+            Inner::FilterError { .. } => ErrorKind::Filter,
+            Inner::IsADirectory { .. } => ErrorKind::IsADirectory,
+            Inner::OutputError { .. } => ErrorKind::Output,
+            Inner::PermissionDenied { .. } => ErrorKind::PermissionDenied,
+            Inner::PathError { .. } => ErrorKind::Io,
+            Inner::TimedOut { .. } => ErrorKind::TimedOut,
+            Inner::TooLarge { .. } => ErrorKind::TooLarge,
+            Inner::Invalid { .. } => ErrorKind::Invalid,
+        }
+    }
+
+    /// Returns kind of the underlying IO error if the failure was caused by
+    /// one.
+    ///
+    /// This is the case for errors spawning the editor and for errors
+    /// operating on files (i.e. errors whose [`kind`](`Self::kind`) is
+    /// [`ErrorKind::SpawnFailed`], [`ErrorKind::Io`], [`ErrorKind::Filter`]
+    /// or [`ErrorKind::Output`]) as well as for
+    /// [`ErrorKind::PermissionDenied`] errors.  For other errors returns
+    /// `None`.
+    ///
+    /// ```no_run
+    /// let res = run_editor::edit().file("/etc/motd");
+    /// if let Err(err) = res {
+    ///     if err.io_error_kind() == Some(std::io::ErrorKind::PermissionDenied) {
+    ///         eprintln!("try running with sudo");
+    ///     }
+    /// }
+    /// ```
+    pub fn io_error_kind(&self) -> Option<std::io::ErrorKind> {
+        match &self.0 {
+            Inner::CmdError { error } |
+            Inner::FilterError { error } |
+            Inner::OutputError { error } |
+            Inner::PathError { error, .. } => Some(error.kind()),
+            Inner::PermissionDenied { .. } => {
+                Some(std::io::ErrorKind::PermissionDenied)
+            }
+            _ => None,
+        }
+    }
+
+    /// Constructs error rejecting the editor command for given reason.
+    ///
+    /// This is meant for filters installed with
+    /// [`crate::Edit::with_editor_filter`] which want to abort editing with
+    /// [`ErrorKind::EditorRejected`] error.  Filters may also return any other
+    /// error, e.g. one they got from the crate.
+    ///
+    /// ```
+    /// let err = run_editor::Error::editor_rejected("emacs is not allowed");
+    /// assert_eq!(run_editor::ErrorKind::EditorRejected, err.kind());
+    /// assert_eq!("editor rejected: emacs is not allowed", err.to_string());
+    /// ```
+    pub fn editor_rejected(reason: impl Into<String>) -> Self {
+        Self(Inner::EditorRejected { reason: reason.into() })
+    }
+}
+
+/// Constructors meant for tests of code handling errors returned by the crate.
+///
+/// These require `test-util` Cargo feature to be enabled.
+#[cfg(feature = "test-util")]
+impl Error {
+    /// Constructs error reporting that the editor exited with given status,
+    /// e.g. as returned by [`crate::Edit::file`].
+    ///
+    /// ```
+    /// # #[cfg(unix)] {
+    /// use std::os::unix::process::ExitStatusExt;
+    ///
+    /// let status = std::process::ExitStatus::from_raw(3 << 8);
+    /// let err = run_editor::Error::editor_failed("vi", status);
+    /// assert_eq!(run_editor::ErrorKind::EditorFailed, err.kind());
+    /// assert_eq!("vi: terminated with exit status: 3", err.to_string());
+    /// # }
+    /// ```
+    pub fn editor_failed(
+        editor: impl Into<std::ffi::OsString>,
+        status: std::process::ExitStatus,
+    ) -> Self {
+        Self(Inner::EditorError { editor: editor.into(), status })
+    }
+
+    /// Constructs error reporting that the shell executing the editor couldn’t
+    /// be started.
+    pub fn spawn_failed(error: std::io::Error) -> Self {
+        Self(Inner::CmdError { error })
+    }
+
+    /// Constructs error reporting that an IO operation on given file failed.
+    pub fn io(
+        path: impl Into<std::path::PathBuf>,
+        error: std::io::Error,
+    ) -> Self {
+        Self(Inner::PathError { path: path.into(), error })
+    }
+}
+
+/// Category of an [`Error`]; see [`Error::kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The editor has been killed because the application cancelled editing;
+    /// see [`crate::Edit::file_polling`].
+    Cancelled,
+    /// The shell executing the editor couldn’t be started or waited for.
+    SpawnFailed,
+    /// The file has been modified by someone else while user was editing it;
+    /// see [`crate::Edit::file_guarded`].
+    ConcurrentModification,
+    /// The editor has been terminated by a signal, e.g. because it crashed.
+    EditorCrashed,
+    /// The editor exited with non-zero status.
+    EditorFailed,
+    /// The editor command has been rejected by the editor filter; see
+    /// [`crate::Edit::with_editor_filter`].
+    EditorRejected,
+    /// Program of the editor command couldn’t be found; see
+    /// [`crate::Edit::prepare`].
+    EditorNotFound,
+    /// The file is locked by another process; see
+    /// [`crate::Edit::with_lock`].
+    Locked,
+    /// The file doesn’t contain marker delimiting region to edit; see
+    /// [`crate::Edit::file_region`].
+    MissingMarker,
+    /// The editor command has unbalanced quotes; see
+    /// [`crate::Edit::strict_command`].
+    MalformedCommand,
+    /// The editor command is empty; see [`crate::Edit::try_editor`].
+    EmptyCommand,
+    /// No editor command is configured; see
+    /// [`crate::Edit::use_default_editor`].
+    NotConfigured,
+    /// The editor command uses shell syntax but running it through a shell
+    /// has been forbidden; see [`crate::Edit::no_shell`].
+    ShellForbidden,
+    /// The editor command runs a program which cannot save files; see
+    /// [`crate::Edit::warn_readonly_editor`].
+    ReadonlyEditor,
+    /// A buffer filter failed; see [`crate::Edit::with_pre_filter`].
+    Filter,
+    /// A path which should point to a file refers to a directory.
+    IsADirectory,
+    /// Writing edited data into the output failed; see
+    /// [`crate::Edit::buffer_to`].
+    Output,
+    /// The file is not writable by the user; see
+    /// [`crate::Edit::warn_unwritable`].
+    PermissionDenied,
+    /// An IO operation on a file failed.
+    Io,
+    /// The editor has been killed after a timeout; see
+    /// [`crate::Edit::with_timeout`].
+    TimedOut,
+    /// The file is too large to edit; see [`crate::Edit::warn_above_size`].
+    TooLarge,
+    /// Edited content has been rejected by validation; see
+    /// [`crate::Edit::edit_or_default`].
+    Invalid,
+}
+
+
+/// Invalid value passed to a validating [`crate::Edit`] setter such as
+/// [`crate::Edit::try_with_timeout`].
+pub struct BuildError(pub(super) &'static str);
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str(self.0)
+    }
+}
+
+impl std::fmt::Debug for BuildError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, fmt)
+    }
+}
+
+impl std::error::Error for BuildError {}
+
 
 /// Converts `std::io::Error` into an `Error(Inner::PathError)` adding specified
 /// path.
@@ -65,6 +404,57 @@ impl<T, P, E: WithPathContext<P>> WithPathContext<P> for Result<T, E> {
 }
 
 
+#[test]
+#[cfg(unix)]
+fn test_kind() {
+    let run = |command: &str| {
+        let status = std::process::Command::new("/bin/sh")
+            .args(["-c", command])
+            .status()
+            .unwrap();
+        let editor = std::ffi::OsString::from(command);
+        Error(Inner::EditorError { editor, status }).kind()
+    };
+
+    assert_eq!(ErrorKind::EditorFailed, run("exit 3"));
+    assert_eq!(ErrorKind::EditorCrashed, run("kill -SEGV $$"));
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_constructors() {
+    let error = std::io::Error::from(std::io::ErrorKind::NotFound);
+    let err = Error::io("/etc/app.conf", error);
+    assert_eq!(ErrorKind::Io, err.kind());
+    assert_eq!(Some(std::io::ErrorKind::NotFound), err.io_error_kind());
+    assert!(err.to_string().starts_with("/etc/app.conf: "), "{}", err);
+
+    let error = std::io::Error::from(std::io::ErrorKind::NotFound);
+    let err = Error::spawn_failed(error);
+    assert_eq!(ErrorKind::SpawnFailed, err.kind());
+    assert!(err.to_string().starts_with("sh: "), "{}", err);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        let status = std::process::ExitStatus::from_raw(9);
+        let err = Error::editor_failed("vi", status);
+        assert_eq!(ErrorKind::EditorCrashed, err.kind());
+        assert_eq!("vi: terminated by signal: 9 (SIGKILL)", err.to_string());
+    }
+}
+
+#[test]
+fn test_io_error_kind() {
+    let error = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+    let path = std::path::PathBuf::from("/etc/motd");
+    let err = Error(Inner::PathError { path: path.clone(), error });
+    assert_eq!(Some(std::io::ErrorKind::PermissionDenied), err.io_error_kind());
+
+    let err = Error(Inner::IsADirectory { path });
+    assert_eq!(None, err.io_error_kind());
+}
+
 #[test]
 fn test_editor_failure() {
     fn check(
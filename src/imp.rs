@@ -6,71 +6,1843 @@
 use std::ffi::{OsStr, OsString};
 
 use crate::error;
+use crate::error::WithPathContext;
+
+
+/// Runs user’s preferred editor on given file; see [`crate::Edit::file`].
+///
+/// If in-process editor is set, calls it instead of spawning the editor; see
+/// [`crate::Edit::with_inprocess_editor`].
+pub(super) fn edit_file(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    with_hooks(edit, &[path], || {
+        if let Some(inprocess) = &edit.inprocess_editor {
+            return inprocess(path).with_path_ctx(path);
+        }
+        if reads_stdin(edit) {
+            return read_stdin_into(path);
+        }
+        let child = spawn(edit, &editor, &[path], |_| ())?;
+        finish(edit, editor, child)
+    })
+}
+
+/// Returns whether editing is performed without spawning the editor, i.e. if
+/// in-process editor is set or standard input is read instead.
+pub(super) fn replaces_editor(edit: &crate::Edit) -> bool {
+    edit.inprocess_editor.is_some() || reads_stdin(edit)
+}
+
+/// Returns whether standard input should be read in place of running the
+/// editor; see [`crate::Edit::fallback_line_editor`].
+fn reads_stdin(edit: &crate::Edit) -> bool {
+    use std::io::IsTerminal;
+    edit.stdin_fallback && !std::io::stdin().is_terminal()
+}
+
+/// Replaces contents of given file with all data read from standard input.
+fn read_stdin_into(path: &std::path::Path) -> Result<(), error::Error> {
+    use std::io::Read;
+
+    let mut buf = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut buf).map_err(cmd_error)?;
+    std::fs::write(path, buf).with_path_ctx(path)
+}
+
+/// Calls `run` surrounded by calls to hooks; see [`crate::Edit::on_launch`]
+/// and [`crate::Edit::on_exit`].
+fn with_hooks<T>(
+    edit: &crate::Edit,
+    paths: &[&std::path::Path],
+    run: impl FnOnce() -> Result<T, error::Error>,
+) -> Result<T, error::Error> {
+    if let Some(hook) = &edit.on_launch {
+        paths.iter().for_each(|path| hook(path));
+    }
+    let res = run();
+    if let Some(hook) = &edit.on_exit {
+        paths.iter().for_each(|path| hook(path));
+    }
+    res
+}
+
+/// Runs user’s preferred editor on several files at once.
+///
+/// If in-process editor is set, it’s called for each file in turn; see
+/// [`crate::Edit::files`].
+pub(super) fn edit_files(
+    edit: &crate::Edit,
+    editor: OsString,
+    paths: &[&std::path::Path],
+) -> Result<(), error::Error> {
+    if replaces_editor(edit) {
+        return paths
+            .iter()
+            .try_for_each(|path| edit_file(edit, editor.clone(), path));
+    }
+    with_hooks(edit, paths, || {
+        let child = spawn(edit, &editor, paths, |_| ())?;
+        finish(edit, editor, child)
+    })
+}
+
+/// Removes paths referring to the same file keeping the first occurrence; see
+/// [`crate::Edit::dedup_paths`].
+pub(super) fn dedup_paths(
+    paths: Vec<std::path::PathBuf>,
+) -> Vec<std::path::PathBuf> {
+    let mut seen = std::collections::HashSet::new();
+    paths
+        .into_iter()
+        .filter(|path| {
+            let key = std::fs::canonicalize(path)
+                .unwrap_or_else(|_| normalize_path(path));
+            seen.insert(key)
+        })
+        .collect()
+}
+
+/// Lexically normalises path making it absolute and resolving `.` and `..`
+/// components without accessing the file system (other than to get current
+/// working directory).
+fn normalize_path(path: &std::path::Path) -> std::path::PathBuf {
+    use std::path::Component;
+
+    let mut normalized = std::path::PathBuf::new();
+    if path.is_relative() {
+        if let Ok(dir) = std::env::current_dir() {
+            normalized = dir;
+        }
+    }
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Runs editor on a file on remote host; see [`crate::Edit::remote`].
+#[cfg(feature = "ssh")]
+pub(super) fn edit_remote(
+    edit: &crate::Edit,
+    editor: OsString,
+    host: &OsStr,
+    path: &OsStr,
+) -> Result<(), error::Error> {
+    let (program, args) = remote_command_line(&editor, host, path);
+    let child = std::process::Command::new(program)
+        .args(args)
+        .spawn()
+        .map_err(cmd_error)?;
+    finish(edit, editor, child)
+}
+
+/// Constructs program and its arguments which run editor on a remote file;
+/// see [`crate::Edit::remote_command`].
+#[cfg(feature = "ssh")]
+pub(super) fn remote_command_line(
+    editor: &OsStr,
+    host: &OsStr,
+    path: &OsStr,
+) -> (OsString, Vec<OsString>) {
+    let path = crate::shell::quote(&crate::shell::to_bytes(path));
+    let mut command = editor.to_owned();
+    command.push(" ");
+    command.push(crate::shell::from_bytes(path));
+    ("ssh".into(), vec!["-t".into(), "--".into(), host.into(), command])
+}
+
+/// Runs user’s preferred editor on given file and returns what it wrote to
+/// standard output; see [`crate::Edit::buffer_capture_stdout`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn capture_stdout(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+) -> Result<Vec<u8>, error::Error> {
+    use std::io::Read;
+
+    if replaces_editor(edit) {
+        edit_file(edit, editor, path)?;
+        return std::fs::read(path).with_path_ctx(path);
+    }
+
+    with_hooks(edit, &[path], || {
+        let mut child = spawn(edit, &editor, &[path], |cmd| {
+            cmd.stdout(std::process::Stdio::piped());
+        })?;
+        // Read the output in a separate thread so that the editor doesn’t block
+        // on full pipe while we’re waiting for it to terminate.
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            stdout.read_to_end(&mut buf).map(|_| buf)
+        });
+        finish(edit, editor, child)?;
+        reader.join().unwrap().map_err(cmd_error)
+    })
+}
+
+/// Starts process running the editor on given files.
+///
+/// `setup` is called to further configure the process before it’s spawned.  If
+/// the shell cannot be found, the process is retried with fallback interpreter
+/// if one is configured; see [`crate::Edit::with_shell_fallback`].
+fn spawn(
+    edit: &crate::Edit,
+    editor: &OsStr,
+    paths: &[&std::path::Path],
+    setup: impl Fn(&mut std::process::Command),
+) -> Result<std::process::Child, error::Error> {
+    let spawn = |shell| {
+        let mut cmd = command(edit, editor, paths, shell);
+        setup(&mut cmd);
+        cmd.spawn()
+    };
+    match (spawn(None), edit.shell_fallback) {
+        (Err(err), Some(fallback))
+            if err.kind() == std::io::ErrorKind::NotFound =>
+        {
+            spawn(Some(fallback))
+        }
+        (res, _) => res,
+    }
+    .map_err(cmd_error)
+}
+
+/// Converts error spawning or waiting for the editor into [`error::Error`].
+fn cmd_error(error: std::io::Error) -> error::Error {
+    error::Error(error::Inner::CmdError { error })
+}
+
+/// Runs user’s preferred editor on given file calling `poll` while waiting for
+/// it to terminate; see [`crate::Edit::file_polling`].
+pub(super) fn edit_file_polling(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+    mut poll: impl FnMut() -> bool,
+) -> Result<(), error::Error> {
+    if replaces_editor(edit) {
+        return edit_file(edit, editor, path);
+    }
+    with_hooks(edit, &[path], || {
+        let mut child = spawn(edit, &editor, &[path], |_| ())?;
+        let start = std::time::Instant::now();
+        let mut soft_timeout = edit.soft_timeout.as_ref();
+        let status = loop {
+            if let Some(status) = child.try_wait().map_err(cmd_error)? {
+                break Some(status);
+            }
+            check_soft_timeout(&mut soft_timeout, start.elapsed());
+            if !poll() {
+                child.kill().and_then(|_| child.wait()).map_err(cmd_error)?;
+                return Err(error::Error(error::Inner::Cancelled { editor }));
+            }
+            let elapsed = start.elapsed();
+            if edit.timeout.is_some_and(|timeout| elapsed >= timeout) {
+                child.kill().and_then(|_| child.wait()).map_err(cmd_error)?;
+                break None;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        check_status(edit, editor, status)
+    })
+}
+
+/// Waits for spawned editor to terminate and checks its exit status.
+fn finish(
+    edit: &crate::Edit,
+    editor: OsString,
+    mut child: std::process::Child,
+) -> Result<(), error::Error> {
+    let status = wait(edit, &mut child).map_err(cmd_error)?;
+    check_status(edit, editor, status)
+}
+
+/// Checks exit status of terminated editor.
+///
+/// `status` is `None` if the editor has been killed after a timeout.
+fn check_status(
+    edit: &crate::Edit,
+    editor: OsString,
+    status: Option<std::process::ExitStatus>,
+) -> Result<(), error::Error> {
+    match status {
+        Some(status) if is_success(edit, status) => Ok(()),
+        Some(status) => Err(error::Inner::EditorError { editor, status }),
+        None => {
+            let timeout = edit.timeout.unwrap_or_default();
+            Err(error::Inner::TimedOut { editor, timeout })
+        }
+    }
+    .map_err(error::Error)
+}
+
+/// Returns whether editor’s exit status indicates success.
+///
+/// On Unix, termination by one of signals configured with
+/// [`crate::Edit::with_ok_signals`] is considered success as well.
+fn is_success(edit: &crate::Edit, status: std::process::ExitStatus) -> bool {
+    if let Some(predicate) = &edit.success_predicate {
+        return predicate(&status);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return edit.ok_signals.contains(&signal);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = edit;
+    status.success()
+}
+
+/// Interval between checks whether the editor has terminated.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(10);
+
+/// Waits for the editor to terminate.
+///
+/// Returns `None` if the editor has been killed because it didn’t finish in
+/// time; see [`crate::Edit::with_timeout`].
+fn wait(
+    edit: &crate::Edit,
+    child: &mut std::process::Child,
+) -> std::io::Result<Option<std::process::ExitStatus>> {
+    if edit.timeout.is_none() && edit.soft_timeout.is_none() {
+        return child.wait().map(Some);
+    }
+    let start = std::time::Instant::now();
+    let mut soft_timeout = edit.soft_timeout.as_ref();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let elapsed = start.elapsed();
+        check_soft_timeout(&mut soft_timeout, elapsed);
+        let timeout = edit.timeout.unwrap_or(std::time::Duration::MAX);
+        if elapsed >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(POLL_INTERVAL.min(timeout - elapsed));
+    }
+}
+
+/// Calls soft timeout callback if its time has elapsed; see
+/// [`crate::Edit::with_soft_timeout`].
+///
+/// Once the callback is called, `soft_timeout` is cleared so that it’s not
+/// called again.
+fn check_soft_timeout(
+    soft_timeout: &mut Option<&(std::time::Duration, crate::SoftTimeout)>,
+    elapsed: std::time::Duration,
+) {
+    if let Some((timeout, callback)) = soft_timeout {
+        if elapsed >= *timeout {
+            callback();
+            *soft_timeout = None;
+        }
+    }
+}
+
+
+/// Checks whether program of the editor command can be executed; see
+/// [`crate::Edit::prepare`].
+pub(super) fn check_editor(editor: &OsStr) -> Result<(), error::Error> {
+    let program = crate::shell::program(editor);
+    match program.as_deref().and_then(which) {
+        Some(_) => Ok(()),
+        None => {
+            let program = program.unwrap_or_else(|| editor.to_owned());
+            Err(error::Error(error::Inner::EditorNotFound { program }))
+        }
+    }
+}
+
+/// Looks up an executable program.
+///
+/// If `program` contains a slash, checks whether it’s an executable file.
+/// Otherwise, searches directories in `PATH` environment variable for an
+/// executable file with given name.
+pub(super) fn which(program: &OsStr) -> Option<std::path::PathBuf> {
+    let is_executable = |path: &std::path::Path| {
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => return false,
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.permissions().mode() & 0o111 != 0
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = metadata;
+            true
+        }
+    };
+
+    let path = std::path::Path::new(program);
+    if path.components().count() > 1 {
+        return Some(path.to_path_buf()).filter(|path| is_executable(path));
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+
+/// Creates missing parent directories of given file; see
+/// [`crate::Edit::with_create_parents`].
+pub(super) fn create_parents(
+    edit: &crate::Edit,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    match path.parent() {
+        Some(dir) if edit.create_parents && !dir.as_os_str().is_empty() => {
+            std::fs::create_dir_all(dir).with_path_ctx(dir)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Calls `run` and restores mode of given file if it has changed in the
+/// meantime; see [`crate::Edit::preserve_mode`].
+pub(super) fn preserving_mode(
+    edit: &crate::Edit,
+    path: &std::path::Path,
+    run: impl FnOnce() -> Result<(), error::Error>,
+) -> Result<(), error::Error> {
+    #[cfg(unix)]
+    if edit.preserve_mode {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.permissions().mode() & 0o7777,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return run();
+            }
+            Err(err) => return Err(err.with_path_ctx(path)),
+        };
+        let res = run();
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return res;
+            }
+            Err(err) => return res.and(Err(err.with_path_ctx(path))),
+        };
+        if metadata.permissions().mode() & 0o7777 != mode {
+            let perms = std::fs::Permissions::from_mode(mode);
+            let restored = std::fs::set_permissions(path, perms);
+            return res.and(restored.with_path_ctx(path));
+        }
+        return res;
+    }
+    #[cfg(not(unix))]
+    let _ = (edit, path);
+    run()
+}
+
+/// Checks whether file can be edited; see [`crate::Edit::warn_above_size`] and
+/// [`crate::Edit::warn_unwritable`].
+pub(super) fn check_file(
+    edit: &crate::Edit,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    check_size(edit, path)?;
+    if edit.check_writable {
+        check_writable(path)?;
+    }
+    Ok(())
+}
+
+/// Checks whether file is not too large to edit; see
+/// [`crate::Edit::warn_above_size`].
+fn check_size(
+    edit: &crate::Edit,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    let limit = match edit.max_file_size {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+    let size = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.with_path_ctx(path)),
+    };
+    if size > limit {
+        let path = path.to_path_buf();
+        Err(error::Error(error::Inner::TooLarge { path, size, limit }))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks whether file can be opened for writing; see
+/// [`crate::Edit::warn_unwritable`].
+///
+/// Only regular files are probed.  Opening special files, such as FIFOs, may
+/// block or have side effects so they are let through.
+fn check_writable(path: &std::path::Path) -> Result<(), error::Error> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {
+            let path = path.to_path_buf();
+            return Err(error::Error(error::Inner::IsADirectory { path }));
+        }
+        Ok(metadata) if !metadata.is_file() => return Ok(()),
+        Ok(_) => (),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.with_path_ctx(path)),
+    }
+    match std::fs::OpenOptions::new().write(true).open(path) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+            let path = path.to_path_buf();
+            Err(error::Error(error::Inner::PermissionDenied { path }))
+        }
+        Err(err) => Err(err.with_path_ctx(path)),
+    }
+}
+
+
+/// Reads editor command from the first line of given reader; see
+/// [`crate::Edit::with_command_from_reader`].
+///
+/// Reads one byte at a time so that nothing past the first line is consumed.
+/// Returns `None` if the line is blank or reading fails.
+pub(super) fn read_command(rd: &mut dyn std::io::Read) -> Option<OsString> {
+    let mut line = Vec::new();
+    let mut byte = 0;
+    loop {
+        match rd.read(std::slice::from_mut(&mut byte)) {
+            Ok(0) => break,
+            Ok(_) if byte == b'\n' => break,
+            Ok(_) => line.push(byte),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(_) => return None,
+        }
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    if line.iter().all(u8::is_ascii_whitespace) {
+        None
+    } else {
+        Some(crate::shell::from_bytes(line))
+    }
+}
+
+
+/// Lock file held while a file is being edited; see [`crate::Edit::with_lock`].
+///
+/// The lock file is removed when the object is dropped.
+pub(super) struct LockFile(std::path::PathBuf);
+
+impl Drop for LockFile {
+    fn drop(&mut self) { let _ = std::fs::remove_file(&self.0); }
+}
+
+/// Acquires lock for editing given file; see [`crate::Edit::with_lock`].
+///
+/// Returns the lock which is held until dropped or `None` if locking is not
+/// enabled.
+pub(super) fn lock(
+    edit: &crate::Edit,
+    path: &std::path::Path,
+) -> Result<Option<LockFile>, error::Error> {
+    if !edit.lock {
+        return Ok(None);
+    }
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = std::path::PathBuf::from(lock_path);
+
+    loop {
+        let res = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path);
+        match res {
+            Ok(_) => return Ok(Some(LockFile(lock_path))),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                if !edit.lock_wait {
+                    let path = path.to_path_buf();
+                    return Err(error::Error(error::Inner::Locked { path }));
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Err(err) => return Err(err.with_path_ctx(lock_path)),
+        }
+    }
+}
+
+
+/// Constructs program and its arguments which run editor on given file; see
+/// [`crate::Edit::audit_command`].
+///
+/// The editor command is executed through a shell with the path passed as
+/// a positional parameter so that it doesn’t need to be escaped.  If
+/// [`crate::Edit::with_path_var`] has been used, the path is instead passed
+/// through an environment variable; see [`command`].  The reference to the
+/// path is placed according to [`crate::Edit::path_position`].  Leading,
+/// editor-specific and trailing arguments (see
+/// [`crate::Edit::with_leading_args`] and [`crate::Edit::with_editor_args`])
+/// are passed as positional parameters as well.
+pub(super) fn command_line(
+    edit: &crate::Edit,
+    editor: &OsStr,
+    path: &std::path::Path,
+) -> (OsString, Vec<OsString>) {
+    with_launcher(edit, shell_command_line(edit, editor, &[path]))
+}
+
+/// Prepends launcher to given command line; see
+/// [`crate::Edit::with_launcher`].
+fn with_launcher(
+    edit: &crate::Edit,
+    (program, args): (OsString, Vec<OsString>),
+) -> (OsString, Vec<OsString>) {
+    match edit.launcher.split_first() {
+        None => (program, args),
+        Some((launcher, launcher_args)) => {
+            let mut all = launcher_args.to_vec();
+            all.push(program);
+            all.extend(args);
+            (launcher.clone(), all)
+        }
+    }
+}
+
+/// Constructs shell and its arguments which run editor on given files; this is
+/// [`command_line`] without the launcher.
+///
+/// The path variable (see [`crate::Edit::with_path_var`]) is used only if
+/// there is a single file.  Multiple files are always passed as positional
+/// parameters.
+fn shell_command_line(
+    edit: &crate::Edit,
+    editor: &OsStr,
+    paths: &[&std::path::Path],
+) -> (OsString, Vec<OsString>) {
+    if edit.no_shell {
+        return direct_command_line(edit, editor, paths);
+    }
+    let editor_args = editor_args(edit, editor);
+    let reference = match path_variable(edit, paths) {
+        None => OsString::from(" \"$@\""),
+        Some(var) => {
+            // Refer to the arguments individually so that the variable can be
+            // placed between them.
+            let mut reference = OsString::new();
+            let refs = |reference: &mut OsString, range| {
+                for n in range {
+                    reference.push(format!(" \"${{{}}}\"", n));
+                }
+            };
+            let leading = edit.leading_args.len() + editor_args.len();
+            refs(&mut reference, 1..leading + 1);
+            reference.push(" \"$");
+            reference.push(var);
+            reference.push("\"");
+            refs(
+                &mut reference,
+                leading + 1..leading + edit.trailing_args.len() + 1,
+            );
+            reference
+        }
+    };
+
+    let bytes = crate::shell::to_bytes(editor);
+    let command = match (edit.path_position, crate::shell::split_first(&bytes))
+    {
+        (crate::PathPosition::First, Some((program, rest))) => {
+            let mut command = crate::shell::from_bytes(program.to_vec());
+            command.push(reference);
+            command.push(crate::shell::from_bytes(rest.to_vec()));
+            command
+        }
+        _ => concat_os_str(editor, &reference),
+    };
+
+    let mut args = vec!["-c".into(), command, "sh".into()];
+    args.extend(edit.leading_args.iter().cloned());
+    args.extend(editor_args.iter().cloned());
+    if path_variable(edit, paths).is_none() {
+        args.extend(paths.iter().map(|path| path.as_os_str().to_owned()));
+    }
+    args.extend(edit.trailing_args.iter().cloned());
+    let shell = edit.shell.unwrap_or(OsStr::new("/bin/sh"));
+    (shell.into(), args)
+}
+
+/// Constructs program and its arguments which run editor on given files without
+/// a shell; see [`crate::Edit::no_shell`].
+///
+/// The editor command is split into words with its first word becoming the
+/// program.  Leading, editor-specific and trailing arguments and the paths are
+/// placed after the rest of the words or, if [`crate::Edit::path_position`]
+/// says so, right after the program.
+fn direct_command_line(
+    edit: &crate::Edit,
+    editor: &OsStr,
+    paths: &[&std::path::Path],
+) -> (OsString, Vec<OsString>) {
+    let words = crate::shell::split(&crate::shell::to_bytes(editor));
+    let mut words =
+        words.unwrap_or_default().into_iter().map(crate::shell::from_bytes);
+    let program = words.next().unwrap_or_else(|| editor.to_owned());
+    let mut args = Vec::new();
+    if edit.path_position == crate::PathPosition::Last {
+        args.extend(words.by_ref());
+    }
+    args.extend(edit.leading_args.iter().cloned());
+    args.extend(editor_args(edit, editor).iter().cloned());
+    args.extend(paths.iter().map(|path| path.as_os_str().to_owned()));
+    args.extend(edit.trailing_args.iter().cloned());
+    args.extend(words);
+    (program, args)
+}
+
+/// Returns arguments specific to the program of given editor command; see
+/// [`crate::Edit::with_editor_args`].
+fn editor_args<'e>(edit: &'e crate::Edit, editor: &OsStr) -> &'e [OsString] {
+    if edit.editor_args.is_empty() {
+        return &[];
+    }
+    let program = match crate::shell::program(editor) {
+        Some(program) => program,
+        None => return &[],
+    };
+    let name =
+        std::path::Path::new(&program).file_name().and_then(OsStr::to_str);
+    let name = match name {
+        Some(name) => crate::profile::normalize(name),
+        None => return &[],
+    };
+    edit.editor_args
+        .iter()
+        .find(|entry| entry.0 == name)
+        .map_or(&[], |entry| entry.1.as_slice())
+}
+
+/// Returns name of environment variable to pass path of the file in or `None`
+/// if paths are passed as positional parameters.
+fn path_variable<'a>(
+    edit: &crate::Edit<'a>,
+    paths: &[&std::path::Path],
+) -> Option<&'a OsStr> {
+    edit.path_variable.filter(|_| paths.len() == 1 && !edit.no_shell)
+}
+
+/// Constructs process which runs editor on given files.
+///
+/// This is [`command_line`] with environment set up as necessary.  If `shell`
+/// is given, it’s used in place of the shell.  It’s split into words so that
+/// it may include arguments, e.g. `busybox sh`.
+fn command(
+    edit: &crate::Edit,
+    editor: &OsStr,
+    paths: &[&std::path::Path],
+    shell: Option<&OsStr>,
+) -> std::process::Command {
+    let (mut program, mut args) = shell_command_line(edit, editor, paths);
+    if let Some(shell) = shell.filter(|_| !edit.no_shell) {
+        let bytes = crate::shell::to_bytes(shell);
+        let words =
+            crate::shell::split(&bytes).unwrap_or_else(|| vec![bytes.to_vec()]);
+        let mut words = words.into_iter().map(crate::shell::from_bytes);
+        if let Some(first) = words.next() {
+            program = first;
+            args.splice(0..0, words);
+        }
+    }
+    let (program, args) = with_launcher(edit, (program, args));
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(args);
+    #[cfg(unix)]
+    if let Some(arg0) = edit.arg0.filter(|_| edit.no_shell) {
+        if edit.launcher.is_empty() {
+            std::os::unix::process::CommandExt::arg0(&mut cmd, arg0);
+        }
+    }
+    if let Some(var) = path_variable(edit, paths) {
+        cmd.env(var, paths[0]);
+    }
+    if let Some(label) = edit.label {
+        cmd.env("RUN_EDITOR_LABEL", label);
+    }
+    #[cfg(unix)]
+    if edit.use_dev_tty {
+        if let Some((input, output)) = open_dev_tty() {
+            cmd.stdin(input).stdout(output);
+        }
+    }
+    cmd
+}
+
+/// Opens `/dev/tty` for reading and writing; see [`crate::Edit::use_dev_tty`].
+///
+/// Returns `None` if the file cannot be opened.
+#[cfg(unix)]
+fn open_dev_tty() -> Option<(std::fs::File, std::fs::File)> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .ok()?;
+    Some((file.try_clone().ok()?, file))
+}
+
+
+/// Runs user’s preferred editor to edit data held in memory; see
+/// [`crate::Edit::buffer`].
+///
+/// The temporary file is created in `tempdir`.  If `perms` is given, they are
+/// applied to the temporary file before the editor is started; see
+/// [`crate::Edit::buffer_like`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_buffer(
+    edit: &crate::Edit,
+    editor: OsString,
+    mut buf: Vec<u8>,
+    tempdir: std::path::PathBuf,
+    perms: Option<std::fs::Permissions>,
+) -> Result<Vec<u8>, error::Error> {
+    use std::io::{Read, Write};
+
+    buf = start_buffer(edit, buf)?;
+
+    let write = |file: &mut std::fs::File| file.write_all(buf.as_slice());
+    let (path, mut file) = edit_temp(edit, editor, tempdir, write, perms)?;
+    buf.clear();
+    file.read_to_end(&mut buf).with_path_ctx(&*path)?;
+    buf = finish_buffer(edit, buf)?;
+    path.release();
+    Ok(buf)
+}
+
+/// Runs user’s preferred editor on a kept temporary file and reads the result;
+/// see [`crate::Edit::resume`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn resume(
+    edit: &crate::Edit,
+    editor: Option<OsString>,
+    path: &std::path::Path,
+) -> Result<Vec<u8>, error::Error> {
+    if !std::fs::metadata(path).with_path_ctx(path)?.is_file() {
+        let error = std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "not a regular file",
+        );
+        return Err(error.with_path_ctx(path));
+    }
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path).with_path_ctx(path)?;
+    if let Some(editor) = editor {
+        if edit.feed_stdin && !replaces_editor(edit) {
+            edit_file_fed(edit, editor.clone(), path)?;
+        } else {
+            edit_file(edit, editor.clone(), path)?;
+        }
+        if !edit.post_command.is_empty() {
+            validate_file(edit, editor, path)?;
+        }
+        file = read_back(edit, file, path).with_path_ctx(path)?.0;
+    }
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).with_path_ctx(path)?;
+    let buf = finish_buffer(edit, buf)?;
+    if !edit.debug_keep_temps {
+        std::fs::remove_file(path).with_path_ctx(path)?;
+    }
+    Ok(buf)
+}
+
+/// Runs user’s preferred editor on several named buffers at once; see
+/// [`crate::Edit::buffers_named`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_buffers_named(
+    edit: &crate::Edit,
+    editor: OsString,
+    bufs: std::collections::BTreeMap<String, Vec<u8>>,
+) -> Result<std::collections::BTreeMap<String, Vec<u8>>, error::Error> {
+    let tempdir = temp_dir(edit);
+    let dir = tempfile::TempDir::new_in(&tempdir).with_path_ctx(tempdir)?;
+    let result = edit_buffers_in(edit, editor, dir.path(), bufs);
+    if edit.debug_keep_temps || (edit.keep_on_error && result.is_err()) {
+        // TempDir::keep, which replaces into_path, requires tempfile 3.20.
+        #[allow(deprecated)]
+        let _ = dir.into_path();
+    }
+    result
+}
+
+/// Writes named buffers into files in `dir` and runs the editor on them; see
+/// [`edit_buffers_named`].
+#[cfg(feature = "with_tempfile")]
+fn edit_buffers_in(
+    edit: &crate::Edit,
+    editor: OsString,
+    dir: &std::path::Path,
+    bufs: std::collections::BTreeMap<String, Vec<u8>>,
+) -> Result<std::collections::BTreeMap<String, Vec<u8>>, error::Error> {
+    let mut used = std::collections::HashSet::new();
+    let mut files = Vec::with_capacity(bufs.len());
+    for (name, mut buf) in bufs {
+        buf = start_buffer(edit, buf)?;
+        let path = dir.join(unique_file_name(&name, &mut used));
+        std::fs::write(&path, buf).with_path_ctx(&path)?;
+        files.push((name, path));
+    }
+
+    let paths =
+        files.iter().map(|(_, path)| path.as_path()).collect::<Vec<_>>();
+    edit_files(edit, editor, &paths)?;
+
+    files
+        .into_iter()
+        .map(|(name, path)| {
+            let buf = std::fs::read(&path).with_path_ctx(&path)?;
+            Ok((name, finish_buffer(edit, buf)?))
+        })
+        .collect()
+}
+
+/// Converts buffer name into a file name distinct from those in `used`.
+///
+/// Characters which are not allowed in file names (on any popular system) and
+/// control characters are replaced by underscores.  If the name is already
+/// used, a number is appended to its stem, e.g. `config-2.toml`.
+#[cfg(feature = "with_tempfile")]
+fn unique_file_name(
+    name: &str,
+    used: &mut std::collections::HashSet<String>,
+) -> String {
+    let is_invalid = |ch: char| {
+        ch.is_control() ||
+            matches!(ch, '/' | '\\' | ':' | '*' | '?' | '"') ||
+            matches!(ch, '<' | '>' | '|')
+    };
+    let mut name = name.replace(is_invalid, "_");
+    if name.trim_matches('.').is_empty() {
+        name.insert(0, '_');
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name.as_str(), ""),
+    };
+    let mut candidate = name.clone();
+    let mut n = 1;
+    while used.contains(&candidate.to_lowercase()) {
+        n += 1;
+        candidate = format!("{}-{}{}", stem, n, ext);
+    }
+    used.insert(candidate.to_lowercase());
+    candidate
+}
+
+/// Runs user’s preferred pager on data held in memory; see
+/// [`crate::Page::buffer`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn page_buffer(
+    pager: OsString,
+    buf: &[u8],
+) -> Result<(), error::Error> {
+    use std::io::Write;
+
+    let edit = crate::edit();
+    let mut temp = new_temp_file(&edit, std::env::temp_dir())?;
+    temp.as_file_mut().write_all(buf).with_path_ctx(temp.path())?;
+    let path = TempFile::new(&edit, temp);
+    edit_file(&edit, pager, &path)?;
+    path.release();
+    Ok(())
+}
+
+/// Runs user’s preferred editor on data held in memory and returns what it
+/// wrote to standard output; see [`crate::Edit::buffer_capture_stdout`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_buffer_capture(
+    edit: &crate::Edit,
+    editor: OsString,
+    mut buf: Vec<u8>,
+) -> Result<Vec<u8>, error::Error> {
+    use std::io::Write;
+
+    buf = start_buffer(edit, buf)?;
+
+    let mut temp = new_temp_file(edit, temp_dir(edit))?;
+    temp.as_file_mut().write_all(buf.as_slice()).with_path_ctx(temp.path())?;
+    let path = TempFile::new(edit, temp);
+    buf = capture_stdout(edit, editor, &path)?;
+    buf = finish_buffer(edit, buf)?;
+    path.release();
+    Ok(buf)
+}
+
+/// Runs user’s preferred editor on data held in memory and copies the result
+/// into a writer; see [`crate::Edit::buffer_to`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_buffer_to(
+    edit: &crate::Edit,
+    editor: OsString,
+    mut buf: Vec<u8>,
+    mut out: impl std::io::Write,
+) -> Result<(), error::Error> {
+    use std::io::{BufRead, Write};
+
+    buf = start_buffer(edit, buf)?;
+
+    let write = |file: &mut std::fs::File| file.write_all(buf.as_slice());
+    let tempdir = temp_dir(edit);
+    let (path, file) = edit_temp(edit, editor, tempdir, write, None)?;
+    drop(buf);
+
+    // Don’t use std::io::copy so that we can tell whether reading or writing
+    // failed.
+    let mut rd = std::io::BufReader::new(file);
+    loop {
+        let chunk = rd.fill_buf().with_path_ctx(&*path)?;
+        if chunk.is_empty() {
+            break;
+        }
+        let len = chunk.len();
+        out.write_all(chunk).map_err(output_error)?;
+        rd.consume(len);
+    }
+    out.flush().map_err(output_error)?;
+    path.release();
+    Ok(())
+}
+
+/// Writes buffer into output writer; see [`crate::Edit::buffer_to`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn write_output(
+    mut out: impl std::io::Write,
+    buf: &[u8],
+) -> Result<(), error::Error> {
+    out.write_all(buf).and_then(|()| out.flush()).map_err(output_error)
+}
+
+/// Converts error writing into output writer into [`error::Error`].
+#[cfg(feature = "with_tempfile")]
+fn output_error(error: std::io::Error) -> error::Error {
+    error::Error(error::Inner::OutputError { error })
+}
+
+/// Runs user’s preferred editor to edit lines of text; see
+/// [`crate::Edit::buffer_from_lines`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_lines(
+    edit: &crate::Edit,
+    editor: OsString,
+    lines: impl Iterator<Item = String>,
+) -> Result<Vec<String>, error::Error> {
+    use std::io::{Read, Write};
+
+    let (path, mut file) = edit_temp(
+        edit,
+        editor,
+        temp_dir(edit),
+        |file| {
+            let mut wr = std::io::BufWriter::new(file);
+            for line in lines {
+                wr.write_all(line.as_bytes())?;
+                wr.write_all(b"\n")?;
+            }
+            wr.flush()
+        },
+        None,
+    )?;
+    let mut text = String::new();
+    file.read_to_string(&mut text).with_path_ctx(&*path)?;
+    path.release();
+    Ok(split_lines(&text).map(String::from).collect())
+}
+
+/// Splits text into lines with line terminators removed.
+///
+/// Recognised terminators are `"\n"`, `"\r\n"` and a lone `"\r"` (used by
+/// classic Mac OS).  Terminator at the end of the text doesn’t result in an
+/// additional empty line.
+#[cfg(feature = "with_tempfile")]
+fn split_lines(mut text: &str) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        if text.is_empty() {
+            return None;
+        }
+        let (line, rest) = match text.find(['\n', '\r']) {
+            None => (text, ""),
+            Some(pos) => {
+                let len = if text[pos..].starts_with("\r\n") { 2 } else { 1 };
+                (&text[..pos], &text[pos + len..])
+            }
+        };
+        text = rest;
+        Some(line)
+    })
+}
+
+/// Creates a temporary file with data written by `write` and lets user edit
+/// it.
+///
+/// The file is created in `tempdir`.  Returns path to the temporary file and
+/// the file opened for reading after the editor terminated.  If `perms` is
+/// given, they are applied to the temporary file before the editor is started.
+#[cfg(feature = "with_tempfile")]
+fn edit_temp(
+    edit: &crate::Edit,
+    editor: OsString,
+    tempdir: std::path::PathBuf,
+    write: impl FnOnce(&mut std::fs::File) -> std::io::Result<()>,
+    perms: Option<std::fs::Permissions>,
+) -> Result<(TempFile, std::fs::File), error::Error> {
+    let mut temp = new_temp_file(edit, tempdir)?;
+    write(temp.as_file_mut()).with_path_ctx(temp.path())?;
+    if let Some(perms) = perms {
+        temp.as_file().set_permissions(perms).with_path_ctx(temp.path())?;
+    }
+    let file = temp.as_file().try_clone().with_path_ctx(temp.path())?;
+    let path = TempFile::new(edit, temp);
+
+    if edit.feed_stdin && !replaces_editor(edit) {
+        edit_file_fed(edit, editor.clone(), &path)?;
+    } else {
+        edit_file(edit, editor.clone(), &path)?;
+    }
+    if !edit.post_command.is_empty() {
+        validate_file(edit, editor, &path)?;
+    }
+
+    let (file, _) = read_back(edit, file, &path).with_path_ctx(&*path)?;
+    Ok((path, file))
+}
+
+/// Runs user’s preferred editor on given file writing its contents to the
+/// editor’s standard input; see [`crate::Edit::feed_stdin`].
+#[cfg(feature = "with_tempfile")]
+fn edit_file_fed(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    use std::io::Write;
+
+    let data = std::fs::read(path).with_path_ctx(path)?;
+    with_hooks(edit, &[path], || {
+        let mut child = spawn(edit, &editor, &[path], |cmd| {
+            cmd.stdin(std::process::Stdio::piped());
+        })?;
+        // Write from a separate thread so that an editor which doesn’t read
+        // its input (or reads only part of it) doesn’t block us.  Errors (most
+        // notably broken pipe) are ignored for the same reason.
+        let stdin = child.stdin.take();
+        let writer = std::thread::spawn(move || {
+            if let Some(mut stdin) = stdin {
+                let _ = stdin.write_all(&data);
+            }
+        });
+        let res = finish(edit, editor, child);
+        let _ = writer.join();
+        res
+    })
+}
+
+/// Runs validator on edited file rerunning the editor until it passes; see
+/// [`crate::Edit::with_post_command`].
+#[cfg(feature = "with_tempfile")]
+fn validate_file(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    let mut attempts = edit.max_attempts.max(1);
+    loop {
+        let output = std::process::Command::new(&edit.post_command[0])
+            .args(&edit.post_command[1..])
+            .arg(path)
+            .stdin(std::process::Stdio::null())
+            .output()
+            .map_err(cmd_error)?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let mut message = String::from_utf8_lossy(&output.stderr).into_owned();
+        if message.trim().is_empty() {
+            let program = std::path::Path::new(&edit.post_command[0]);
+            message = format!("{}: {}", program.display(), output.status);
+        }
+        if attempts <= 1 {
+            let message = message.trim_end().to_owned();
+            return Err(error::Error(error::Inner::Invalid { message }));
+        }
+        attempts -= 1;
+
+        let header = message
+            .trim_end()
+            .lines()
+            .map(|line| format!("# {}\n", line))
+            .collect::<Vec<_>>();
+        let mut data = header.concat().into_bytes();
+        data.extend(std::fs::read(path).with_path_ctx(path)?);
+        std::fs::write(path, &data).with_path_ctx(path)?;
+        std::thread::sleep(edit.retry_delay);
+        if edit.feed_stdin && !replaces_editor(edit) {
+            edit_file_fed(edit, editor.clone(), path)?;
+        } else {
+            edit_file(edit, editor.clone(), path)?;
+        }
+
+        // Strip header lines the user left in place.  Lines are removed one at
+        // a time so that it works even if user deleted some of them.
+        let data = std::fs::read(path).with_path_ctx(path)?;
+        let mut rest = data.as_slice();
+        while let Some(tail) =
+            header.iter().find_map(|line| rest.strip_prefix(line.as_bytes()))
+        {
+            rest = tail;
+        }
+        if rest.len() != data.len() {
+            std::fs::write(path, rest).with_path_ctx(path)?;
+        }
+    }
+}
+
+/// Returns file for reading contents of edited temporary file.
+///
+/// `file` is the temporary file as opened before editing.  The second element
+/// of returned tuple is whether `file` has been reused.
+///
+/// Usually we need to reopen the file (rather than using file.rewind() because
+/// an editor might have replaced the dentry.  This usually happens because
+/// editors implement atomic write which makes the file descriptor we have
+/// point to now deleted file.  On Unix, if the path still refers to the same
+/// inode, the editor modified the file in place and it’s enough to rewind it.
+#[cfg(feature = "with_tempfile")]
+fn read_back(
+    edit: &crate::Edit,
+    mut file: std::fs::File,
+    path: &std::path::Path,
+) -> std::io::Result<(std::fs::File, bool)> {
+    #[cfg(unix)]
+    {
+        use std::io::Seek;
+        use std::os::unix::fs::MetadataExt;
+
+        let same = |a: std::fs::Metadata, b: std::fs::Metadata| {
+            a.dev() == b.dev() && a.ino() == b.ino()
+        };
+        if let (Ok(a), Ok(b)) = (file.metadata(), std::fs::metadata(path)) {
+            if same(a, b) {
+                file.rewind()?;
+                return Ok((file, true));
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = file;
+    reopen(edit, path).map(|file| (file, false))
+}
+
+/// Converts error returned by a buffer filter into [`error::Error`].
 #[cfg(feature = "with_tempfile")]
-use crate::error::WithPathContext;
+fn filter_error(error: std::io::Error) -> error::Error {
+    error::Error(error::Inner::FilterError { error })
+}
 
+/// Prepares buffer for editing by passing it through pre filters; see
+/// [`crate::Edit::with_pre_filter`].
+#[cfg(feature = "with_tempfile")]
+fn start_buffer(
+    edit: &crate::Edit,
+    mut buf: Vec<u8>,
+) -> Result<Vec<u8>, error::Error> {
+    for filter in edit.pre_filters.iter() {
+        buf = filter(buf).map_err(filter_error)?;
+    }
+    Ok(buf)
+}
 
-/// Runs user’s preferred editor on given file; see [`crate::Edit::file`].
-pub(super) fn edit_file(
-    editor: OsString,
+/// Processes data read back after editing by stripping byte order mark and
+/// passing it through post filters; see [`crate::Edit::strip_bom`] and
+/// [`crate::Edit::with_post_filter`].
+#[cfg(feature = "with_tempfile")]
+fn finish_buffer(
+    edit: &crate::Edit,
+    mut buf: Vec<u8>,
+) -> Result<Vec<u8>, error::Error> {
+    if edit.strip_bom && buf.starts_with(b"\xEF\xBB\xBF") {
+        buf.drain(..3);
+    }
+    for filter in edit.post_filters.iter().rev() {
+        buf = filter(buf).map_err(filter_error)?;
+    }
+    Ok(buf)
+}
+
+/// Opens file after editor finished editing it; see
+/// [`crate::Edit::reopen_retry`].
+///
+/// If opening fails with `NotFound` error, retries the operation as configured
+/// in `edit`.
+#[cfg(feature = "with_tempfile")]
+fn reopen(
+    edit: &crate::Edit,
     path: &std::path::Path,
-) -> Result<(), error::Error> {
-    let command =
-        concat_os_str(editor.as_os_str(), OsStr::new(" \"$TMP_file_path\""));
-    std::process::Command::new("/bin/sh")
-        .arg("-c")
-        .arg(command)
-        .env("TMP_file_path", path)
-        .status()
-        .map_err(|error| error::Inner::CmdError { error })
-        .and_then(|status| {
-            if status.success() {
-                Ok(())
-            } else {
-                Err(error::Inner::EditorError { editor, status })
+) -> std::io::Result<std::fs::File> {
+    let mut attempts = edit.reopen_attempts.max(1);
+    loop {
+        match std::fs::File::open(path) {
+            Err(err)
+                if attempts > 1 &&
+                    err.kind() == std::io::ErrorKind::NotFound =>
+            {
+                attempts -= 1;
+                std::thread::sleep(edit.reopen_delay);
             }
-        })
-        .map_err(|inner| error::Error(inner).into())
+            res => return res,
+        }
+    }
 }
 
 
-/// Runs user’s preferred editor to edit data held in memory; see
-/// [`crate::Edit::buffer`].
+/// Runs user’s preferred editor on a buffer until `check` accepts the result.
+///
+/// If `check` fails, the editor is rerun on the rejected content.  This is
+/// repeated at most `edit.max_attempts` times after which error returned by
+/// `check` is converted into [`error::Inner::Invalid`].
 #[cfg(feature = "with_tempfile")]
-pub(super) fn edit_buffer(
-    editor: OsString,
+pub(super) fn edit_buffer_until<T, E: std::fmt::Display>(
+    edit: &crate::Edit,
     mut buf: Vec<u8>,
-) -> Result<Vec<u8>, error::Error> {
-    use std::io::{Read, Write};
+    mut check: impl FnMut(&[u8]) -> Result<T, E>,
+) -> Result<T, error::Error> {
+    let mut attempts = edit.max_attempts.max(1);
+    loop {
+        buf = edit.buffer(buf)?;
+        match check(buf.as_slice()) {
+            Ok(value) => return Ok(value),
+            Err(err) if attempts <= 1 => {
+                let message = err.to_string();
+                return Err(error::Error(error::Inner::Invalid { message }));
+            }
+            Err(_) => attempts -= 1,
+        }
+        std::thread::sleep(edit.retry_delay);
+    }
+}
 
-    let mut temp = new_temp_file(std::env::temp_dir())?;
-    temp.as_file_mut().write_all(buf.as_slice()).with_path_ctx(temp.path())?;
-    let path = temp.into_temp_path();
+/// Runs user’s preferred editor on a string until they save valid UTF-8; see
+/// [`crate::Edit::buffer_utf8`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_buffer_utf8(
+    edit: &crate::Edit,
+    buf: String,
+) -> Result<String, error::Error> {
+    let mut attempts = edit.max_attempts.max(1);
+    let mut buf = edit.buffer(buf.into_bytes())?;
+    loop {
+        let err = match String::from_utf8(buf) {
+            Ok(buf) => return Ok(buf),
+            Err(err) => err,
+        };
+        let message = err.utf8_error().to_string();
+        if attempts <= 1 {
+            return Err(error::Error(error::Inner::Invalid { message }));
+        }
+        attempts -= 1;
+        let header =
+            format!("# error: {}; delete this line when fixed\n", message);
+        let mut seed = header.clone().into_bytes();
+        seed.extend_from_slice(err.as_bytes());
+        std::thread::sleep(edit.retry_delay);
+        buf = edit.buffer(seed)?;
+        if buf.starts_with(header.as_bytes()) {
+            buf.drain(..header.len());
+        }
+    }
+}
 
-    edit_file(editor, &path)?;
+/// Returns whether buffer is empty or consists of ASCII white space only.
+#[cfg(feature = "with_tempfile")]
+pub(super) fn is_blank(buf: &[u8]) -> bool {
+    buf.iter().all(u8::is_ascii_whitespace)
+}
 
-    // We need to reopen the file (rather than using file.rewind() because an
-    // editor might have replaced the dentry.  This usually happens because
-    // editors implement atomic write which makes the file descriptor we have
-    // point to now deleted file.
 
-    (|| {
-        let mut file = std::fs::File::open(&path)?;
-        buf.clear();
-        file.read_to_end(&mut buf)
-    })()
-    .with_path_ctx(&*path)?;
+/// Calculates 64-bit FNV-1a hash of given data; see
+/// [`crate::Edit::buffer_hashed`] and [`crate::Edit::files_changed`].
+pub(super) fn fnv1a(buf: &[u8]) -> u64 {
+    buf.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
 
-    Ok(buf)
+
+/// Calculates hash of contents of given file or returns `None` if it doesn’t
+/// exist; see [`crate::Edit::files_changed`].
+pub(super) fn file_hash(
+    path: &std::path::Path,
+) -> Result<Option<u64>, error::Error> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(Some(fnv1a(&data))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.with_path_ctx(path)),
+    }
 }
 
+/// Returns directory to create temporary files holding edited buffers in; see
+/// [`crate::Edit::with_tempdir_fn`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn temp_dir(edit: &crate::Edit) -> std::path::PathBuf {
+    match &edit.tempdir_fn {
+        Some(tempdir) => tempdir(),
+        None => std::env::temp_dir(),
+    }
+}
 
 /// Creates a new temporary file in a given directory.
 #[cfg(feature = "with_tempfile")]
 pub(super) fn new_temp_file(
+    edit: &crate::Edit,
     tempdir: std::path::PathBuf,
 ) -> Result<tempfile::NamedTempFile, error::Error> {
-    tempfile::NamedTempFile::new_in(&tempdir).with_path_ctx(tempdir)
+    tempfile::Builder::new()
+        .suffix(edit.suffix.as_deref().unwrap_or_default())
+        .tempfile_in(&tempdir)
+        .with_path_ctx(tempdir)
+}
+
+/// Path to a temporary file which is deleted when dropped unless configured
+/// otherwise.
+///
+/// If [`crate::Edit::debug_keep_temps`] is enabled, the file is not deleted.
+/// If [`crate::Edit::keep_on_error`] is enabled, the file is not deleted if
+/// it’s dropped without [`TempFile::release`] being called, i.e. when the
+/// operation using it failed.
+#[cfg(feature = "with_tempfile")]
+pub(super) struct TempFile {
+    path: Option<tempfile::TempPath>,
+    keep_on_drop: bool,
+    keep_on_release: bool,
+}
+
+#[cfg(feature = "with_tempfile")]
+impl TempFile {
+    /// Closes the temporary file and wraps its path.
+    pub(super) fn new(
+        edit: &crate::Edit,
+        temp: tempfile::NamedTempFile,
+    ) -> TempFile {
+        Self {
+            path: Some(temp.into_temp_path()),
+            keep_on_drop: edit.debug_keep_temps || edit.keep_on_error,
+            keep_on_release: edit.debug_keep_temps,
+        }
+    }
+
+    /// Disposes of the file after the operation using it succeeded.
+    pub(super) fn release(mut self) {
+        self.keep_on_drop = self.keep_on_release;
+    }
+
+    /// Keeps the file on disk and returns its path.
+    pub(super) fn keep(mut self) -> Result<std::path::PathBuf, error::Error> {
+        self.path.take().unwrap().keep().map_err(|err| {
+            let path = err.path.to_path_buf();
+            err.error.with_path_ctx(path)
+        })
+    }
+}
+
+#[cfg(feature = "with_tempfile")]
+impl std::ops::Deref for TempFile {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &std::path::Path { self.path.as_ref().unwrap() }
+}
+
+#[cfg(feature = "with_tempfile")]
+impl AsRef<std::path::Path> for TempFile {
+    fn as_ref(&self) -> &std::path::Path { self }
+}
+
+#[cfg(feature = "with_tempfile")]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.take() {
+            if self.keep_on_drop {
+                let _ = path.keep();
+            }
+        }
+    }
+}
+
+
+/// Runs user’s preferred editor on a copy of given file and atomically replaces
+/// the file with the result; see [`crate::Edit::atomic`].
+///
+/// If the file doesn’t exist, it’s edited in place.
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_file_atomic(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return edit_file(edit, editor, path);
+        }
+        Err(err) => return Err(err.with_path_ctx(path)),
+    };
+
+    let temp = copy_temp(edit, path, path, None)?;
+    copy_attributes(&metadata, &temp)?;
+    edit_file(edit, editor, &temp)?;
+    std::fs::File::open(&temp)
+        .and_then(|file| file.sync_all())
+        .with_path_ctx(&*temp)?;
+    // The editor might have replaced the temporary file so copy the attributes
+    // again.
+    copy_attributes(&metadata, &temp)?;
+    persist(temp, path)
+}
+
+/// Runs user’s preferred editor on a region of given file and splices the
+/// result back; see [`crate::Edit::file_region`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_file_region(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+    begin: &str,
+    end: &str,
+) -> Result<(), error::Error> {
+    use std::io::Write;
+
+    let metadata = std::fs::metadata(path).with_path_ctx(path)?;
+    let data = std::fs::read(path).with_path_ctx(path)?;
+    let region = find_region(&data, begin, end).map_err(|marker| {
+        let (path, marker) = (path.to_path_buf(), marker.to_owned());
+        error::Error(error::Inner::MissingMarker { path, marker })
+    })?;
+
+    let dir = parent_dir(path)?;
+    let old = data[region.clone()].to_vec();
+    let perms = Some(metadata.permissions());
+    let mut new = edit_buffer(edit, editor, old.clone(), dir.clone(), perms)?;
+    if new == old {
+        return Ok(());
+    }
+    if !new.is_empty() && !new.ends_with(b"\n") {
+        new.push(b'\n');
+    }
+
+    let mut temp = new_temp_file(edit, dir)?;
+    let file = temp.as_file_mut();
+    file.write_all(&data[..region.start])
+        .and_then(|()| file.write_all(&new))
+        .and_then(|()| file.write_all(&data[region.end..]))
+        .and_then(|()| file.sync_all())
+        .with_path_ctx(temp.path())?;
+    let temp = TempFile::new(edit, temp);
+    copy_attributes(&metadata, &temp)?;
+    persist(temp, path)
+}
+
+/// Finds byte range of lines between `begin` and `end` marker lines.
+///
+/// Returns the marker which couldn’t be found on failure.
+#[cfg(feature = "with_tempfile")]
+fn find_region<'m>(
+    data: &[u8],
+    begin: &'m str,
+    end: &'m str,
+) -> Result<std::ops::Range<usize>, &'m str> {
+    let is_marker = |line: &[u8], marker: &str| {
+        let len = line.iter().rposition(|ch| !ch.is_ascii_whitespace());
+        &line[..len.map_or(0, |len| len + 1)] == marker.as_bytes()
+    };
+    let mut offset = 0;
+    let mut start = None;
+    for line in data.split_inclusive(|&ch| ch == b'\n') {
+        match start {
+            None if is_marker(line, begin) => start = Some(offset + line.len()),
+            Some(start) if is_marker(line, end) => return Ok(start..offset),
+            _ => (),
+        }
+        offset += line.len();
+    }
+    Err(if start.is_some() { end } else { begin })
+}
+
+/// Runs user’s preferred editor on a copy of given file and replaces the file
+/// with the result rotating numbered backups; see
+/// [`crate::Edit::file_with_rotation`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_file_rotated(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+    keep: usize,
+) -> Result<(), error::Error> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return edit_file(edit, editor, path);
+        }
+        Err(err) => return Err(err.with_path_ctx(path)),
+    };
+
+    let temp = copy_temp(edit, path, path, None)?;
+    copy_attributes(&metadata, &temp)?;
+    edit_file(edit, editor, &temp)?;
+    if same_contents(path, &temp)? {
+        temp.release();
+        return Ok(());
+    }
+    std::fs::File::open(&temp)
+        .and_then(|file| file.sync_all())
+        .with_path_ctx(&*temp)?;
+    copy_attributes(&metadata, &temp)?;
+
+    if keep > 0 {
+        let backup = |n: usize| {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(format!(".{}", n));
+            std::path::PathBuf::from(backup)
+        };
+        let oldest = backup(keep);
+        match std::fs::remove_file(&oldest) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                return Err(err.with_path_ctx(oldest));
+            }
+            _ => (),
+        }
+        for n in (1..keep).rev() {
+            let src = backup(n);
+            match std::fs::rename(&src, backup(n + 1)) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(err.with_path_ctx(src));
+                }
+                _ => (),
+            }
+        }
+        let dst = backup(1);
+        std::fs::copy(path, &dst).with_path_ctx(dst)?;
+    }
+    persist(temp, path)
+}
+
+/// Atomically appends data to a file creating it if it doesn’t exist; see
+/// [`crate::Edit::buffer_append_to`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn append_atomic(
+    edit: &crate::Edit,
+    data: &[u8],
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    use std::io::Write;
+
+    let (temp, metadata) = match std::fs::metadata(path) {
+        Ok(metadata) => (copy_temp(edit, path, path, None)?, Some(metadata)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let temp = new_temp_file(edit, parent_dir(path)?)?;
+            (TempFile::new(edit, temp), None)
+        }
+        Err(err) => return Err(err.with_path_ctx(path)),
+    };
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&temp)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .with_path_ctx(&*temp)?;
+    if let Some(metadata) = metadata {
+        copy_attributes(&metadata, &temp)?;
+    }
+    persist(temp, path)
+}
+
+/// Runs user’s preferred editor on a copy of given file and replaces the file
+/// with the result unless it’s been modified in the meantime; see
+/// [`crate::Edit::file_guarded`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_file_guarded(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    let stamp = |metadata: &std::fs::Metadata| {
+        metadata.modified().map(|mtime| (mtime, metadata.len()))
+    };
+    let metadata = std::fs::metadata(path).with_path_ctx(path)?;
+    let before = stamp(&metadata).with_path_ctx(path)?;
+
+    let temp = copy_temp(edit, path, path, None)?;
+    copy_attributes(&metadata, &temp)?;
+    edit_file(edit, editor, &temp)?;
+
+    let after = std::fs::metadata(path).and_then(|metadata| stamp(&metadata));
+    if after.ok() != Some(before) {
+        let edited = temp.keep()?;
+        let path = path.to_path_buf();
+        return Err(error::Error(error::Inner::ConcurrentModification {
+            path,
+            edited,
+        }));
+    }
+    copy_attributes(&metadata, &temp)?;
+    persist(temp, path)
+}
+
+/// Runs user’s preferred editor on a copy of given file and, if the user
+/// changed it, replaces the file with the result; see
+/// [`crate::Edit::file_review`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_file_review(
+    edit: &crate::Edit,
+    editor: OsString,
+    path: &std::path::Path,
+) -> Result<Option<Vec<crate::DiffLine>>, error::Error> {
+    let metadata = std::fs::metadata(path).with_path_ctx(path)?;
+    let temp = copy_temp(edit, path, path, None)?;
+    copy_attributes(&metadata, &temp)?;
+    edit_file(edit, editor, &temp)?;
+
+    let old = std::fs::read(path).with_path_ctx(path)?;
+    let new = std::fs::read(&temp).with_path_ctx(&*temp)?;
+    if old == new {
+        temp.release();
+        return Ok(None);
+    }
+    let diff = crate::diff::diff_lines(&old, &new);
+    copy_attributes(&metadata, &temp)?;
+    persist(temp, path)?;
+    Ok(Some(diff))
+}
+
+/// Runs user’s preferred editor on contents of a file and atomically writes
+/// the result to another one; see [`crate::Edit::edit_as`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn edit_as(
+    edit: &crate::Edit,
+    editor: Option<OsString>,
+    src: &std::path::Path,
+    dst: &std::path::Path,
+) -> Result<(), error::Error> {
+    use std::io::Read;
+
+    let mut buf = Vec::new();
+    if src == std::path::Path::new("-") {
+        std::io::stdin().lock().read_to_end(&mut buf).map_err(cmd_error)?;
+    } else {
+        buf = std::fs::read(src).with_path_ctx(src)?;
+    }
+    if let Some(editor) = editor {
+        buf = edit_buffer(edit, editor, buf, temp_dir(edit), None)?;
+    }
+    write_atomic(edit, dst, &buf)
+}
+
+/// Runs filter over a file and atomically replaces it with the output; see
+/// [`crate::Edit::apply`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn apply(
+    edit: &crate::Edit,
+    path: &std::path::Path,
+    expr: &str,
+) -> Result<(), error::Error> {
+    let default = [OsString::from("sed"), OsString::from("-e")];
+    let filter = match edit.apply_filter.as_slice() {
+        [] => &default[..],
+        filter => filter,
+    };
+    let output = std::process::Command::new(&filter[0])
+        .args(&filter[1..])
+        .arg(expr)
+        .arg(path)
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(cmd_error)?;
+    if !output.status.success() {
+        let editor = filter[0].clone();
+        let status = output.status;
+        return Err(error::Error(error::Inner::EditorError { editor, status }));
+    }
+    write_atomic(edit, path, &output.stdout)
+}
+
+/// Atomically replaces contents of a file with given data.
+///
+/// If the file exists, its attributes are preserved and if it already holds
+/// the data, it’s not rewritten at all.
+#[cfg(feature = "with_tempfile")]
+pub(super) fn write_atomic(
+    edit: &crate::Edit,
+    dst: &std::path::Path,
+    buf: &[u8],
+) -> Result<(), error::Error> {
+    use std::io::Write;
+
+    let metadata = match std::fs::metadata(dst) {
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err.with_path_ctx(dst)),
+    };
+    if metadata.is_some() && std::fs::read(dst).with_path_ctx(dst)? == buf {
+        return Ok(());
+    }
+    let mut temp = new_temp_file(edit, parent_dir(dst)?)?;
+    temp.as_file_mut()
+        .write_all(buf)
+        .and_then(|()| temp.as_file().sync_all())
+        .with_path_ctx(temp.path())?;
+    let temp = TempFile::new(edit, temp);
+    match metadata {
+        Some(metadata) => copy_attributes(&metadata, &temp)?,
+        None => set_result_mode(edit, &temp, dst)?,
+    }
+    persist(temp, dst)
+}
+
+/// Applies permissions and, if possible, ownership described by `metadata` to
+/// a file.
+///
+/// Changing ownership on Unix requires privileges.  If it fails, the error is
+/// ignored.
+#[cfg(feature = "with_tempfile")]
+fn copy_attributes(
+    metadata: &std::fs::Metadata,
+    path: &std::path::Path,
+) -> Result<(), error::Error> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let (uid, gid) = (metadata.uid(), metadata.gid());
+        let _ = std::os::unix::fs::chown(path, Some(uid), Some(gid));
+    }
+    std::fs::set_permissions(path, metadata.permissions()).with_path_ctx(path)
 }
 
 
@@ -78,62 +1850,168 @@ pub(super) fn new_temp_file(
 ///
 /// Destination path is not touched in any way.  It’s only needed to determine
 /// its parent directory to put a temporary file in.
+///
+/// If `progress` is given, it’s called with number of bytes copied so far after
+/// each chunk; see [`crate::Edit::on_copy_progress`].
 #[cfg(feature = "with_tempfile")]
 pub(super) fn copy_temp(
+    edit: &crate::Edit,
     src: &std::path::Path,
     dst: &std::path::Path,
-) -> Result<tempfile::TempPath, error::Error> {
+    mut progress: Option<&mut dyn FnMut(u64)>,
+) -> Result<TempFile, error::Error> {
+    use std::io::{Read, Write};
+
     let mut input = std::fs::File::open(src).with_path_ctx(src)?;
+    let mut temp = new_temp_file(edit, parent_dir(dst)?)?;
+    let mut buf = vec![0; 64 * 1024];
+    let mut copied = 0;
+    loop {
+        let len = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {
+                continue
+            }
+            Err(err) => return Err(err.with_path_ctx(src)),
+        };
+        temp.as_file_mut().write_all(&buf[..len]).with_path_ctx(temp.path())?;
+        copied += len as u64;
+        if let Some(progress) = progress.as_mut() {
+            progress(copied);
+        }
+    }
+    Ok(TempFile::new(edit, temp))
+}
+
+
+/// Applies permissions configured with [`crate::Edit::with_result_mode`] to
+/// temporary file which is about to be persisted at `dst`.
+#[cfg(feature = "with_tempfile")]
+pub(super) fn set_result_mode(
+    edit: &crate::Edit,
+    path: &std::path::Path,
+    dst: &std::path::Path,
+) -> Result<(), error::Error> {
+    #[cfg(unix)]
+    if let Some((mode, always)) = edit.result_mode {
+        use std::os::unix::fs::PermissionsExt;
+        if always || !dst.try_exists().with_path_ctx(dst)? {
+            let perms = std::fs::Permissions::from_mode(mode);
+            std::fs::set_permissions(path, perms).with_path_ctx(path)?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (edit, path, dst);
+    Ok(())
+}
+
 
-    let dir = match dst.parent() {
+/// Returns directory containing given file.
+///
+/// Fails if the path has no parent (e.g. is root directory).
+#[cfg(feature = "with_tempfile")]
+pub(super) fn parent_dir(
+    path: &std::path::Path,
+) -> Result<std::path::PathBuf, error::Error> {
+    match path.parent() {
         None => {
-            let error = if cfg!(unix) {
-                const EISDIR: i32 = 21;
-                std::io::Error::from_raw_os_error(EISDIR)
-            } else {
-                std::io::Error::new(std::io::ErrorKind::Other, "is a directory")
-            };
-            return Err(error.with_path_ctx(dst));
+            let path = path.to_path_buf();
+            Err(error::Error(error::Inner::IsADirectory { path }))
         }
-        Some(path) if path == std::path::Path::new("") => {
-            std::env::current_dir().with_path_ctx(path)?
+        Some(dir) if dir == std::path::Path::new("") => {
+            std::env::current_dir().with_path_ctx(dir)
         }
-        Some(path) => path.to_path_buf(),
+        Some(dir) => Ok(dir.to_path_buf()),
+    }
+}
+
+
+/// Checks whether two files have the same contents.
+#[cfg(feature = "with_tempfile")]
+pub(super) fn same_contents(
+    a: &std::path::Path,
+    b: &std::path::Path,
+) -> Result<bool, error::Error> {
+    use std::io::Read;
+
+    let open = |path| {
+        std::fs::File::open(path)
+            .map(std::io::BufReader::new)
+            .with_path_ctx(path)
     };
+    let (mut a_bytes, mut b_bytes) = (open(a)?.bytes(), open(b)?.bytes());
+    loop {
+        match (a_bytes.next(), b_bytes.next()) {
+            (None, None) => return Ok(true),
+            (Some(x), Some(y)) => {
+                if x.with_path_ctx(a)? != y.with_path_ctx(b)? {
+                    return Ok(false);
+                }
+            }
+            (x, y) => {
+                x.transpose().with_path_ctx(a)?;
+                y.transpose().with_path_ctx(b)?;
+                return Ok(false);
+            }
+        }
+    }
+}
+
+
+/// Checks whether two buffers are the same; see
+/// [`crate::Edit::change_compare`].
+#[cfg(feature = "with_tempfile")]
+pub(super) fn same_buffers(
+    compare: crate::Compare,
+    a: &[u8],
+    b: &[u8],
+) -> bool {
+    match compare {
+        crate::Compare::Exact => a == b,
+        crate::Compare::IgnoreTrailingWs => {
+            trim_trailing_ws(a).eq(trim_trailing_ws(b))
+        }
+    }
+}
 
-    let mut temp = new_temp_file(dir)?;
-    // TODO(mina86): One issue here is that on error std::io::copy does not
-    // specify whether the failure happened when reading input or writing to
-    // output.  This mean that we cannot reliably specify whether issue was with
-    // sourec or destination file.  For now give destination file as context.
-    std::io::copy(&mut input, temp.as_file_mut()).with_path_ctx(temp.path())?;
-    Ok(temp.into_temp_path())
+/// Splits buffer into lines with trailing white space removed and empty lines
+/// at the end of the buffer dropped.
+#[cfg(feature = "with_tempfile")]
+fn trim_trailing_ws(buf: &[u8]) -> impl Iterator<Item = &[u8]> {
+    fn trim(line: &[u8]) -> &[u8] {
+        let len = line.iter().rposition(|ch| !ch.is_ascii_whitespace());
+        &line[..len.map_or(0, |len| len + 1)]
+    }
+    trim(buf).split(|&ch| ch == b'\n').map(trim)
 }
 
 
 /// Persist a temporary file into given destination location.
+///
+/// If that fails, the temporary file is disposed of as if the operation failed;
+/// see [`TempFile`].
 #[cfg(feature = "with_tempfile")]
 pub(super) fn persist(
-    path: tempfile::TempPath,
+    mut temp: TempFile,
     dst: &std::path::Path,
 ) -> Result<(), error::Error> {
-    path.persist(dst).map_err(|err| {
-        error::Error(error::Inner::PathError {
-            path: err.path.to_path_buf(),
-            error: err.error,
-        })
+    temp.path.take().unwrap().persist(dst).map_err(|err| {
+        let path = err.path.to_path_buf();
+        temp.path = Some(err.path);
+        err.error.with_path_ctx(path)
     })
 }
 
 
 /// Concatenates two [`OsStr`]s into a newly allocated [`OsString`].
 fn concat_os_str(x: &OsStr, y: &OsStr) -> OsString {
-    #[cfg(any(target_family = "unix", target_family = "wasi"))]
+    #[cfg(any(target_family = "unix", target_os = "wasi"))]
     {
         #[cfg(target_family = "unix")]
         use std::os::unix::ffi::{OsStrExt, OsStringExt};
-        #[cfg(target_family = "wasi")]
-        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+        #[cfg(target_os = "wasi")]
+        use std::os::wasi::ffi::{OsStrExt, OsStringExt};
         return OsString::from_vec([x.as_bytes(), y.as_bytes()].concat());
     }
     #[allow(unreachable_code)]
@@ -144,6 +2022,82 @@ fn concat_os_str(x: &OsStr, y: &OsStr) -> OsString {
     }
 }
 
+#[test]
+#[cfg(feature = "with_tempfile")]
+fn test_find_region() {
+    let data = b"a\n# BEGIN \nb\nc\n# END\nd";
+    assert_eq!(Ok(11..15), find_region(data, "# BEGIN", "# END"));
+    assert_eq!(b"b\nc\n", &data[11..15]);
+    assert_eq!(Ok(4..4), find_region(b"a\nB\nE\n", "B", "E"));
+    assert_eq!(Err("E"), find_region(b"a\nB\n", "B", "E"));
+    assert_eq!(Err("B"), find_region(b"a\nE\n", "B", "E"));
+    assert_eq!(Err("E"), find_region(b"a\nE\nB", "B", "E"));
+}
+
+#[test]
+#[cfg(feature = "with_tempfile")]
+fn test_split_lines() {
+    fn test(want: &[&str], text: &str) {
+        assert_eq!(want, split_lines(text).collect::<Vec<_>>().as_slice());
+    }
+
+    test(&[], "");
+    test(&["foo"], "foo");
+    test(&["foo", "bar"], "foo\nbar\n");
+    test(&["foo", "bar"], "foo\r\nbar");
+    test(&["foo", "bar", "baz"], "foo\rbar\rbaz\r");
+    test(&["foo", "", "bar"], "foo\r\rbar");
+    test(&["foo", "", "bar"], "foo\n\rbar");
+    test(&["", ""], "\n\n");
+}
+
+#[test]
+#[cfg(feature = "with_tempfile")]
+fn test_unique_file_name() {
+    let mut used = std::collections::HashSet::new();
+    let mut test = |want: &str, name: &str| {
+        assert_eq!(want, unique_file_name(name, &mut used));
+    };
+    test("config.toml", "config.toml");
+    test("config-2.toml", "config.toml");
+    test("Config-3.toml", "Config.toml");
+    test("a_b_c", "a/b\\c");
+    test("_..", "..");
+    test("_", "");
+    test(".bashrc", ".bashrc");
+    test(".bashrc-2", ".bashrc");
+}
+
+#[test]
+#[cfg(all(unix, feature = "with_tempfile"))]
+fn test_read_back() {
+    use std::io::{Read, Write};
+
+    let check = |command: &str, want_rewound: bool| {
+        let mut temp =
+            new_temp_file(&crate::edit(), std::env::temp_dir()).unwrap();
+        temp.write_all(b"foo").unwrap();
+        let file = temp.as_file().try_clone().unwrap();
+        let edit = crate::edit();
+        edit_file(&edit, command.into(), temp.path()).unwrap();
+
+        let (mut file, rewound) = read_back(&edit, file, temp.path()).unwrap();
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf).unwrap();
+        assert_eq!((&b"bar\n"[..], want_rewound), (buf.as_slice(), rewound));
+    };
+
+    check("f() { echo bar >\"$1\"; }; f", true);
+    check("f() { echo bar >\"$1.new\" && mv \"$1.new\" \"$1\"; }; f", false);
+}
+
+#[test]
+fn test_fnv1a() {
+    assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(b""));
+    assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(b"a"));
+    assert_eq!(0x8594_4171_f739_67e8, fnv1a(b"foobar"));
+}
+
 #[test]
 fn test_concat_os_str() {
     let got = concat_os_str(OsStr::new("foo"), OsStr::new("bar"));
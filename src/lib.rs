@@ -2,8 +2,13 @@
 
 use std::ffi::{OsStr, OsString};
 
+mod diff;
 mod error;
 mod imp;
+mod profile;
+#[cfg(feature = "test-util")]
+mod recording;
+mod shell;
 #[cfg(test)]
 mod tests;
 
@@ -26,7 +31,6 @@ mod tests;
 /// let buf = String::from("\n\n# Enter commit message above.");
 /// let buf = run_editor::edit().buffer(Vec::from(buf)).unwrap();
 /// ```
-#[derive(Default)]
 pub struct Edit<'a> {
     /// Name of an additional environment variable to read editor command from.
     editor_variable: Option<&'a OsStr>,
@@ -34,6 +38,400 @@ pub struct Edit<'a> {
     /// Command to use in preference to those determined by default methods of
     /// getting user preferences.
     editor_command: Option<&'a OsStr>,
+
+    /// Reader to read editor command from and the command once it’s been
+    /// read; see [`Edit::with_command_from_reader`].
+    command_reader: Option<std::cell::RefCell<CommandReader<'a>>>,
+
+    /// Maximum number of times the editor is run when edited content is
+    /// rejected by validation.
+    max_attempts: usize,
+
+    /// Delay before rerunning the editor when edited content is rejected by
+    /// validation.
+    retry_delay: std::time::Duration,
+
+    /// Number of times to try opening temporary file after editing.
+    reopen_attempts: usize,
+
+    /// Delay between attempts to open temporary file after editing.
+    reopen_delay: std::time::Duration,
+
+    /// Where to put path of the file to edit in the editor command line.
+    path_position: PathPosition,
+
+    /// Whether [`Edit::file`] locks the file while it’s being edited.
+    lock: bool,
+
+    /// Whether locking waits for the lock if it’s held by someone else.
+    lock_wait: bool,
+
+    /// Size above which [`Edit::file`] refuses to edit the file.
+    max_file_size: Option<u64>,
+
+    /// Whether [`Edit::file`] checks that the file is writable.
+    check_writable: bool,
+
+    /// Whether [`Edit::file`] creates missing parent directories of the file.
+    create_parents: bool,
+
+    /// Whether [`Edit::files`] removes duplicate paths.
+    dedup_paths: bool,
+
+    /// Name of environment variable to pass path of the file to edit in.
+    path_variable: Option<&'a OsStr>,
+
+    /// Description of what’s being edited exported to the editor.
+    label: Option<&'a OsStr>,
+
+    /// Arguments passed to the editor before the path.
+    leading_args: Vec<OsString>,
+
+    /// Arguments passed to particular editors after leading arguments keyed by
+    /// normalised name of the editor program.
+    editor_args: Vec<(String, Vec<OsString>)>,
+
+    /// Arguments passed to the editor after the path.
+    trailing_args: Vec<OsString>,
+
+    /// Program and arguments the shell running the editor is launched with.
+    launcher: Vec<OsString>,
+
+    /// Transformations applied to buffers before they are written to temporary
+    /// files, in order.
+    pre_filters: Vec<Filter<'a>>,
+
+    /// Transformations applied to buffers after they are read from temporary
+    /// files, in reverse order.
+    post_filters: Vec<Filter<'a>>,
+
+    /// Whether to connect editor’s standard input and output to `/dev/tty`.
+    #[cfg(unix)]
+    use_dev_tty: bool,
+
+    /// Whether [`Edit::file`] restores mode of the file changed by the editor.
+    #[cfg(unix)]
+    preserve_mode: bool,
+
+    /// Signals termination by which is treated as success.
+    #[cfg(unix)]
+    ok_signals: &'a [i32],
+
+    /// `argv[0]` of the editor executed without a shell.
+    #[cfg(unix)]
+    arg0: Option<&'a OsStr>,
+
+    /// Function deciding whether the editor terminated successfully.
+    success_predicate: Option<SuccessPredicate<'a>>,
+
+    /// Time after which the editor is killed.
+    timeout: Option<std::time::Duration>,
+
+    /// Time after which a function is called while the editor keeps running.
+    soft_timeout: Option<(std::time::Duration, SoftTimeout<'a>)>,
+
+    /// Whether quoting of the editor command is validated before running it.
+    strict_command: bool,
+
+    /// Whether to fall back to `vi` if no editor command is configured.
+    use_default_editor: bool,
+
+    /// Whether the editor is executed directly rather than through a shell.
+    no_shell: bool,
+
+    /// Whether running programs which cannot save files is an error.
+    warn_readonly_editor: bool,
+
+    /// Names of programs which cannot save files.  `None` means the built-in
+    /// list.
+    readonly_editors: Option<Vec<String>>,
+
+    /// Function rewriting or rejecting resolved editor command.
+    editor_filter: Option<EditorFilter<'a>>,
+
+    /// Whether [`Edit::file`] edits a copy of the file and then atomically
+    /// replaces the original.
+    #[cfg(feature = "with_tempfile")]
+    atomic: bool,
+
+    /// Permissions given to destination files of [`Edit::file_copy`] and
+    /// whether they are applied to existing files as well.
+    #[cfg(all(unix, feature = "with_tempfile"))]
+    result_mode: Option<(u32, bool)>,
+
+    /// Whether UTF-8 byte order mark is removed from edited buffers.
+    #[cfg(feature = "with_tempfile")]
+    strip_bom: bool,
+
+    /// Whether edited buffers are also written to the editor’s standard input.
+    #[cfg(feature = "with_tempfile")]
+    feed_stdin: bool,
+
+    /// Validator run on edited temporary files.  Empty if there’s none.
+    #[cfg(feature = "with_tempfile")]
+    post_command: Vec<OsString>,
+
+    /// Filter run by [`Edit::apply`].  Empty means `sed -e`.
+    #[cfg(feature = "with_tempfile")]
+    apply_filter: Vec<OsString>,
+
+    /// Function returning directory to create temporary files in.
+    #[cfg(feature = "with_tempfile")]
+    tempdir_fn: Option<TempdirFn<'a>>,
+
+    /// Whether temporary files are kept for debugging.
+    #[cfg(feature = "with_tempfile")]
+    debug_keep_temps: bool,
+
+    /// Whether temporary files are kept if editing fails.
+    #[cfg(feature = "with_tempfile")]
+    keep_on_error: bool,
+
+    /// How [`Edit::buffer_changed`] compares buffers.
+    #[cfg(feature = "with_tempfile")]
+    change_compare: Compare,
+
+    /// Suffix of names of temporary files.
+    #[cfg(feature = "with_tempfile")]
+    suffix: Option<OsString>,
+
+    /// Editor commands used for temporary files with given suffixes.
+    #[cfg(feature = "with_tempfile")]
+    filetype_editors: Vec<(OsString, OsString)>,
+
+    /// Function used to edit files instead of spawning the editor.
+    inprocess_editor: Option<InprocessEditor<'a>>,
+
+    /// Whether standard input replaces the file if it’s not a terminal.
+    stdin_fallback: bool,
+
+    /// Function called with path of each file before the editor is run on it.
+    on_launch: Option<Hook<'a>>,
+
+    /// Function called with path of each file after the editor terminates.
+    on_exit: Option<Hook<'a>>,
+
+    /// Function notified about progress of copying in [`Edit::file_copy`].
+    #[cfg(feature = "with_tempfile")]
+    copy_progress: Option<CopyProgress<'a>>,
+
+    /// Shell used to run the editor command.  `None` means `/bin/sh`.
+    shell: Option<&'a OsStr>,
+
+    /// Interpreter to run the editor command with if the shell is missing.
+    shell_fallback: Option<&'a OsStr>,
+
+    /// Editor command to run on remote hosts.
+    #[cfg(feature = "ssh")]
+    remote_editor: Option<&'a OsStr>,
+}
+
+/// Source of the editor command; see [`Edit::with_command_from_reader`].
+enum CommandReader<'a> {
+    /// Reader which hasn’t been read yet.
+    Pending(Box<dyn std::io::Read + 'a>),
+    /// Command read from the reader or `None` if it was empty.
+    Done(Option<OsString>),
+}
+
+/// Function deciding whether the editor succeeded; see
+/// [`Edit::with_success_predicate`].
+type SuccessPredicate<'a> = Box<dyn Fn(&std::process::ExitStatus) -> bool + 'a>;
+
+/// Function rewriting or rejecting editor command; see
+/// [`Edit::with_editor_filter`].
+type EditorFilter<'a> = Box<dyn Fn(OsString) -> Result<OsString, Error> + 'a>;
+
+/// Function editing a file in place of the editor; see
+/// [`Edit::with_inprocess_editor`].
+type InprocessEditor<'a> =
+    Box<dyn Fn(&std::path::Path) -> std::io::Result<()> + 'a>;
+
+/// Function called when the editor runs for too long; see
+/// [`Edit::with_soft_timeout`].
+type SoftTimeout<'a> = Box<dyn Fn() + 'a>;
+
+/// Function called with path of edited file; see [`Edit::on_launch`].
+type Hook<'a> = Box<dyn Fn(&std::path::Path) + 'a>;
+
+/// Function notified about number of bytes copied so far; see
+/// [`Edit::on_copy_progress`].
+#[cfg(feature = "with_tempfile")]
+type CopyProgress<'a> = std::cell::RefCell<Box<dyn FnMut(u64) + 'a>>;
+
+/// Function returning directory for temporary files; see
+/// [`Edit::with_tempdir_fn`].
+#[cfg(feature = "with_tempfile")]
+type TempdirFn<'a> = Box<dyn Fn() -> std::path::PathBuf + 'a>;
+
+/// Transformation of a buffer; see [`Edit::with_pre_filter`].
+type Filter<'a> = Box<dyn Fn(Vec<u8>) -> std::io::Result<Vec<u8>> + 'a>;
+
+/// Editing configuration with resolved editor command; see [`Edit::prepare`].
+pub struct Prepared<'e, 'a> {
+    /// The configuration the editor has been resolved from.
+    edit: &'e Edit<'a>,
+
+    /// Resolved editor command or `None` if the command is a no-op.
+    editor: Option<OsString>,
+}
+
+impl<'e, 'a> Prepared<'e, 'a> {
+    /// Returns the resolved editor command or `None` if it’s a no-op.
+    pub fn editor(&self) -> Option<&OsStr> { self.editor.as_deref() }
+
+    /// Executes text editor letting user modify the file; see [`Edit::file`].
+    pub fn file(&self, path: impl AsRef<std::path::Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        match &self.editor {
+            Some(editor) => {
+                imp::create_parents(self.edit, path)?;
+                imp::check_file(self.edit, path)?;
+                let _lock = imp::lock(self.edit, path)?;
+                imp::preserving_mode(self.edit, path, || {
+                    #[cfg(feature = "with_tempfile")]
+                    if self.edit.atomic {
+                        return imp::edit_file_atomic(
+                            self.edit,
+                            editor.clone(),
+                            path,
+                        );
+                    }
+                    imp::edit_file(self.edit, editor.clone(), path)
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Writes contents of a buffer to temporary file to let user edit it; see
+    /// [`Edit::buffer`].
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer(&self, buf: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match &self.editor {
+            Some(editor) => {
+                let tempdir = imp::temp_dir(self.edit);
+                imp::edit_buffer(self.edit, editor.clone(), buf, tempdir, None)
+            }
+            None => Ok(buf),
+        }
+    }
+}
+
+/// Editing configuration which doesn’t borrow any data; see
+/// [`Edit::to_owned`].
+pub struct EditOwned {
+    /// Settings which don’t borrow data.
+    base: Edit<'static>,
+    /// Owned copy of `Edit::editor_variable`.
+    editor_variable: Option<OsString>,
+    /// Owned copy of `Edit::editor_command`.
+    editor_command: Option<OsString>,
+    /// Owned copy of `Edit::path_variable`.
+    path_variable: Option<OsString>,
+    /// Owned copy of `Edit::label`.
+    label: Option<OsString>,
+    /// Owned copy of `Edit::shell`.
+    shell: Option<OsString>,
+    /// Owned copy of `Edit::shell_fallback`.
+    shell_fallback: Option<OsString>,
+    /// Owned copy of `Edit::remote_editor`.
+    #[cfg(feature = "ssh")]
+    remote_editor: Option<OsString>,
+    /// Owned copy of `Edit::ok_signals`.
+    #[cfg(unix)]
+    ok_signals: Vec<i32>,
+    /// Owned copy of `Edit::arg0`.
+    #[cfg(unix)]
+    arg0: Option<OsString>,
+}
+
+impl EditOwned {
+    /// Returns [`Edit`] object with configuration borrowed from this object.
+    ///
+    /// The returned object may be further configured without affecting this
+    /// object.  For example:
+    ///
+    /// ```no_run
+    /// let owned = run_editor::edit().with_max_attempts(5).to_owned();
+    /// owned.edit().file("/home/lex/.shellrc").unwrap();
+    /// ```
+    pub fn edit(&self) -> Edit<'_> {
+        let mut edit = self.base.copy_settings();
+        edit.editor_variable = self.editor_variable.as_deref();
+        edit.editor_command = self.editor_command.as_deref();
+        edit.path_variable = self.path_variable.as_deref();
+        edit.label = self.label.as_deref();
+        edit.shell = self.shell.as_deref();
+        edit.shell_fallback = self.shell_fallback.as_deref();
+        #[cfg(feature = "ssh")]
+        {
+            edit.remote_editor = self.remote_editor.as_deref();
+        }
+        #[cfg(unix)]
+        {
+            edit.ok_signals = self.ok_signals.as_slice();
+            edit.arg0 = self.arg0.as_deref();
+        }
+        edit
+    }
+}
+
+/// Result of [`Edit::file_copy_outcome`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CopyOutcome {
+    /// Whether the destination file didn’t exist and has been created.
+    pub created: bool,
+    /// Whether the user modified contents copied from the source file.
+    pub changed: bool,
+}
+
+/// Known peculiarities of an editor; see [`Edit::editor_quirks`].
+///
+/// All flags are `false` for editors the crate knows nothing about.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EditorQuirks {
+    /// Whether the editor returns immediately, before the user finishes
+    /// editing, unless run with an option such as `--wait` which is missing
+    /// from the editor command.
+    pub needs_wait: bool,
+    /// Whether the editor requires a running server, e.g. `emacsclient`
+    /// which fails if Emacs server is not running.
+    pub needs_server: bool,
+    /// Whether the editor is a graphical application.
+    pub is_gui: bool,
+}
+
+/// Where the path to edit is placed in the editor’s command line; see
+/// [`Edit::path_position`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathPosition {
+    /// Path is placed right after the program name, before options from the
+    /// editor command.
+    First,
+    /// Path is placed after all options from the editor command.
+    #[default]
+    Last,
+}
+
+/// How to decide whether buffer has been changed; see
+/// [`Edit::change_compare`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compare {
+    /// Buffers are equal if they consist of the same bytes.
+    #[default]
+    Exact,
+    /// White space at the end of lines and empty lines at the end of the
+    /// buffer are ignored.
+    IgnoreTrailingWs,
+}
+
+impl<'a> Default for Edit<'a> {
+    fn default() -> Self { edit() }
 }
 
 /// Constructs default [`Edit`] object.
@@ -45,10 +443,194 @@ pub struct Edit<'a> {
 /// run_editor::edit().file(path).unwrap();
 /// ```
 pub const fn edit<'a>() -> Edit<'a> {
-    Edit { editor_variable: None, editor_command: None }
+    Edit {
+        editor_variable: None,
+        editor_command: None,
+        command_reader: None,
+        max_attempts: 3,
+        retry_delay: std::time::Duration::from_millis(250),
+        reopen_attempts: 1,
+        reopen_delay: std::time::Duration::ZERO,
+        path_position: PathPosition::Last,
+        lock: false,
+        lock_wait: false,
+        max_file_size: None,
+        check_writable: false,
+        create_parents: false,
+        dedup_paths: false,
+        path_variable: None,
+        label: None,
+        leading_args: Vec::new(),
+        editor_args: Vec::new(),
+        launcher: Vec::new(),
+        trailing_args: Vec::new(),
+        pre_filters: Vec::new(),
+        post_filters: Vec::new(),
+        #[cfg(unix)]
+        use_dev_tty: false,
+        #[cfg(unix)]
+        preserve_mode: false,
+        #[cfg(unix)]
+        ok_signals: &[],
+        #[cfg(unix)]
+        arg0: None,
+        success_predicate: None,
+        timeout: None,
+        soft_timeout: None,
+        strict_command: false,
+        use_default_editor: true,
+        no_shell: false,
+        warn_readonly_editor: false,
+        readonly_editors: None,
+        editor_filter: None,
+        #[cfg(feature = "with_tempfile")]
+        atomic: false,
+        #[cfg(all(unix, feature = "with_tempfile"))]
+        result_mode: None,
+        #[cfg(feature = "with_tempfile")]
+        strip_bom: false,
+        #[cfg(feature = "with_tempfile")]
+        feed_stdin: false,
+        #[cfg(feature = "with_tempfile")]
+        post_command: Vec::new(),
+        #[cfg(feature = "with_tempfile")]
+        apply_filter: Vec::new(),
+        #[cfg(feature = "with_tempfile")]
+        tempdir_fn: None,
+        #[cfg(feature = "with_tempfile")]
+        debug_keep_temps: false,
+        #[cfg(feature = "with_tempfile")]
+        keep_on_error: false,
+        #[cfg(feature = "with_tempfile")]
+        change_compare: Compare::Exact,
+        #[cfg(feature = "with_tempfile")]
+        suffix: None,
+        #[cfg(feature = "with_tempfile")]
+        filetype_editors: Vec::new(),
+        inprocess_editor: None,
+        stdin_fallback: false,
+        on_launch: None,
+        on_exit: None,
+        #[cfg(feature = "with_tempfile")]
+        copy_progress: None,
+        shell: None,
+        shell_fallback: None,
+        #[cfg(feature = "ssh")]
+        remote_editor: None,
+    }
+}
+
+pub use diff::{ByteRange, DiffLine, LineChange};
+pub use error::{BuildError, Error, ErrorKind};
+#[cfg(feature = "test-util")]
+pub use recording::{Invocation, RecordingEditor};
+
+
+/// Process-wide editor command override; see [`set_global_editor`].
+static GLOBAL_EDITOR: std::sync::RwLock<Option<OsString>> =
+    std::sync::RwLock::new(None);
+
+/// Sets editor command to use for all [`Edit`] objects in the process.
+///
+/// This is meant for applications which accept an option such as `--editor`
+/// and want it to apply everywhere without threading an [`Edit`] object
+/// through the code.  Once set, the command takes priority over all other
+/// methods of determining the editor; see [`Edit::editor`] for full
+/// description of the resolution priorities.
+///
+/// By default no override is set.  It can be removed with
+/// [`clear_global_editor`].
+///
+/// ```no_run
+/// run_editor::set_global_editor("nano".into());
+/// run_editor::edit().file("/home/lex/.shellrc").unwrap();
+/// ```
+pub fn set_global_editor(editor: OsString) {
+    *GLOBAL_EDITOR.write().unwrap_or_else(|err| err.into_inner()) =
+        Some(editor);
+}
+
+/// Removes process-wide editor override set with [`set_global_editor`].
+pub fn clear_global_editor() {
+    *GLOBAL_EDITOR.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Returns whether editor command is a no-op, i.e. `":"` or `"true"`.
+///
+/// On Windows, the comparison is case-insensitive and ignores `.exe` extension
+/// so that for example `True.exe` is recognised as well.
+fn is_nop(editor: &OsStr) -> bool {
+    match editor.to_str() {
+        Some(editor) => matches!(&*profile::normalize(editor), ":" | "true"),
+        None => false,
+    }
+}
+
+/// Returns process-wide editor override if one is set.
+fn global_editor() -> Option<OsString> {
+    GLOBAL_EDITOR.read().unwrap_or_else(|err| err.into_inner()).clone()
+}
+
+
+/// Quotes a string such that a POSIX shell interprets it literally.
+///
+/// This is useful when building a command line which includes a path, e.g.
+/// to run [`Edit::editor`] command manually.  The string is enclosed in single
+/// quotes with any single quotes inside replaced by `'\''`.  All other
+/// characters, including `$`, `\` and new lines, are kept as is since they
+/// have no special meaning inside of single quotes.  On Unix, the string is
+/// treated as a sequence of bytes so non-UTF-8 strings are preserved.  On other
+/// systems, the string is converted to UTF-8 lossily first.
+///
+/// ```
+/// use std::ffi::OsStr;
+///
+/// let quoted = run_editor::shell_quote(OsStr::new("/tmp/it's here"));
+/// assert_eq!("'/tmp/it'\\''s here'", quoted);
+///
+/// let mut cmd = run_editor::edit().editor();
+/// cmd.push(" ");
+/// cmd.push(run_editor::shell_quote(OsStr::new("/tmp/file")));
+/// ```
+pub fn shell_quote(value: &OsStr) -> OsString {
+    shell::from_bytes(shell::quote(&shell::to_bytes(value)))
 }
 
-pub use error::Error;
+/// Returns whether the process is running interactively.
+///
+/// The process is considered interactive if both its standard input and
+/// standard output are terminals.  This is the definition the crate uses when
+/// it needs to know whether a user can respond to a prompt; applications
+/// building their own confirmation prompts can use it to behave consistently.
+///
+/// ```no_run
+/// if run_editor::is_interactive() {
+///     run_editor::edit().file("/home/lex/.shellrc").unwrap();
+/// } else {
+///     eprintln!("not running interactively; refusing to start the editor");
+/// }
+/// ```
+pub fn is_interactive() -> bool {
+    use std::io::IsTerminal;
+    std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+/// Returns names of environment variables consulted when resolving the editor
+/// command.
+///
+/// The variables are listed in order of priority, i.e. `VISUAL` followed by
+/// `EDITOR`.  This doesn’t include variables configured at runtime; see
+/// [`Edit::relevant_env_vars`] for the list specific to given configuration.
+/// This is useful to automatically generate help text of an application.
+///
+/// ```
+/// for var in run_editor::relevant_env_vars() {
+///     println!("  {}", var.to_string_lossy());
+/// }
+/// ```
+pub fn relevant_env_vars() -> Vec<&'static OsStr> {
+    vec![OsStr::new("VISUAL"), OsStr::new("EDITOR")]
+}
 
 impl<'a> Edit<'a> {
     /// Executes text editor letting user modify the file.
@@ -65,13 +647,215 @@ impl<'a> Edit<'a> {
     /// Note that in cases where the value to edit does not exist in a file but
     /// is kept in memory, it may be more convenient to use
     /// [`buffer`](`Self::buffer`) instead.
+    ///
+    /// `path` may be anything which implements `AsRef<Path>`.  This includes
+    /// `camino::Utf8Path` and `camino::Utf8PathBuf` so applications using UTF-8
+    /// paths can pass them directly without any conversion.
     pub fn file(&self, path: impl AsRef<std::path::Path>) -> Result<(), Error> {
-        match self.editor_unless_nop() {
-            Some(editor) => imp::edit_file(editor, path.as_ref()),
+        self.unchecked()?.file(path)
+    }
+
+    /// Executes text editor letting user modify the file and returns whether
+    /// the file existed beforehand.
+    ///
+    /// This is [`file`](`Self::file`) which additionally checks whether the file
+    /// exists before the editor is run (since the editor may create it).  It
+    /// lets tools tell user whether the file has been created or edited.  Note
+    /// that `true` is returned if the file existed even if the user hasn’t
+    /// changed it and `false` is returned if the file didn’t exist even if the
+    /// user hasn’t saved it.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let path = "/home/lex/.shellrc";
+    /// match run_editor::edit().file_existed(path) {
+    ///     Ok(true) => println!("edited {path}"),
+    ///     Ok(false) => println!("created {path}"),
+    ///     Err(err) => eprintln!("{err}"),
+    /// }
+    /// ```
+    pub fn file_existed(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<bool, Error> {
+        use error::WithPathContext;
+
+        let path = path.as_ref();
+        let existed = path.try_exists().with_path_ctx(path)?;
+        self.file(path)?;
+        Ok(existed)
+    }
+
+    /// Executes text editor letting user modify several files at once.
+    ///
+    /// The editor is run once with all the paths as its arguments (most
+    /// editors open each file in a separate buffer).  If in-process editor is
+    /// set (see [`with_inprocess_editor`](`Self::with_inprocess_editor`)), it’s
+    /// called for each file in turn.  Each file is checked and locked as with
+    /// [`file`](`Self::file`) however the files are always edited in place,
+    /// i.e. [`atomic`](`Self::atomic`) option is ignored.
+    ///
+    /// By default the paths are passed to the editor as given.  With
+    /// [`dedup_paths`](`Self::dedup_paths`) option enabled, paths referring to
+    /// the same file are passed only once.
+    ///
+    /// ```no_run
+    /// let paths = ["/home/lex/.shellrc", "/home/lex/.profile"];
+    /// if let Err(err) = run_editor::edit().files(paths) {
+    ///     eprintln!("{err}");
+    /// }
+    /// ```
+    pub fn files(
+        &self,
+        paths: impl IntoIterator<Item = impl AsRef<std::path::Path>>,
+    ) -> Result<(), Error> {
+        let editor = match self.editor_unless_nop()? {
+            Some(editor) => editor,
+            None => return Ok(()),
+        };
+        let mut paths = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+        if self.dedup_paths {
+            paths = imp::dedup_paths(paths);
+        }
+        let mut locks = Vec::with_capacity(paths.len());
+        for path in paths.iter() {
+            imp::create_parents(self, path)?;
+            imp::check_file(self, path)?;
+            locks.push(imp::lock(self, path)?);
+        }
+        let paths = paths.iter().map(|path| path.as_path()).collect::<Vec<_>>();
+        imp::edit_files(self, editor, &paths)
+    }
+
+    /// Executes text editor letting user modify several files at once and
+    /// reports which of them have changed.
+    ///
+    /// This is [`files`](`Self::files`) which additionally hashes contents of
+    /// each file before the editor is launched and after it exits.  Returned
+    /// vector lists the paths in the order they were given together with
+    /// a flag indicating whether contents of the file differ.  A file which
+    /// didn’t exist before editing is reported as changed if the editor
+    /// created it.  Note that the comparison uses a 64-bit non-cryptographic
+    /// hash so it won’t detect deliberately crafted collisions.
+    ///
+    /// ```no_run
+    /// let paths = ["/home/lex/.shellrc", "/home/lex/.profile"];
+    /// let changed = run_editor::edit().files_changed(paths).unwrap();
+    /// for (path, _) in changed.iter().filter(|(_, changed)| *changed) {
+    ///     println!("{} updated", path.display());
+    /// }
+    /// ```
+    pub fn files_changed(
+        &self,
+        paths: impl IntoIterator<Item = impl AsRef<std::path::Path>>,
+    ) -> Result<Vec<(std::path::PathBuf, bool)>, Error> {
+        let paths = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+        let before = paths
+            .iter()
+            .map(|path| imp::file_hash(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.files(&paths)?;
+        paths
+            .into_iter()
+            .zip(before)
+            .map(|(path, before)| {
+                let changed = imp::file_hash(&path)? != before;
+                Ok((path, changed))
+            })
+            .collect()
+    }
+
+    /// Executes text editor letting user modify the file without blocking the
+    /// application’s event loop.
+    ///
+    /// Works like [`file`](`Self::file`) except that while the editor is
+    /// running, `poll` is called periodically (roughly every 10 milliseconds)
+    /// giving the application a chance to redraw its interface or handle its
+    /// own input.  This is mostly useful with graphical editors which don’t
+    /// take over the terminal.
+    ///
+    /// If `poll` returns `false`, editing is cancelled: the editor (or rather
+    /// the shell running it) is killed, waited for and an error of kind
+    /// [`ErrorKind::Cancelled`] is returned.  Whatever the editor has saved
+    /// into the file before being killed stays in the file.  `poll` is not
+    /// called after the editor terminates and it’s not called at all if the
+    /// editor command is a no-op or an in-process editor is set (see
+    /// [`with_inprocess_editor`](`Self::with_inprocess_editor`)).  Timeout set
+    /// with [`with_timeout`](`Self::with_timeout`) is respected.  The file is
+    /// always edited in place, i.e. [`atomic`](`Self::atomic`) option is
+    /// ignored.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let quit = std::sync::atomic::AtomicBool::new(false);
+    /// let res = run_editor::edit().file_polling("/home/lex/.shellrc", || {
+    ///     // Redraw the interface and handle pending events here.
+    ///     !quit.load(std::sync::atomic::Ordering::Relaxed)
+    /// });
+    /// if let Err(err) = res {
+    ///     eprintln!("{err}");
+    /// }
+    /// ```
+    pub fn file_polling(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        poll: impl FnMut() -> bool,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        match self.editor_unless_nop()? {
+            Some(editor) => {
+                imp::create_parents(self, path)?;
+                imp::check_file(self, path)?;
+                let _lock = imp::lock(self, path)?;
+                imp::edit_file_polling(self, editor, path, poll)
+            }
             None => Ok(()),
         }
     }
 
+    /// Lets user edit the file once and parses the result.
+    ///
+    /// Unlike [`edit_or_default`](`Self::edit_or_default`), the editor is not
+    /// rerun if the content is invalid.  Instead, the outer `Result` reports
+    /// failures to run the editor or read the file while the inner one is
+    /// value returned by `parse` leaving the caller free to handle parse errors
+    /// as they see fit.  If the editor command is a no-op (see
+    /// [`file`](`Self::file`)), the file’s current contents are parsed.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let path = "/home/lex/.jobs";
+    /// let parse = |buf: &[u8]| {
+    ///     String::from_utf8_lossy(buf).trim().parse::<u32>()
+    /// };
+    /// match run_editor::edit().file_parsed_once(path, parse) {
+    ///     Ok(Ok(jobs)) => println!("using {jobs} jobs"),
+    ///     Ok(Err(err)) => eprintln!("{path}: invalid number: {err}"),
+    ///     Err(err) => eprintln!("{err}"),
+    /// }
+    /// ```
+    pub fn file_parsed_once<T, E>(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        parse: impl FnOnce(&[u8]) -> Result<T, E>,
+    ) -> Result<Result<T, E>, Error> {
+        use error::WithPathContext;
+
+        let path = path.as_ref();
+        self.file(path)?;
+        let buf = std::fs::read(path).with_path_ctx(path)?;
+        Ok(parse(buf.as_slice()))
+    }
+
     /// Writes contents of a buffer to temporary file to let user edit it.
     ///
     /// This is a wrapper around [`file`](`Self::file`) which first writes the
@@ -97,121 +881,3025 @@ impl<'a> Edit<'a> {
     /// is enabled by default.
     #[cfg(feature = "with_tempfile")]
     pub fn buffer(&self, buf: Vec<u8>) -> Result<Vec<u8>, Error> {
-        match self.editor_unless_nop() {
-            Some(editor) => imp::edit_buffer(editor, buf),
-            None => Ok(buf),
-        }
+        self.unchecked()?.buffer(buf)
     }
 
-    /// Copies file from `src` to `dst` letting user edit it.
+    /// Lets user continue editing a temporary file kept after a failed edit.
     ///
-    /// This is a bit like first copying the file and then running
-    /// [`file`](`Self::file`) on it except that this method performs atomic
-    /// write and on failure the destination location is not affected at all.
-    /// That is, on failure if the destination file didn’t exist, it won’t be
-    /// created and if it existed it won’t be edited.
+    /// With [`keep_on_error`](`Self::keep_on_error`) option enabled,
+    /// temporary files are not deleted if editing fails, e.g. because the
+    /// editor crashed or the result was rejected.  This method lets the user
+    /// continue where they left off: the editor is run on the kept file at
+    /// `temp_path` (rather than on a fresh copy of the original buffer) and
+    /// once it terminates, contents of the file are read and returned as with
+    /// [`buffer`](`Self::buffer`).  Pre filters are not applied (since they
+    /// have been applied when the file was created) but post filters are.
+    /// Similarly, validator set with
+    /// [`with_post_command`](`Self::with_post_command`) is run on the file.
     ///
-    /// Example usage:
+    /// On success the file is deleted unless `debug_keep_temps` is enabled.  On
+    /// failure it’s left in place so that editing can be resumed again.  It’s
+    /// an error if the file doesn’t exist.
     ///
     /// ```no_run
-    /// let template_path = "/etc/skel/.bashrc";
-    /// let destination_path = "/home/lex/.bashrc";
-    /// let res = run_editor::edit().file_copy(template_path, destination_path);
-    /// if let Err(err) = res {
-    ///     eprintln!("{err}")
+    /// let path = std::path::Path::new("/tmp/.tmpdNbgyY");
+    /// match run_editor::edit().resume(path) {
+    ///     Ok(buf) => println!("{}", String::from_utf8_lossy(&buf)),
+    ///     Err(err) => eprintln!("{err}"),
     /// }
     /// ```
     ///
     /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
     /// is enabled by default.
     #[cfg(feature = "with_tempfile")]
-    pub fn file_copy(
+    pub fn resume(
         &self,
-        src: impl AsRef<std::path::Path>,
-        dst: impl AsRef<std::path::Path>,
-    ) -> Result<(), Error> {
-        let temp = imp::copy_temp(src.as_ref(), dst.as_ref())?;
-        self.file(&*temp)?;
-        imp::persist(temp, dst.as_ref())?;
-        Ok(())
+        temp_path: &std::path::Path,
+    ) -> Result<Vec<u8>, Error> {
+        let editor = self.editor_unless_nop()?;
+        imp::resume(self, editor, temp_path)
     }
 
-    /// Returns the editor command to use to let user edit files.
+    /// Lets user edit a buffer shared with other threads while holding a lock
+    /// on it.
     ///
-    /// The resolution of the editor command is goes as follows:
-    /// 1. If variable name has been provided via
-    ///    [`with_editor_variable`](`Self::with_editor_variable`) and such
-    ///    environment variable is set, use its value.
-    /// 2. Otherwise, if editor command has been provided via
-    ///    [`with`](`Self::with`) method, use that command.
-    /// 3. Otherwise, use system-dependent method for determining user
-    ///    preferences.  At the moment that means reading `VISUAL` and `EDITOR`
-    ///    environment variables.
-    /// 4. If that fails as well, use system-dependent default.  At the moment
-    ///    that means `"vi"` which should be available on any Unix system.
+    /// `lock` is called to obtain exclusive access to the buffer, for example
+    /// by locking a [`Mutex`](`std::sync::Mutex`), and the returned guard is
+    /// held until editing finishes.  Contents of the buffer are edited as with
+    /// [`buffer`](`Self::buffer`) and on success replaced with the result.  On
+    /// failure, the buffer is left unchanged.  Since the guard is held while
+    /// the editor runs, at most one editor is run against the value at any
+    /// given time (assuming all code accessing the value goes through the same
+    /// lock) and other threads block until the user finishes editing.
     ///
-    /// Note that returned string is a *command*.  This means that it needs to
-    /// be executed through a shell with file to be edited *properly escaped*
-    /// and appended to the command.  Or better still, quoted environment
-    /// variable name appended and the file path passed as that variable.  For
-    /// example, on Unix systems one might execute:
+    /// The editor command is resolved before `lock` is called, so
+    /// configuration errors are reported without taking the lock.  `lock` is
+    /// called even if the editor command is a no-op.
     ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let value = std::sync::Mutex::new(b"foo\n".to_vec());
+    /// run_editor::edit()
+    ///     .buffer_guarded(|| value.lock().unwrap())
+    ///     .unwrap();
     /// ```
-    /// // File to let user edit:
-    /// let path = "/home/lex/.shellrc";
     ///
-    /// // Determine command to execute; pass path as variable so we don’t need
-    /// // to worry about escaping:
-    /// let mut cmd = run_editor::edit().editor();
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_guarded<G>(
+        &self,
+        lock: impl FnOnce() -> G,
+    ) -> Result<(), Error>
+    where
+        G: std::ops::DerefMut<Target = Vec<u8>>, {
+        let prepared = self.unchecked()?;
+        let mut guard = lock();
+        *guard = prepared.buffer(guard.clone())?;
+        Ok(())
+    }
+
+    /// Like [`buffer`](`Self::buffer`) but writes the result into `out`.
+    ///
+    /// Rather than reading edited temporary file into memory, its contents are
+    /// copied straight into the writer.  This reduces memory usage when large
+    /// data is edited to be forwarded elsewhere, e.g. into a file or a socket.
+    /// Pre filters (see [`with_pre_filter`](`Self::with_pre_filter`)) are
+    /// applied to the buffer but since the result is never held in memory,
+    /// post filters are not run and byte order mark is not stripped (see
+    /// [`strip_bom`](`Self::strip_bom`)).  If the editor command is a no-op
+    /// (see [`file`](`Self::file`)), the buffer is written into `out`
+    /// unchanged.
+    ///
+    /// Example usage (error handling omitted for brevity):
+    ///
+    /// ```no_run
+    /// let out = std::fs::File::create("/home/lex/notes.txt").unwrap();
+    /// run_editor::edit().buffer_to(b"Notes:\n".to_vec(), out).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_to(
+        &self,
+        buf: Vec<u8>,
+        out: impl std::io::Write,
+    ) -> Result<(), Error> {
+        match self.editor_unless_nop()? {
+            Some(editor) => imp::edit_buffer_to(self, editor, buf, out),
+            None => imp::write_output(out, &buf),
+        }
+    }
+
+    /// Like [`buffer`](`Self::buffer`) but gives the temporary file the same
+    /// permissions as file at `like` path.
+    ///
+    /// This is useful when the buffer holds contents of an existing file and
+    /// the editor (or its hooks and plugins) should see the temporary file with
+    /// that file’s mode.  The `like` file is read for its metadata only.
+    ///
+    /// Example usage (error handling omitted for brevity):
+    ///
+    /// ```no_run
+    /// let path = "/home/lex/bin/script";
+    /// let buf = std::fs::read(path).unwrap();
+    /// let buf = run_editor::edit().buffer_like(buf, path).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_like(
+        &self,
+        buf: Vec<u8>,
+        like: impl AsRef<std::path::Path>,
+    ) -> Result<Vec<u8>, Error> {
+        use error::WithPathContext;
+
+        let editor = match self.editor_unless_nop()? {
+            Some(editor) => editor,
+            None => return Ok(buf),
+        };
+        let like = like.as_ref();
+        let perms = std::fs::metadata(like).with_path_ctx(like)?.permissions();
+        let tempdir = imp::temp_dir(self);
+        imp::edit_buffer(self, editor, buf, tempdir, Some(perms))
+    }
+
+    /// Like [`buffer`](`Self::buffer`) but creates the temporary file in the
+    /// same directory as `near` path.
+    ///
+    /// By default the temporary file is created in system’s temporary
+    /// directory.  If the result is going to be written to a specific location,
+    /// creating the temporary file on the same file system ensures the editor
+    /// and its plugins see similar environment (e.g. mount options or project
+    /// configuration files) and allows the result to be moved into place
+    /// without copying it between devices.  The `near` file itself needn’t
+    /// exist and is not touched.
+    ///
+    /// Example usage (error handling omitted for brevity):
+    ///
+    /// ```no_run
+    /// let path = "/home/lex/notes/today.txt";
+    /// let buf = run_editor::edit().buffer_near(Vec::new(), path).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_near(
+        &self,
+        buf: Vec<u8>,
+        near: impl AsRef<std::path::Path>,
+    ) -> Result<Vec<u8>, Error> {
+        match self.editor_unless_nop()? {
+            Some(editor) => {
+                let tempdir = imp::parent_dir(near.as_ref())?;
+                imp::edit_buffer(self, editor, buf, tempdir, None)
+            }
+            None => Ok(buf),
+        }
+    }
+
+    /// Runs the editor on a buffer written to temporary file and returns what
+    /// the editor printed to standard output.
+    ///
+    /// Some tools used as editors, such as formatters, read a file and emit
+    /// the result to standard output rather than modifying the file.  This
+    /// method supports such tools.  Contents of the temporary file after the
+    /// editor terminates are ignored.  Standard error and standard input are
+    /// inherited.
+    ///
+    /// If the editor terminates with non-zero exit status, an error is
+    /// returned and its output discarded.  If the editor command is a no-op
+    /// (see [`file`](`Self::file`)), the buffer is returned unchanged.  Buffer
+    /// filters (see [`with_pre_filter`](`Self::with_pre_filter`)) are applied
+    /// to the buffer and the output respectively and byte order mark is
+    /// stripped from the output as with [`buffer`](`Self::buffer`) (see
+    /// [`strip_bom`](`Self::strip_bom`)).
+    ///
+    /// Example usage (error handling omitted for brevity):
+    ///
+    /// ```no_run
+    /// let source = b"fn main() {}".to_vec();
+    /// let formatted = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new("rustfmt --emit stdout"))
+    ///     .buffer_capture_stdout(source)
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_capture_stdout(
+        &self,
+        buf: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        match self.editor_unless_nop()? {
+            Some(editor) => imp::edit_buffer_capture(self, editor, buf),
+            None => Ok(buf),
+        }
+    }
+
+    /// Lets user edit lines of text provided by an iterator.
+    ///
+    /// Lines are streamed into the temporary file as they are produced by the
+    /// iterator, each followed by a new line character, without building one
+    /// big buffer first.  After editing, the file is read back and split into
+    /// lines with line terminators (`"\n"`, `"\r\n"` or a lone `"\r"` used
+    /// by classic Mac OS tools) removed.  A missing new line at the end of the
+    /// file doesn’t result in an additional line.
+    ///
+    /// Buffer filters (see [`with_pre_filter`](`Self::with_pre_filter`)) are
+    /// not applied.  The edited file must be valid UTF-8.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let todo = (1..=100).map(|n| format!("pick commit-{n}"));
+    /// let todo = run_editor::edit().buffer_from_lines(todo).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_from_lines(
+        &self,
+        lines: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<String>, Error> {
+        let lines = lines.into_iter();
+        match self.editor_unless_nop()? {
+            Some(editor) => imp::edit_lines(self, editor, lines),
+            None => Ok(lines.collect()),
+        }
+    }
+
+    /// Lets user edit several named buffers at once.
+    ///
+    /// Each buffer is written into a temporary file whose name is the
+    /// buffer’s key so that the editor shows meaningful names (e.g. in tabs)
+    /// and the editor is run once with all the files.  Once it terminates,
+    /// edited buffers are returned keyed by the same names.  Buffer filters
+    /// (see [`with_pre_filter`](`Self::with_pre_filter`)) are applied to each
+    /// buffer.
+    ///
+    /// The files are created in a new temporary directory which is deleted
+    /// afterwards.  Characters which are not valid in file names (such as
+    /// slashes) are replaced by underscores.  If two names map to the same file
+    /// name (including names which differ in case only), a number is appended
+    /// to the latter, e.g. `config-2.toml`.  Keys of the returned map are
+    /// always the original names.  If [`with_path_var`](`Self::with_path_var`)
+    /// is used and there is more than one buffer, the files are passed to the
+    /// editor as arguments rather than through the variable.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let mut bufs = std::collections::BTreeMap::new();
+    /// bufs.insert("server.toml".to_string(), b"port = 80\n".to_vec());
+    /// bufs.insert("client.toml".to_string(), b"retries = 3\n".to_vec());
+    /// let bufs = run_editor::edit().buffers_named(bufs).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffers_named(
+        &self,
+        bufs: std::collections::BTreeMap<String, Vec<u8>>,
+    ) -> Result<std::collections::BTreeMap<String, Vec<u8>>, Error> {
+        match self.editor_unless_nop()? {
+            Some(_) if bufs.is_empty() => Ok(bufs),
+            Some(editor) => imp::edit_buffers_named(self, editor, bufs),
+            None => Ok(bufs),
+        }
+    }
+
+    /// Lets user edit lines of text and reports what happened to each of them.
+    ///
+    /// This is like [`buffer_from_lines`](`Self::buffer_from_lines`) except
+    /// that rather than returning edited lines it aligns them with the
+    /// original ones and classifies each as kept, modified, added or deleted;
+    /// see [`LineChange`].  This lets tools which track per-line metadata
+    /// figure out which of the original lines survived editing.
+    ///
+    /// Alignment is based on the longest common subsequence of the lines.
+    /// Within each changed region, deleted lines are paired with added ones in
+    /// order and reported as modified.  The returned changes are in order of
+    /// the edited buffer with deleted lines placed where they used to be.
+    ///
+    /// Example usage (error handling omitted for brevity):
+    ///
+    /// ```no_run
+    /// use run_editor::LineChange;
+    ///
+    /// let todo = vec!["pick a".to_string(), "pick b".to_string()];
+    /// for change in run_editor::edit().buffer_lines_tracked(todo).unwrap() {
+    ///     match change {
+    ///         LineChange::Kept { old, .. } => println!("{old}: kept"),
+    ///         LineChange::Modified { old, line } => println!("{old}: {line}"),
+    ///         LineChange::Added { line } => println!("new: {line}"),
+    ///         LineChange::Deleted { old, .. } => println!("{old}: deleted"),
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_lines_tracked(
+        &self,
+        lines: Vec<String>,
+    ) -> Result<Vec<LineChange>, Error> {
+        let edited = self.buffer_from_lines(lines.iter().cloned())?;
+        Ok(diff::track_lines(lines, edited))
+    }
+
+    /// Lets user edit a buffer and returns the result together with ranges of
+    /// bytes which changed.
+    ///
+    /// The ranges are offsets in the edited buffer which differ from the
+    /// original.  This lets callers highlight changes or persist only the
+    /// modified part.  The diff is deliberately simple: common prefix and
+    /// suffix of the original and edited buffer are stripped and whatever
+    /// remains in between is reported as a single range.  Thus, at most one
+    /// range is returned and none if the buffer hasn’t changed.  If bytes
+    /// have only been removed, the range is empty and indicates where the
+    /// removal happened.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let (buf, ranges) = run_editor::edit()
+    ///     .buffer_ranges(b"name = Lex\n".to_vec())
+    ///     .unwrap();
+    /// for range in ranges {
+    ///     println!("changed: {:?}", String::from_utf8_lossy(&buf[range]));
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_ranges(
+        &self,
+        buf: Vec<u8>,
+    ) -> Result<(Vec<u8>, Vec<ByteRange>), Error> {
+        let edited = self.buffer(buf.clone())?;
+        let ranges = diff::changed_range(&buf, &edited).into_iter().collect();
+        Ok((edited, ranges))
+    }
+
+    /// Lets user edit a buffer and appends the result to a file.
+    ///
+    /// This supports ‘compose an entry then add it to the list’ flows in
+    /// a single call.  After editing, `dst` is copied into a temporary file
+    /// next to it, edited contents are appended and the temporary file
+    /// atomically replaces `dst`.  That is, readers of `dst` see either the
+    /// old or the new contents but never a partially appended entry and if
+    /// the editor fails, `dst` is not touched.  Permissions of `dst` are
+    /// preserved.
+    ///
+    /// If `dst` doesn’t exist, it’s created with the edited contents only.
+    /// Note that the edited contents are appended as is; if the entries need
+    /// to be separated (e.g. by a new line character), it’s up to the caller
+    /// to include the separator in the buffer.  Concurrent appends to the same
+    /// file are not synchronised and one of them may be lost.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let entry = b"\n# Describe what you worked on today\n".to_vec();
+    /// run_editor::edit().buffer_append_to(entry, "journal.txt").unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_append_to(
+        &self,
+        buf: Vec<u8>,
+        dst: impl AsRef<std::path::Path>,
+    ) -> Result<(), Error> {
+        let buf = self.buffer(buf)?;
+        imp::append_atomic(self, &buf, dst.as_ref())
+    }
+
+    /// Shows a buffer in a pager and then lets user edit it.
+    ///
+    /// This is a two-step flow for large or unfamiliar content: the user first
+    /// pages through the buffer with the pager resolved as described in
+    /// [`Page::pager`] and once the pager exits, the buffer is edited as with
+    /// [`buffer`](`Self::buffer`).  If the pager command is a no-op or its
+    /// program cannot be found, the preview step is skipped.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let license = std::fs::read("LICENSE").unwrap();
+    /// let license = run_editor::edit().preview_then_edit(license).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn preview_then_edit(&self, buf: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let pager = page().pager();
+        if !is_nop(&pager) && imp::check_editor(&pager).is_ok() {
+            imp::page_buffer(pager, &buf)?;
+        }
+        self.buffer(buf)
+    }
+
+    /// Lets user edit a buffer returning `None` if they cleared it.
+    ///
+    /// [`buffer`](`Self::buffer`) returns whatever the user saved, including
+    /// an empty buffer.  This method makes ‘clear to delete’ flows explicit: if
+    /// the edited file is empty, `None` is returned.  Note that a file
+    /// consisting of white space only (e.g. a single new line character some
+    /// editors insist on adding) is not considered empty.
+    ///
+    /// Example usage (error handling omitted for brevity):
+    ///
+    /// ```no_run
+    /// let note = b"Some note".to_vec();
+    /// match run_editor::edit().buffer_opt(note).unwrap() {
+    ///     Some(note) => println!("{}", String::from_utf8_lossy(&note)),
+    ///     None => println!("note deleted"),
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_opt(&self, buf: Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+        let buf = self.buffer(buf)?;
+        Ok(Some(buf).filter(|buf| !buf.is_empty()))
+    }
+
+    /// Lets user edit a buffer and reports whether they changed it.
+    ///
+    /// This is like [`buffer`](`Self::buffer`) but additionally returns
+    /// whether the edited buffer differs from the original.  This is useful
+    /// for aborting an operation if the user didn’t change anything, e.g. the
+    /// way `git rebase -i` does.  How buffers are compared is controlled with
+    /// [`change_compare`](`Self::change_compare`).  If the editor command is
+    /// a no-op, the buffer is returned unchanged.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let todo = b"pick 1234 Some commit\n".to_vec();
+    /// match run_editor::edit().buffer_changed(todo) {
+    ///     Ok((_, false)) => eprintln!("nothing to do"),
+    ///     Ok((todo, true)) => println!("{}", String::from_utf8_lossy(&todo)),
+    ///     Err(err) => eprintln!("{err}"),
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_changed(
+        &self,
+        buf: Vec<u8>,
+    ) -> Result<(Vec<u8>, bool), Error> {
+        let original = buf.clone();
+        let buf = self.buffer(buf)?;
+        let changed = !imp::same_buffers(self.change_compare, &original, &buf);
+        Ok((buf, changed))
+    }
+
+    /// Lets user edit a buffer and returns the result together with its hash.
+    ///
+    /// This is meant for caching layers which key stored data on its hash.
+    /// The hash is 64-bit FNV-1a of the edited bytes.  The algorithm is fixed
+    /// so the value is stable across platforms, program runs and versions of
+    /// this crate.  Note that it’s *not* cryptographically secure and must not
+    /// be relied upon where collisions could be crafted maliciously.
+    ///
+    /// Example usage (error handling omitted for brevity):
+    ///
+    /// ```no_run
+    /// let (buf, hash) = run_editor::edit().buffer_hashed(Vec::new()).unwrap();
+    /// println!("{hash:016x}");
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_hashed(&self, buf: Vec<u8>) -> Result<(Vec<u8>, u64), Error> {
+        let buf = self.buffer(buf)?;
+        let hash = imp::fnv1a(&buf);
+        Ok((buf, hash))
+    }
+
+    /// Lets user edit a string converting result to UTF-8 lossily.
+    ///
+    /// This is a wrapper around [`buffer`](`Self::buffer`) which never fails
+    /// due to encoding errors.  If the user saves content which is not valid
+    /// UTF-8, each invalid sequence is replaced by U+FFFD REPLACEMENT
+    /// CHARACTER (see [`String::from_utf8_lossy`]).  This is convenient for
+    /// display-oriented tools which tolerate such characters; in other cases
+    /// it’s likely better to handle invalid UTF-8 explicitly.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let message = "Some value to edit".to_string();
+    /// match run_editor::edit().buffer_string_lossy(message) {
+    ///     Ok(message) => println!("{message}"),
+    ///     Err(err) => eprintln!("edit failed: {err}"),
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_string_lossy(&self, buf: String) -> Result<String, Error> {
+        let buf = self.buffer(buf.into_bytes())?;
+        Ok(String::from_utf8(buf).unwrap_or_else(|err| {
+            String::from_utf8_lossy(err.as_bytes()).into_owned()
+        }))
+    }
+
+    /// Lets user edit a string making sure the result is valid UTF-8.
+    ///
+    /// The string is edited as with [`buffer`](`Self::buffer`).  If the user
+    /// saves content which is not valid UTF-8 (e.g. after pasting binary
+    /// data), rather than failing, the editor is run again on the saved
+    /// content with a comment line describing the problem added at the top.
+    /// If the user leaves that line intact, it’s removed from the result.
+    /// This is repeated up to the number of times configured with
+    /// [`with_max_attempts`](`Self::with_max_attempts`) after which an error
+    /// of kind [`ErrorKind::Invalid`] is returned.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let message = "Commit message".to_string();
+    /// let message = run_editor::edit().buffer_utf8(message).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_utf8(&self, buf: String) -> Result<String, Error> {
+        imp::edit_buffer_utf8(self, buf)
+    }
+
+    /// Asks user to enter a value in the editor.
+    ///
+    /// This is a high-level convenience for simple prompts.  The temporary file
+    /// is seeded with `label` as a comment (i.e. every line of the label
+    /// prefixed with `# `) followed by `initial` value.  Once the user finishes
+    /// editing, all lines starting with `#` are removed and the rest trimmed of
+    /// white space.  If the user clears the file, an empty string is returned
+    /// and it’s up to the caller to decide whether that’s valid.  As with
+    /// [`buffer_string_lossy`](`Self::buffer_string_lossy`), invalid UTF-8 is
+    /// replaced with U+FFFD REPLACEMENT CHARACTER.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let name = run_editor::edit().prompt("Enter your name:", "Lex").unwrap();
+    /// println!("Hello, {name}!");
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn prompt(&self, label: &str, initial: &str) -> Result<String, Error> {
+        let mut seed = String::new();
+        for line in label.lines() {
+            seed.push_str("# ");
+            seed.push_str(line);
+            seed.push('\n');
+        }
+        seed.push_str(initial);
+        seed.push('\n');
+        let value = self.buffer_string_lossy(seed)?;
+        let value = value
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(value.trim().to_owned())
+    }
+
+    /// Lets user edit a buffer and parses the result falling back to default
+    /// if the user cleared it.
+    ///
+    /// This is meant for editing configuration where saving an empty buffer
+    /// means ‘keep the defaults’.  The `seed` is edited as with
+    /// [`buffer`](`Self::buffer`).  If the result is empty or consists of
+    /// white space only, `on_empty` is called to provide the value.  Otherwise
+    /// the result is passed to `parse`.
+    ///
+    /// If `parse` fails, the editor is run again on the rejected content so
+    /// that the user can fix it.  This is repeated up to the number of times
+    /// configured with [`with_max_attempts`](`Self::with_max_attempts`) after
+    /// which the last parse error is returned.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let seed = b"# Enter number of jobs or leave empty for default\n";
+    /// let jobs = run_editor::edit().edit_or_default(
+    ///     seed.to_vec(),
+    ///     |buf| String::from_utf8_lossy(buf).trim().parse::<u32>(),
+    ///     || 4,
+    /// );
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn edit_or_default<T, E: std::fmt::Display>(
+        &self,
+        seed: Vec<u8>,
+        mut parse: impl FnMut(&[u8]) -> Result<T, E>,
+        on_empty: impl FnOnce() -> T,
+    ) -> Result<T, Error> {
+        let mut on_empty = Some(on_empty);
+        imp::edit_buffer_until(self, seed, |buf| {
+            if imp::is_blank(buf) {
+                Ok(on_empty.take().unwrap()())
+            } else {
+                parse(buf)
+            }
+        })
+    }
+
+    /// Modifies a file non-interactively with a sed-like expression.
+    ///
+    /// This is a safe and atomic equivalent of `sed -i -e expr path`.  The
+    /// filter (`sed -e` by default; see
+    /// [`with_apply_filter`](`Self::with_apply_filter`)) is run with `expr`
+    /// and `path` as arguments and whatever it writes to standard output
+    /// atomically replaces the file (as with [`edit_as`](`Self::edit_as`)).
+    /// If the filter fails, an error is returned and the file is not touched.
+    /// No editor is run so none of the editor resolution settings apply.
+    ///
+    /// ```no_run
+    /// run_editor::edit()
+    ///     .apply("/home/lex/.shellrc", "s/^EDITOR=.*/EDITOR=vi/")
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn apply(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        expr: &str,
+    ) -> Result<(), Error> {
+        imp::apply(self, path.as_ref(), expr)
+    }
+
+    /// Lets user edit a file and replaces it only once the result is valid.
+    ///
+    /// This bundles the usual ‘edit configuration, validate it and only then
+    /// replace the live file’ flow into one call.  Contents of the file at
+    /// `path` (or an empty buffer if it doesn’t exist) are edited as with
+    /// [`buffer`](`Self::buffer`) and the result passed to `validate`.  If it
+    /// fails, the editor is run again on the rejected content as with
+    /// [`edit_or_default`](`Self::edit_or_default`).  Only once `validate`
+    /// succeeds, the file is atomically replaced with the result (as with
+    /// [`edit_as`](`Self::edit_as`)) and the value returned by `validate` is
+    /// returned.
+    ///
+    /// The file at `path` is never touched until then, so if editing fails,
+    /// is aborted or the user runs out of attempts, the live file stays as it
+    /// was.
+    ///
+    /// ```no_run
+    /// let path = "/home/lex/.config/app/jobs";
+    /// let jobs = run_editor::edit().transact(path, |buf| {
+    ///     String::from_utf8_lossy(buf).trim().parse::<u32>()
+    /// });
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn transact<T, E: std::fmt::Display>(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        mut validate: impl FnMut(&[u8]) -> Result<T, E>,
+    ) -> Result<T, Error> {
+        use error::WithPathContext;
+
+        let path = path.as_ref();
+        let seed = match std::fs::read(path) {
+            Ok(seed) => seed,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Vec::new()
+            }
+            Err(err) => return Err(err.with_path_ctx(path)),
+        };
+        let (value, buf) = imp::edit_buffer_until(self, seed, |buf| {
+            validate(buf).map(|value| (value, buf.to_vec()))
+        })?;
+        imp::write_atomic(self, path, &buf)?;
+        Ok(value)
+    }
+
+    /// Lets user edit textual representation of a value and parses the result.
+    ///
+    /// The value is formatted using its [`Display`](`std::fmt::Display`)
+    /// implementation followed by a new line and the result edited as with
+    /// [`buffer`](`Self::buffer`).  Once the editor terminates, the contents
+    /// (converted to a string replacing invalid UTF-8 sequences with U+FFFD
+    /// REPLACEMENT CHARACTER and with trailing new line removed) are passed to
+    /// `parse`.  If it fails, the editor is run again on the rejected content
+    /// as with [`edit_or_default`](`Self::edit_or_default`).
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let jobs: u32 = 4;
+    /// let jobs = run_editor::edit()
+    ///     .buffer_display(&jobs, |text| text.trim().parse())
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer_display<T, E, F>(
+        &self,
+        value: &T,
+        mut parse: F,
+    ) -> Result<T, Error>
+    where
+        T: std::fmt::Display,
+        E: std::fmt::Display,
+        F: FnMut(&str) -> Result<T, E>, {
+        let seed = format!("{}\n", value).into_bytes();
+        imp::edit_buffer_until(self, seed, |buf| {
+            let text = String::from_utf8_lossy(buf);
+            let text = text.strip_suffix('\n').unwrap_or(&text);
+            parse(text.strip_suffix('\r').unwrap_or(text))
+        })
+    }
+
+    /// Lets user edit the file failing if it’s modified by someone else in the
+    /// meantime.
+    ///
+    /// To tell user’s edits apart from external modifications, the editor is
+    /// run on a copy of the file located in the same directory.  Size and
+    /// modification time of the original file are recorded beforehand and once
+    /// the editor terminates checked again.  If they haven’t changed, the copy
+    /// atomically replaces the original (as with [`atomic`](`Self::atomic`)).
+    /// Otherwise, the original is left as is and an error is returned.  So that
+    /// user’s work isn’t lost, the edited copy is kept and its path included in
+    /// the error message.
+    ///
+    /// Unlike [`file`](`Self::file`), the file must exist.  Note that changes
+    /// which preserve both size and modification time of the file (which may
+    /// have coarse granularity on some file systems) are not detected.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// if let Err(err) = run_editor::edit().file_guarded("/etc/app.conf") {
+    ///     eprintln!("{err}");
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn file_guarded(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        match self.editor_unless_nop()? {
+            Some(editor) => {
+                imp::check_file(self, path)?;
+                imp::edit_file_guarded(self, editor, path)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Lets user edit a marked region of the file leaving the rest intact.
+    ///
+    /// This is useful for blocks managed by an application inside of
+    /// configuration files written by users.  The region consists of lines
+    /// between the first line equal to `begin` and the first following line
+    /// equal to `end` (trailing white space is ignored when comparing lines).
+    /// The marker lines themselves are not part of the region.  Only the region
+    /// is given to the editor and once the editor succeeds, the edited region
+    /// is spliced back and the whole file atomically replaced (as with
+    /// [`atomic`](`Self::atomic`)).  If the edited region is non-empty and
+    /// doesn’t end with a new line character, one is added so that the end
+    /// marker stays on its own line.
+    ///
+    /// If either marker is missing, an error is returned without running the
+    /// editor.  The file must exist.  Note that modifications of the file made
+    /// by others while the user is editing the region are lost.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// run_editor::edit()
+    ///     .file_region("/home/lex/.bashrc", "# BEGIN app", "# END app")
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn file_region(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        begin: &str,
+        end: &str,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        match self.editor_unless_nop()? {
+            Some(editor) => {
+                imp::check_file(self, path)?;
+                imp::edit_file_region(self, editor, path, begin, end)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Lets user edit the file keeping numbered backups of previous versions.
+    ///
+    /// This gives a simple version history without a version control system.
+    /// The editor is run on a copy of the file and once it succeeds, if the
+    /// contents have changed, backups are rotated and the copy atomically
+    /// replaces the original (as with [`atomic`](`Self::atomic`)).  Backups
+    /// are named by appending a number to the file name, e.g. `config.1`,
+    /// `config.2` and so on with `config.1` being the most recent.
+    ///
+    /// Rotation works as follows: `<path>.<keep>`, if it exists, is deleted
+    /// and then for every `n` from `keep - 1` down to 1 `<path>.<n>`, if it
+    /// exists, is renamed to `<path>.<n + 1>`.  Finally, contents of the file
+    /// before editing are copied to `<path>.1`.  As a result, at most `keep`
+    /// most recent versions are kept.  Backups numbered above `keep` (e.g.
+    /// left over from earlier runs with larger `keep`) are not touched.
+    ///
+    /// If editing fails or the user doesn’t change the file, no rotation is
+    /// performed.  If the file doesn’t exist, it’s edited in place and no
+    /// backup is made.  With `keep` equal zero this behaves like
+    /// [`file`](`Self::file`) with [`atomic`](`Self::atomic`) option enabled.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// // Keeps /etc/app.conf.1 through /etc/app.conf.5.
+    /// run_editor::edit().file_with_rotation("/etc/app.conf", 5).unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn file_with_rotation(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        keep: usize,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        match self.editor_unless_nop()? {
+            Some(editor) => {
+                imp::check_file(self, path)?;
+                imp::edit_file_rotated(self, editor, path, keep)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Lets user edit a copy of the file and applies the changes if there are
+    /// any.
+    ///
+    /// The file is copied to a temporary file in the same directory and the
+    /// user edits the copy.  Once the editor terminates, the copy is compared
+    /// line by line with the original.  If the contents are the same, the copy
+    /// is discarded and `None` returned.  Otherwise the copy atomically
+    /// replaces the original (as with [`atomic`](`Self::atomic`)) and the diff
+    /// is returned so that the tool can show the user what it changed or log
+    /// the change.
+    ///
+    /// The returned diff includes all lines of the file, both changed and
+    /// unchanged.  The file must exist.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// use run_editor::DiffLine;
+    ///
+    /// match run_editor::edit().file_review("/etc/hosts") {
+    ///     Ok(None) => eprintln!("no changes"),
+    ///     Ok(Some(diff)) => {
+    ///         for line in diff {
+    ///             let (sign, line) = match &line {
+    ///                 DiffLine::Removed(line) => ('-', line),
+    ///                 DiffLine::Added(line) => ('+', line),
+    ///                 DiffLine::Unchanged(line) => (' ', line),
+    ///             };
+    ///             eprint!("{sign}{}", String::from_utf8_lossy(line));
+    ///         }
+    ///     }
+    ///     Err(err) => eprintln!("{err}"),
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn file_review(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Option<Vec<DiffLine>>, Error> {
+        let path = path.as_ref();
+        match self.editor_unless_nop()? {
+            Some(editor) => {
+                imp::check_file(self, path)?;
+                imp::edit_file_review(self, editor, path)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Copies file from `src` to `dst` letting user edit it.
+    ///
+    /// This is a bit like first copying the file and then running
+    /// [`file`](`Self::file`) on it except that this method performs atomic
+    /// write and on failure the destination location is not affected at all.
+    /// That is, on failure if the destination file didn’t exist, it won’t be
+    /// created and if it existed it won’t be edited.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let template_path = "/etc/skel/.bashrc";
+    /// let destination_path = "/home/lex/.bashrc";
+    /// let res = run_editor::edit().file_copy(template_path, destination_path);
+    /// if let Err(err) = res {
+    ///     eprintln!("{err}")
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn file_copy(
+        &self,
+        src: impl AsRef<std::path::Path>,
+        dst: impl AsRef<std::path::Path>,
+    ) -> Result<(), Error> {
+        let temp = self.copy_temp(src.as_ref(), dst.as_ref())?;
+        self.file(&*temp)?;
+        imp::set_result_mode(self, &temp, dst.as_ref())?;
+        imp::persist(temp, dst.as_ref())?;
+        Ok(())
+    }
+
+    /// Lets user edit contents of `src` and atomically writes the result to
+    /// `dst`.
+    ///
+    /// Contents of `src` are read into memory and edited as with
+    /// [`buffer`](`Self::buffer`), i.e. the editor is run on a temporary file
+    /// in the usual temporary directory rather than next to `dst`.  Only once
+    /// editing succeeds, the result is written to `dst` atomically (through
+    /// a temporary file in the same directory which then replaces `dst`).  On
+    /// failure `dst` is not affected at all.  If `dst` exists, its permissions
+    /// (and ownership if possible) are preserved; otherwise the file is created
+    /// as with [`file_copy`](`Self::file_copy`).
+    ///
+    /// `src` and `dst` may be the same path in which case this is an atomic in
+    /// place edit.  If `dst` already has the edited contents (in particular if
+    /// user made no changes during in place edit), it’s not rewritten.
+    ///
+    /// If `src` is `-`, contents are read from standard input instead.  Since
+    /// the editor’s standard input is then not a terminal, this is usually
+    /// combined with [`use_dev_tty`](`Self::use_dev_tty`).
+    ///
+    /// ```no_run
+    /// let (src, dst) = ("/etc/skel/.bashrc", "/home/lex/.bashrc");
+    /// let res = run_editor::edit().edit_as(src, dst);
+    /// if let Err(err) = res {
+    ///     eprintln!("{err}")
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn edit_as(
+        &self,
+        src: impl AsRef<std::path::Path>,
+        dst: impl AsRef<std::path::Path>,
+    ) -> Result<(), Error> {
+        let editor = self.editor_unless_nop()?;
+        imp::edit_as(self, editor, src.as_ref(), dst.as_ref())
+    }
+
+    /// Like [`file_copy`](`Self::file_copy`) but reports what has happened to
+    /// the destination.
+    ///
+    /// This lets tools tell the user whether they ‘created ~/.bashrc’ or
+    /// ‘updated ~/.bashrc’.  See [`CopyOutcome`] for description of reported
+    /// information.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let dst = "/home/lex/.bashrc";
+    /// match run_editor::edit().file_copy_outcome("/etc/skel/.bashrc", dst) {
+    ///     Ok(outcome) if outcome.created => eprintln!("created {dst}"),
+    ///     Ok(_) => eprintln!("updated {dst}"),
+    ///     Err(err) => eprintln!("{err}"),
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn file_copy_outcome(
+        &self,
+        src: impl AsRef<std::path::Path>,
+        dst: impl AsRef<std::path::Path>,
+    ) -> Result<CopyOutcome, Error> {
+        use error::WithPathContext;
+
+        let (src, dst) = (src.as_ref(), dst.as_ref());
+        let created = !dst.try_exists().with_path_ctx(dst)?;
+        let temp = self.copy_temp(src, dst)?;
+        self.file(&*temp)?;
+        let changed = !imp::same_contents(src, &temp)?;
+        imp::set_result_mode(self, &temp, dst)?;
+        imp::persist(temp, dst)?;
+        Ok(CopyOutcome { created, changed })
+    }
+
+    /// Like [`file_copy`](`Self::file_copy`) but returns contents of the file
+    /// before and after editing.
+    ///
+    /// The first element of the returned pair is the contents copied from
+    /// `src` (i.e. what the user saw when the editor started) and the second
+    /// is the edited contents which were persisted at `dst`.  Both are read
+    /// from the temporary file so they reflect what was actually copied and
+    /// written even if `src` or `dst` are modified concurrently.
+    ///
+    /// ```no_run
+    /// let (before, after) = run_editor::edit()
+    ///     .file_copy_capture("/etc/skel/.bashrc", "/home/lex/.bashrc")
+    ///     .unwrap();
+    /// if before != after {
+    ///     eprintln!("customised .bashrc");
+    /// }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn file_copy_capture(
+        &self,
+        src: impl AsRef<std::path::Path>,
+        dst: impl AsRef<std::path::Path>,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        use error::WithPathContext;
+
+        let temp = self.copy_temp(src.as_ref(), dst.as_ref())?;
+        let before = std::fs::read(&temp).with_path_ctx(&*temp)?;
+        self.file(&*temp)?;
+        let after = std::fs::read(&temp).with_path_ctx(&*temp)?;
+        imp::set_result_mode(self, &temp, dst.as_ref())?;
+        imp::persist(temp, dst.as_ref())?;
+        Ok((before, after))
+    }
+
+    /// Copies source file into a temporary file next to destination reporting
+    /// progress to function set with
+    /// [`on_copy_progress`](`Self::on_copy_progress`).
+    #[cfg(feature = "with_tempfile")]
+    fn copy_temp(
+        &self,
+        src: &std::path::Path,
+        dst: &std::path::Path,
+    ) -> Result<imp::TempFile, Error> {
+        let mut progress =
+            self.copy_progress.as_ref().map(|cb| cb.borrow_mut());
+        let progress =
+            progress.as_mut().map(|cb| &mut **cb as &mut dyn FnMut(u64));
+        imp::copy_temp(self, src, dst, progress)
+    }
+
+    /// Executes text editor on a remote host letting user modify a file there.
+    ///
+    /// The editor is run through `ssh -t` which allocates a terminal on the
+    /// remote host so that interactive editors work.  That is, with `vi` as the
+    /// editor, `ssh -t -- <host> "vi '<path>'"` is executed.  The `path` is
+    /// quoted so that the remote shell interprets it literally.
+    ///
+    /// The editor command is the one set with
+    /// [`with_remote_editor`](`Self::with_remote_editor`) or, if none is set,
+    /// the local editor resolved as described in [`editor`](`Self::editor`).
+    /// In the latter case, if the command is a no-op, nothing is executed.
+    /// Note that the remote editor is executed by user’s login shell on the
+    /// remote host; the `ssh` itself is executed directly.
+    ///
+    /// Example usage:
+    ///
+    /// ```no_run
+    /// let res = run_editor::edit().remote("web1", "/etc/nginx/nginx.conf");
+    /// if let Err(err) = res {
+    ///     eprintln!("{err}");
+    /// }
+    /// ```
+    ///
+    /// This requires `ssh` Cargo feature to be enabled.
+    #[cfg(feature = "ssh")]
+    pub fn remote(
+        &self,
+        host: impl AsRef<OsStr>,
+        path: impl AsRef<OsStr>,
+    ) -> Result<(), Error> {
+        let editor = match self.remote_editor {
+            Some(editor) => editor.to_owned(),
+            None => match self.editor_unless_nop()? {
+                Some(editor) => editor,
+                None => return Ok(()),
+            },
+        };
+        imp::edit_remote(self, editor, host.as_ref(), path.as_ref())
+    }
+
+    /// Returns program and arguments which would be spawned to edit remote
+    /// file; see [`remote`](`Self::remote`).
+    ///
+    /// Like [`audit_command`](`Self::audit_command`), this has no side effects
+    /// and doesn’t apply the editor filter.
+    ///
+    /// ```
+    /// let (program, args) = run_editor::edit()
+    ///     .with_remote_editor(std::ffi::OsStr::new("vim"))
+    ///     .remote_command("web1", "/etc/motd");
+    /// assert_eq!("ssh", program);
+    /// assert_eq!(["-t", "--", "web1", "vim '/etc/motd'"], args.as_slice());
+    /// ```
+    ///
+    /// This requires `ssh` Cargo feature to be enabled.
+    #[cfg(feature = "ssh")]
+    pub fn remote_command(
+        &self,
+        host: impl AsRef<OsStr>,
+        path: impl AsRef<OsStr>,
+    ) -> (OsString, Vec<OsString>) {
+        let editor =
+            self.remote_editor.map_or_else(|| self.editor(), OsStr::to_owned);
+        imp::remote_command_line(&editor, host.as_ref(), path.as_ref())
+    }
+
+    /// Returns the editor command to use to let user edit files.
+    ///
+    /// The resolution of the editor command is goes as follows:
+    /// 0. If process-wide override has been set via [`set_global_editor`],
+    ///    use that command.
+    /// 1. Otherwise, if variable name has been provided via
+    ///    [`with_editor_variable`](`Self::with_editor_variable`) and such
+    ///    environment variable is set, use its value.
+    /// 2. Otherwise, if suffix of temporary files has been set with
+    ///    [`with_suffix`](`Self::with_suffix`) and an editor has been
+    ///    configured for it with
+    ///    [`with_filetype_editor`](`Self::with_filetype_editor`), use that
+    ///    editor.
+    /// 3. Otherwise, if editor command has been provided via
+    ///    [`with`](`Self::with`) method, use that command.
+    /// 4. Otherwise, if reader has been provided via
+    ///    [`with_command_from_reader`](`Self::with_command_from_reader`) and
+    ///    its first line is not blank, use that line.
+    /// 5. Otherwise, use system-dependent method for determining user
+    ///    preferences.  At the moment that means reading `VISUAL` and `EDITOR`
+    ///    environment variables.
+    /// 6. If that fails as well, use system-dependent default.  At the moment
+    ///    that means `"vi"` which should be available on any Unix system.
+    ///    The default can be disabled with
+    ///    [`use_default_editor`](`Self::use_default_editor`) in which case
+    ///    editing fails; see [`try_editor`](`Self::try_editor`).
+    ///
+    /// Note that returned string is a *command*.  This means that it needs to
+    /// be executed through a shell with file to be edited *properly escaped*
+    /// and appended to the command.  Or better still, quoted environment
+    /// variable name appended and the file path passed as that variable.  For
+    /// example, on Unix systems one might execute:
+    ///
+    /// ```
+    /// // File to let user edit:
+    /// let path = "/home/lex/.shellrc";
+    ///
+    /// // Determine command to execute; pass path as variable so we don’t need
+    /// // to worry about escaping:
+    /// let mut cmd = run_editor::edit().editor();
     /// cmd.push(" \"$TMP_FILE_PATH\"");
     ///
-    /// let mut cmd = std::process::Command::new("sh")
-    ///     // Don’t forget to pass the variable:
-    ///     .env("TMP_FILE_PATH", path)
-    ///     .arg("-c")
-    ///     .arg(cmd);
+    /// let mut cmd = std::process::Command::new("sh")
+    ///     // Don’t forget to pass the variable:
+    ///     .env("TMP_FILE_PATH", path)
+    ///     .arg("-c")
+    ///     .arg(cmd);
+    /// ```
+    ///
+    /// Normally, you just want to use [`file`](`Self::file`) method instead
+    /// which handles all that for you.
+    pub fn editor(&self) -> OsString {
+        self.configured_editor().unwrap_or_else(|| OsString::from("vi"))
+    }
+
+    /// Returns the editor command to use to let user edit files or an error if
+    /// it’s not usable.
+    ///
+    /// The command is resolved as described in [`editor`](`Self::editor`) but
+    /// rather than always returning something, configuration problems are
+    /// reported as errors:
+    /// * if no editor command is configured and falling back to `vi` has been
+    ///   disabled with [`use_default_editor`](`Self::use_default_editor`),
+    ///   returns an error of kind [`ErrorKind::NotConfigured`] and
+    /// * if the command is empty or consists of white space only (e.g. because
+    ///   `EDITOR` is set to an empty string), returns an error of kind
+    ///   [`ErrorKind::EmptyCommand`].
+    ///
+    /// The same checks are performed before running the editor so this is
+    /// a way to validate the configuration without launching anything.
+    ///
+    /// ```
+    /// let res = run_editor::edit().use_default_editor(false).try_editor();
+    /// match res {
+    ///     Ok(editor) => println!("using {}", editor.to_string_lossy()),
+    ///     Err(err) => eprintln!("{err}; set EDITOR variable"),
+    /// }
+    /// ```
+    pub fn try_editor(&self) -> Result<OsString, Error> {
+        match self.configured_editor() {
+            Some(editor)
+                if shell::to_bytes(&editor)
+                    .iter()
+                    .all(u8::is_ascii_whitespace) =>
+            {
+                Err(Error(error::Inner::EmptyCommand))
+            }
+            Some(editor) => Ok(editor),
+            None if self.use_default_editor => Ok(OsString::from("vi")),
+            None => Err(Error(error::Inner::NotConfigured)),
+        }
+    }
+
+    /// Returns names of environment variables consulted when resolving the
+    /// editor command.
+    ///
+    /// This is [`relevant_env_vars`](`crate::relevant_env_vars`) preceded by
+    /// the variable specified with
+    /// [`with_editor_variable`](`Self::with_editor_variable`) if any.  As with
+    /// the free function, the variables are listed in order of priority.
+    ///
+    /// ```
+    /// use std::ffi::OsStr;
+    ///
+    /// let mut edit = run_editor::edit();
+    /// edit.with_editor_variable(OsStr::new("FOO_EDITOR"));
+    /// assert_eq!(
+    ///     ["FOO_EDITOR", "VISUAL", "EDITOR"].map(OsStr::new).as_slice(),
+    ///     edit.relevant_env_vars().as_slice()
+    /// );
+    /// ```
+    pub fn relevant_env_vars(&self) -> Vec<&OsStr> {
+        self.editor_variable.into_iter().chain(relevant_env_vars()).collect()
+    }
+
+    /// Returns the editor command if one is configured; this is
+    /// [`editor`](`Self::editor`) without the system-dependent default.
+    fn configured_editor(&self) -> Option<OsString> {
+        global_editor()
+            .or_else(|| self.editor_variable.and_then(std::env::var_os))
+            .or_else(|| self.filetype_editor())
+            .or_else(|| self.editor_command.map(OsString::from))
+            .or_else(|| self.command_from_reader())
+            .or_else(|| std::env::var_os("VISUAL"))
+            .or_else(|| std::env::var_os("EDITOR"))
+    }
+
+    /// Returns editor command configured for the suffix of temporary files; see
+    /// [`with_filetype_editor`](`Self::with_filetype_editor`).
+    fn filetype_editor(&self) -> Option<OsString> {
+        #[cfg(feature = "with_tempfile")]
+        if let Some(suffix) = &self.suffix {
+            return self
+                .filetype_editors
+                .iter()
+                .find(|entry| entry.0 == *suffix)
+                .map(|entry| entry.1.clone());
+        }
+        None
+    }
+
+    /// Returns editor command read from reader set with
+    /// [`with_command_from_reader`](`Self::with_command_from_reader`) reading
+    /// it if that hasn’t been done yet.
+    fn command_from_reader(&self) -> Option<OsString> {
+        let mut reader = self.command_reader.as_ref()?.borrow_mut();
+        if let CommandReader::Pending(rd) = &mut *reader {
+            *reader = CommandReader::Done(imp::read_command(rd));
+        }
+        match &*reader {
+            CommandReader::Done(command) => command.clone(),
+            CommandReader::Pending(_) => unreachable!(),
+        }
+    }
+
+    /// Returns program of the editor command, i.e. the command without its
+    /// arguments.
+    ///
+    /// The editor command is resolved as described in [`editor`](`Self::editor`)
+    /// and split into words the way a shell would so quoted programs are
+    /// handled correctly.  This is useful for ‘you are using: vim’ messages.
+    /// If the command cannot be split (e.g. because of an unterminated quote)
+    /// or is empty, the whole command is returned.
+    ///
+    /// ```
+    /// let editor = std::ffi::OsStr::new("'/opt/my editor/bin/edit' --wait");
+    /// let program = run_editor::edit().with(editor).editor_program();
+    /// assert_eq!("/opt/my editor/bin/edit", program);
+    /// ```
+    pub fn editor_program(&self) -> OsString {
+        let editor = self.editor();
+        shell::program(&editor).unwrap_or(editor)
+    }
+
+    /// Returns program and arguments which would be spawned to edit given file.
+    ///
+    /// This is meant for deployments which audit or allow-list executed
+    /// subprocesses.  Unlike [`editor`](`Self::editor`), the result includes
+    /// the shell wrapper used to run the editor command and the path to edit.
+    /// For example, with `vi` as the editor on Unix systems the returned value
+    /// is `/bin/sh` and arguments `-c`, `vi "$@"`, `sh` followed by the path.
+    /// If [`with_path_var`](`Self::with_path_var`) is used, the path is passed
+    /// through the environment and thus is not included in the arguments.
+    ///
+    /// The editor command is resolved the same way [`file`](`Self::file`)
+    /// resolves it, i.e. it’s passed through the filter set with
+    /// [`with_editor_filter`](`Self::with_editor_filter`).  Error is returned
+    /// if the command is empty or the filter rejects it.
+    ///
+    /// The method has no side effects.  Note that if the editor command is
+    /// a no-op (see [`file`](`Self::file`)), nothing is spawned when editing
+    /// files.
+    ///
+    /// ```
+    /// let edit = run_editor::edit();
+    /// let (program, args) = edit.audit_command("/tmp/file").unwrap();
+    /// eprintln!("running {:?} with {:?}", program, args);
+    /// ```
+    pub fn audit_command(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(OsString, Vec<OsString>), Error> {
+        let editor = self.filtered_editor()?;
+        Ok(imp::command_line(self, &editor, path.as_ref()))
+    }
+
+    /// Returns command which would be spawned to edit given file as a single
+    /// line suitable for logging.
+    ///
+    /// This is the same invocation as returned by
+    /// [`audit_command`](`Self::audit_command`) (i.e. including the shell
+    /// wrapper and the path) joined into a string with words quoted as needed.
+    /// In the common case, the result can be pasted into a shell to rerun the
+    /// editor.  However, the string is meant for humans: invalid UTF-8
+    /// sequences are replaced by U+FFFD REPLACEMENT CHARACTER and control
+    /// characters (such as new lines) are escaped so the result always fits
+    /// a single line.
+    ///
+    /// Fails under the same conditions as `audit_command`.
+    ///
+    /// ```
+    /// let line = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new("vi"))
+    ///     .command_display("/tmp/some file")
+    ///     .unwrap();
+    /// # if cfg!(unix) {
+    /// assert_eq!("/bin/sh -c 'vi \"$@\"' sh '/tmp/some file'", line);
+    /// # }
+    /// ```
+    pub fn command_display(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<String, Error> {
+        let (program, args) = self.audit_command(path)?;
+        Ok(shell::display(
+            std::iter::once(&program).chain(&args).map(OsString::as_os_str),
+        ))
+    }
+
+    /// Returns whether the editor runs inside of a terminal.
+    ///
+    /// The editor command is resolved as described in [`editor`](`Self::editor`)
+    /// and its program is compared against a built-in list of known editors.
+    /// Returns `Some(true)` for terminal editors such as `vi` or `nano`,
+    /// `Some(false)` for graphical editors such as `code` or `gedit` and
+    /// `None` if the editor is not known or whether it uses a terminal depends
+    /// on its options (as is the case with `emacs`).
+    ///
+    /// This may be used to decide whether to clear the screen or show
+    /// a spinner while the user is editing a file.
+    ///
+    /// ```
+    /// let edit = run_editor::edit();
+    /// if edit.is_terminal_editor() == Some(false) {
+    ///     eprintln!("Waiting for your editor to close the file...");
+    /// }
+    /// ```
+    pub fn is_terminal_editor(&self) -> Option<bool> {
+        profile::find(&self.editor())?.terminal
+    }
+
+    /// Returns known peculiarities of the editor.
+    ///
+    /// The editor command is resolved as described in [`editor`](`Self::editor`)
+    /// and its program is compared against a built-in list of known editors.
+    /// Applications can use the result to warn users about configurations
+    /// which are unlikely to work, e.g. `code` without `--wait` option which
+    /// makes it return before the file is edited.
+    ///
+    /// ```
+    /// let quirks = run_editor::edit().editor_quirks();
+    /// if quirks.needs_wait {
+    ///     eprintln!("Your editor doesn’t wait; consider adding --wait.");
+    /// }
+    /// ```
+    pub fn editor_quirks(&self) -> EditorQuirks {
+        profile::quirks(&self.editor())
+    }
+
+    /// Returns whether editing would be a no-op.
+    ///
+    /// That is the case if the resolved editor command (after being passed
+    /// through [`with_editor_filter`](`Self::with_editor_filter`) filter if
+    /// any) is `:` or `true`, in which case methods such as
+    /// [`file`](`Self::file`) or [`buffer`](`Self::buffer`) don’t run anything
+    /// and leave the data unchanged.  Applications can use this to skip any
+    /// setup needed for editing.
+    ///
+    /// Returns `false` if an in-process editor is set (see
+    /// [`with_inprocess_editor`](`Self::with_inprocess_editor`)) or if the
+    /// editor command cannot be resolved (e.g. with
+    /// [`use_default_editor`](`Self::use_default_editor`) disabled and no
+    /// editor configured); in the latter case editing methods return an error.
+    ///
+    /// ```
+    /// use std::ffi::OsStr;
+    ///
+    /// let mut edit = run_editor::edit();
+    /// assert!(edit.with(OsStr::new(":")).is_noop());
+    /// ```
+    pub fn is_noop(&self) -> bool {
+        matches!(self.editor_unless_nop(), Ok(None))
+    }
+
+    /// Resolves the editor and checks that it can be executed.
+    ///
+    /// This front-loads checks which would otherwise fail only once the editor
+    /// is run.  In particular, if the editor is misconfigured, no temporary
+    /// files are created.  The editor command is resolved as described in
+    /// [`editor`](`Self::editor`), its program (i.e. first word) is looked up
+    /// in `PATH` (unless it contains a slash) and must be an executable file.
+    /// No-op commands (see [`file`](`Self::file`)) are always accepted.
+    ///
+    /// Note that the check assumes the editor command is a simple command.
+    /// Commands which use shell syntax such as function definitions will be
+    /// rejected.
+    ///
+    /// The files are then edited with methods of the returned [`Prepared`]
+    /// object.  For example:
+    ///
+    /// ```no_run
+    /// let edit = run_editor::edit();
+    /// let prepared = match edit.prepare() {
+    ///     Ok(prepared) => prepared,
+    ///     Err(err) => return eprintln!("{err}"),
+    /// };
+    /// let buf = prepared.buffer(b"Some value to edit".to_vec()).unwrap();
+    /// ```
+    pub fn prepare(&self) -> Result<Prepared<'_, 'a>, Error> {
+        let editor = self.editor_unless_nop()?;
+        if let Some(editor) = &editor {
+            if !imp::replaces_editor(self) && self.launcher.is_empty() {
+                imp::check_editor(editor)?;
+            }
+        }
+        Ok(Prepared { edit: self, editor })
+    }
+
+    /// Converts the object into one which doesn’t borrow any data.
+    ///
+    /// This is useful when configuration is built from borrowed data but needs
+    /// to outlive it, e.g. to be stored in a long-lived structure.  All the
+    /// strings, such as the editor command, are copied.  Functions cannot be
+    /// copied and are dropped; they need to be configured again on the
+    /// [`Edit`] object returned by [`EditOwned::edit`].  Specifically, the
+    /// following settings are lost:
+    /// - editor filter (see
+    ///   [`with_editor_filter`](`Self::with_editor_filter`)),
+    /// - buffer filters (see [`with_pre_filter`](`Self::with_pre_filter`) and
+    ///   [`with_post_filter`](`Self::with_post_filter`)),
+    /// - in-process editor (see
+    ///   [`with_inprocess_editor`](`Self::with_inprocess_editor`)),
+    /// - success predicate (see
+    ///   [`with_success_predicate`](`Self::with_success_predicate`)),
+    /// - soft timeout (see [`with_soft_timeout`](`Self::with_soft_timeout`)),
+    /// - hooks (see [`on_launch`](`Self::on_launch`) and
+    ///   [`on_exit`](`Self::on_exit`)),
+    /// - temporary directory function (see
+    ///   [`with_tempdir_fn`](`Self::with_tempdir_fn`)),
+    /// - copy progress callback (see
+    ///   [`on_copy_progress`](`Self::on_copy_progress`)) and
+    /// - command reader (see
+    ///   [`with_command_from_reader`](`Self::with_command_from_reader`)).
+    ///
+    /// Since dropping editor filter silently changes which editor is run, in
+    /// debug builds the method panics if the filter is set.
+    ///
+    /// ```
+    /// let editor = String::from("nano");
+    /// let owned = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new(&editor))
+    ///     .to_owned();
+    /// drop(editor);
+    /// assert_eq!("nano", owned.edit().editor());
+    /// ```
+    pub fn to_owned(&self) -> EditOwned {
+        debug_assert!(
+            self.editor_filter.is_none(),
+            "editor filter cannot be copied by Edit::to_owned"
+        );
+        EditOwned {
+            base: self.copy_settings(),
+            editor_variable: self.editor_variable.map(OsStr::to_owned),
+            editor_command: self.editor_command.map(OsStr::to_owned),
+            path_variable: self.path_variable.map(OsStr::to_owned),
+            label: self.label.map(OsStr::to_owned),
+            shell: self.shell.map(OsStr::to_owned),
+            shell_fallback: self.shell_fallback.map(OsStr::to_owned),
+            #[cfg(feature = "ssh")]
+            remote_editor: self.remote_editor.map(OsStr::to_owned),
+            #[cfg(unix)]
+            ok_signals: self.ok_signals.to_vec(),
+            #[cfg(unix)]
+            arg0: self.arg0.map(OsStr::to_owned),
+        }
+    }
+
+    /// Returns copy of settings which don’t borrow data.
+    ///
+    /// Fields which borrow data are left with their default values.
+    fn copy_settings<'b>(&self) -> Edit<'b> {
+        Edit {
+            max_attempts: self.max_attempts,
+            retry_delay: self.retry_delay,
+            reopen_attempts: self.reopen_attempts,
+            reopen_delay: self.reopen_delay,
+            path_position: self.path_position,
+            lock: self.lock,
+            lock_wait: self.lock_wait,
+            max_file_size: self.max_file_size,
+            check_writable: self.check_writable,
+            create_parents: self.create_parents,
+            dedup_paths: self.dedup_paths,
+            leading_args: self.leading_args.clone(),
+            editor_args: self.editor_args.clone(),
+            trailing_args: self.trailing_args.clone(),
+            launcher: self.launcher.clone(),
+            #[cfg(unix)]
+            use_dev_tty: self.use_dev_tty,
+            #[cfg(unix)]
+            preserve_mode: self.preserve_mode,
+            timeout: self.timeout,
+            strict_command: self.strict_command,
+            use_default_editor: self.use_default_editor,
+            no_shell: self.no_shell,
+            warn_readonly_editor: self.warn_readonly_editor,
+            readonly_editors: self.readonly_editors.clone(),
+            stdin_fallback: self.stdin_fallback,
+            #[cfg(feature = "with_tempfile")]
+            atomic: self.atomic,
+            #[cfg(all(unix, feature = "with_tempfile"))]
+            result_mode: self.result_mode,
+            #[cfg(feature = "with_tempfile")]
+            strip_bom: self.strip_bom,
+            #[cfg(feature = "with_tempfile")]
+            feed_stdin: self.feed_stdin,
+            #[cfg(feature = "with_tempfile")]
+            post_command: self.post_command.clone(),
+            #[cfg(feature = "with_tempfile")]
+            apply_filter: self.apply_filter.clone(),
+            #[cfg(feature = "with_tempfile")]
+            change_compare: self.change_compare,
+            #[cfg(feature = "with_tempfile")]
+            debug_keep_temps: self.debug_keep_temps,
+            #[cfg(feature = "with_tempfile")]
+            keep_on_error: self.keep_on_error,
+            #[cfg(feature = "with_tempfile")]
+            suffix: self.suffix.clone(),
+            #[cfg(feature = "with_tempfile")]
+            filetype_editors: self.filetype_editors.clone(),
+            ..edit()
+        }
+    }
+
+    /// Returns [`Prepared`] object with resolved editor without checking it.
+    fn unchecked(&self) -> Result<Prepared<'_, 'a>, Error> {
+        Ok(Prepared { edit: self, editor: self.editor_unless_nop()? })
+    }
+
+    /// Returns the editor command passed through the editor filter; see
+    /// [`with_editor_filter`](`Self::with_editor_filter`).
+    fn filtered_editor(&self) -> Result<OsString, Error> {
+        let editor = self.try_editor()?;
+        match &self.editor_filter {
+            Some(filter) => filter(editor),
+            None => Ok(editor),
+        }
+    }
+
+    /// Returns the editor command or `None` if the command is a nop.
+    ///
+    /// Works like [`try_editor`](`Self::try_editor`) except that it applies
+    /// filter set with [`with_editor_filter`](`Self::with_editor_filter`) and
+    /// returns
+    /// `None` if the editor command is `":"` or `"true"`.  If the command is
+    /// set to one of those, the file won’t be edited so there’s no need to
+    /// execute the editor.
+    ///
+    /// If in-process editor is set (see
+    /// [`with_inprocess_editor`](`Self::with_inprocess_editor`)), the command
+    /// is never a no-op, the filter is not applied and the command is not
+    /// validated.
+    fn editor_unless_nop(&self) -> Result<Option<OsString>, Error> {
+        if self.inprocess_editor.is_some() {
+            return Ok(Some(self.editor()));
+        }
+        let editor = self.filtered_editor()?;
+        let bytes = shell::to_bytes(&editor);
+        if is_nop(&editor) {
+            Ok(None)
+        } else if (self.strict_command || self.no_shell) &&
+            shell::split(&bytes).is_none()
+        {
+            Err(Error(error::Inner::MalformedCommand { editor }))
+        } else if self.no_shell && shell::needs_shell(&bytes) {
+            Err(Error(error::Inner::ShellForbidden { editor }))
+        } else if self.warn_readonly_editor && self.is_readonly_editor(&editor)
+        {
+            Err(Error(error::Inner::ReadonlyEditor { editor }))
+        } else {
+            Ok(Some(editor))
+        }
+    }
+
+    /// Returns whether the editor command runs a program which cannot save
+    /// files; see [`warn_readonly_editor`](`Self::warn_readonly_editor`).
+    fn is_readonly_editor(&self, editor: &OsStr) -> bool {
+        let name = match profile::program_name(editor) {
+            Some(name) => name,
+            None => return false,
+        };
+        match &self.readonly_editors {
+            Some(names) => names.contains(&name),
+            None => profile::READONLY_EDITORS.contains(&name.as_str()),
+        }
+    }
+
+    /// Specifies environment variable to read user-preferred editor command
+    /// from.
+    ///
+    /// This is useful in conjunction with [`with`](`Self::with`) to allow easy
+    /// per-command overriding of the application configuration.  For example,
+    /// git supports `core.editor` option but allows that to be overridden with
+    /// `GIT_EDITOR` environment variable, e.g.: `GIT_EDITOR=nano git commit`.
+    ///
+    /// If the variable is set, its value overrides any other methods of
+    /// determining the editor command.  See [`editor`](`Self::editor`) for full
+    /// description of the resolution priorities.
+    #[inline]
+    pub fn with_editor_variable(&mut self, variable: &'a OsStr) -> &mut Self {
+        self.editor_variable = Some(variable);
+        self
+    }
+
+    /// Specifies environment variable to read user-preferred editor command
+    /// from if one is given.
+    ///
+    /// Calling this with `Some` is equivalent to calling
+    /// [`with_editor_variable`](`Self::with_editor_variable`) while `None` is
+    /// a no-op leaving any previously set variable in place.  This lets
+    /// optional configuration be passed in without branching.
+    #[inline]
+    pub fn with_editor_variable_opt(
+        &mut self,
+        variable: Option<&'a OsStr>,
+    ) -> &mut Self {
+        if let Some(variable) = variable {
+            self.editor_variable = Some(variable);
+        }
+        self
+    }
+
+    /// Specifies how many times the editor may be run when edited content is
+    /// rejected by validation.
+    ///
+    /// Methods such as [`edit_or_default`](`Self::edit_or_default`) validate
+    /// what the user has saved and if it’s invalid open the editor again so the
+    /// user can correct their mistakes.  This limits how many times the editor
+    /// is run in total before giving up and returning an error.  Value of zero
+    /// is treated as one.  By default the editor is run at most three times.
+    #[inline]
+    pub fn with_max_attempts(&mut self, attempts: usize) -> &mut Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Specifies how long to wait before rerunning the editor when edited
+    /// content is rejected by validation.
+    ///
+    /// If the editor is not interactive and returns immediately, validation
+    /// loops (see [`with_max_attempts`](`Self::with_max_attempts`)) could
+    /// otherwise relaunch it in a tight loop.  With an interactive editor the
+    /// delay is unnoticeable.  By default the delay is 250 milliseconds.
+    #[inline]
+    pub fn with_retry_delay(
+        &mut self,
+        delay: std::time::Duration,
+    ) -> &mut Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Configures retrying reading back temporary file after editing.
+    ///
+    /// When editing a buffer, the temporary file is opened again once the
+    /// editor exits.  With some network file systems or unusual editors the
+    /// file may be briefly missing at that point, e.g. if the editor’s atomic
+    /// rename is not yet visible.  With this option, if opening the file fails
+    /// with [`std::io::ErrorKind::NotFound`] error, the operation is retried
+    /// waiting `delay` between attempts up to `attempts` times in total.
+    ///
+    /// By default a single attempt is made.  Value of zero is treated as one.
+    #[inline]
+    pub fn reopen_retry(
+        &mut self,
+        attempts: usize,
+        delay: std::time::Duration,
+    ) -> &mut Self {
+        self.reopen_attempts = attempts;
+        self.reopen_delay = delay;
+        self
+    }
+
+    /// Specifies where path of the file to edit is placed in the command line.
+    ///
+    /// By default, the path is appended at the end of editor command, e.g. with
+    /// `wrapper --flag` as the editor, `wrapper --flag <path>` is executed.
+    /// Some wrappers expect the file first and with [`PathPosition::First`]
+    /// `wrapper <path> --flag` is executed instead.
+    ///
+    /// The program name is determined by splitting the editor command as a shell
+    /// would.  If the command cannot be split (e.g. because it has unbalanced
+    /// quotes), the path is appended at the end.
+    #[inline]
+    pub fn path_position(&mut self, position: PathPosition) -> &mut Self {
+        self.path_position = position;
+        self
+    }
+
+    /// Locks the file while it’s being edited to prevent concurrent edits.
+    ///
+    /// With this option enabled, [`file`](`Self::file`) acquires a lock before
+    /// running the editor and releases it once the editor terminates.  If
+    /// another process holds the lock, editing fails immediately with an error
+    /// unless [`lock_wait`](`Self::lock_wait`) is enabled in which case the
+    /// lock is waited for.
+    ///
+    /// The lock is a sibling file whose name is the file’s name with `.lock`
+    /// suffix, e.g. `config.toml.lock` for `config.toml`.  It’s created when
+    /// the lock is acquired (failing if it already exists) and removed when
+    /// it’s released.  If the process is killed while holding the lock, the
+    /// lock file is left behind and needs to be removed manually.  The lock
+    /// only protects against other programs which use the same lock file.
+    ///
+    /// By default no lock is taken.
+    #[inline]
+    pub fn with_lock(&mut self, enable: bool) -> &mut Self {
+        self.lock = enable;
+        self
+    }
+
+    /// Waits for the lock if it’s held by another process.
+    ///
+    /// Has effect only if locking is enabled with
+    /// [`with_lock`](`Self::with_lock`).  When set, rather than failing
+    /// immediately if the file is locked by someone else, editing blocks until
+    /// the lock is released, i.e. until the lock file is removed.
+    ///
+    /// By default this option is disabled.
+    #[inline]
+    pub fn lock_wait(&mut self, enable: bool) -> &mut Self {
+        self.lock_wait = enable;
+        self
+    }
+
+    /// Refuses to edit files larger than given number of bytes.
+    ///
+    /// Accidentally opening a multi-gigabyte log file or a binary in
+    /// a full-screen editor may wedge the terminal.  With this option set,
+    /// [`file`](`Self::file`) checks size of the file before running the editor
+    /// and fails if it exceeds `bytes`.  Files which don’t exist are not
+    /// affected.
+    ///
+    /// By default there is no limit.  Passing `None` removes limit set
+    /// previously.
+    #[inline]
+    pub fn warn_above_size(&mut self, bytes: Option<u64>) -> &mut Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Refuses to edit files the user cannot write to.
+    ///
+    /// Without this option, the editor is happily started on a read-only file
+    /// and the user learns about the problem only when they try to save their
+    /// changes.  With this option enabled, [`file`](`Self::file`) checks
+    /// whether the file can be opened for writing before running the editor
+    /// and fails if access is denied.  Since the check actually opens the file
+    /// (without modifying it), it takes into account everything the operating
+    /// system does, such as ACLs or privileges of the user, rather than just
+    /// permission bits.  Files which don’t exist are not affected.
+    ///
+    /// Only regular files are probed.  If the path is a directory, editing
+    /// fails with [`ErrorKind::IsADirectory`] error.  Other special files,
+    /// such as FIFOs or devices, are not opened (doing so might block or have
+    /// side effects) and are passed to the editor as usual.
+    ///
+    /// By default no check is performed.
+    #[inline]
+    pub fn warn_unwritable(&mut self, enable: bool) -> &mut Self {
+        self.check_writable = enable;
+        self
+    }
+
+    /// Creates missing parent directories of the file before editing it.
+    ///
+    /// Most editors don’t create directories when saving a file so editing
+    /// a file in a directory which doesn’t exist yet (e.g. configuration file
+    /// of an application which is run for the first time) fails only once the
+    /// user tries to save their changes.  With this option enabled,
+    /// [`file`](`Self::file`) creates the directories (as with
+    /// [`std::fs::create_dir_all`]) before running the editor.  Note that the
+    /// directories are created even if the user ends up not saving the file.
+    ///
+    /// By default no directories are created.
+    ///
+    /// ```no_run
+    /// run_editor::edit()
+    ///     .with_create_parents(true)
+    ///     .file("/home/lex/.config/newapp/config")
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn with_create_parents(&mut self, enable: bool) -> &mut Self {
+        self.create_parents = enable;
+        self
+    }
+
+    /// Removes duplicate paths passed to [`files`](`Self::files`).
+    ///
+    /// Passing the same file more than once (possibly spelled differently,
+    /// e.g. `./a` and `a`) makes most editors open it in several buffers which
+    /// is confusing and risks user’s changes being lost.  With this option
+    /// enabled, paths are canonicalised (as with [`std::fs::canonicalize`])
+    /// and only the first of the paths referring to the same file is passed to
+    /// the editor.  Paths which cannot be canonicalised (e.g. because the file
+    /// doesn’t exist yet) are compared by their lexically normalised absolute
+    /// form instead, i.e. with `.` and `..` components resolved without
+    /// following symbolic links.
+    ///
+    /// By default the paths are passed as given.
+    ///
+    /// ```no_run
+    /// run_editor::edit()
+    ///     .dedup_paths(true)
+    ///     .files(["./.shellrc", ".shellrc"])
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn dedup_paths(&mut self, enable: bool) -> &mut Self {
+        self.dedup_paths = enable;
+        self
+    }
+
+    /// Specifies environment variable to pass path of the file to edit in.
+    ///
+    /// By default, the path is passed to the shell running the editor command
+    /// as a positional parameter.  Some editor wrapper scripts are written to
+    /// read the file from a specific environment variable instead.  With this
+    /// option the path is exported in the `variable` and the editor command is
+    /// run with `"$variable"` in place of the path.
+    ///
+    /// The name must be a valid shell variable name, i.e. consist of ASCII
+    /// letters, digits and underscores and not start with a digit.
+    #[inline]
+    pub fn with_path_var(&mut self, variable: &'a OsStr) -> &mut Self {
+        self.path_variable = Some(variable);
+        self
+    }
+
+    /// Specifies description of what’s being edited exported to the editor.
+    ///
+    /// The label is exported in `RUN_EDITOR_LABEL` environment variable of the
+    /// editor process.  It’s purely informational: the crate doesn’t use it in
+    /// any way but users may configure their editor to display it, e.g. in its
+    /// title or status line, so it’s clear what the editor has been opened
+    /// for.  The label should be short and human-readable, e.g. `commit
+    /// message` or `crontab`.
+    ///
+    /// By default the variable isn’t set (though it may be inherited from the
+    /// environment of the application).
+    ///
+    /// ```no_run
+    /// use std::ffi::OsStr;
+    ///
+    /// run_editor::edit()
+    ///     .with_label(OsStr::new("shell configuration"))
+    ///     .file("/home/lex/.shellrc")
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn with_label(&mut self, label: &'a OsStr) -> &mut Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Specifies arguments passed to the editor before the path.
+    ///
+    /// Together with [`with_trailing_args`](`Self::with_trailing_args`) this
+    /// gives full control of the editor’s command line which becomes
+    /// `<editor> <leading>... <path> <trailing>...`.  Where `<editor>` is the
+    /// editor command (which itself may include options), possibly split by the
+    /// path as configured with [`path_position`](`Self::path_position`).  The
+    /// arguments are passed to the shell as positional parameters so they
+    /// don’t need to be escaped.
+    ///
+    /// ```
+    /// let (_, args) = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new("emacs"))
+    ///     .with_leading_args(&["-nw"])
+    ///     .with_trailing_args(&["--eval", "(goto-line 10)"])
+    ///     .audit_command("/tmp/file")
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     ["-c", "emacs \"$@\"", "sh", "-nw", "/tmp/file", "--eval",
+    ///      "(goto-line 10)"],
+    ///     args.as_slice()
+    /// );
+    /// ```
+    ///
+    /// Calling the method replaces any previously specified leading arguments.
+    pub fn with_leading_args(
+        &mut self,
+        args: &[impl AsRef<OsStr>],
+    ) -> &mut Self {
+        self.leading_args =
+            args.iter().map(|arg| arg.as_ref().into()).collect();
+        self
+    }
+
+    /// Specifies arguments passed to the editor only if it’s a particular
+    /// program.
+    ///
+    /// Once the editor command is resolved, base name of its program is
+    /// compared with `basename` (on Windows case-insensitively and ignoring
+    /// `.exe` extension) and if they match, `args` are passed to the editor.
+    /// This lets a single configuration work with whichever editor the user
+    /// happens to have, e.g. pass `-p` to vim so it opens files in tabs while
+    /// passing nothing to nano.
+    ///
+    /// The arguments are passed after leading arguments and before the path,
+    /// so the command line becomes `<editor> <leading>... <editor-args>...
+    /// <path> <trailing>...`; see [`with_leading_args`](`Self::with_leading_args`).
+    ///
+    /// ```
+    /// let mut edit = run_editor::edit();
+    /// edit.with_editor_args("vim", &["-p"]).with_leading_args(&["-R"]);
+    /// edit.with(std::ffi::OsStr::new("vim"));
+    /// let (_, args) = edit.audit_command("f").unwrap();
+    /// assert_eq!(["-c", "vim \"$@\"", "sh", "-R", "-p", "f"], args.as_slice());
+    /// edit.with(std::ffi::OsStr::new("nano"));
+    /// let (_, args) = edit.audit_command("f").unwrap();
+    /// assert_eq!(["-c", "nano \"$@\"", "sh", "-R", "f"], args.as_slice());
+    /// ```
+    ///
+    /// Calling the method again for the same `basename` replaces previously
+    /// specified arguments for that editor.
+    pub fn with_editor_args(
+        &mut self,
+        basename: &str,
+        args: &[impl AsRef<OsStr>],
+    ) -> &mut Self {
+        let name = profile::normalize(basename).into_owned();
+        let args = args.iter().map(|arg| arg.as_ref().into()).collect();
+        match self.editor_args.iter_mut().find(|entry| entry.0 == name) {
+            Some(entry) => entry.1 = args,
+            None => self.editor_args.push((name, args)),
+        }
+        self
+    }
+
+    /// Specifies wrapper program the editor is launched through.
+    ///
+    /// This is meant for sandboxed environments where the editor needs to be
+    /// run outside of the sandbox, e.g. with `flatpak-spawn --host` in Flatpak
+    /// applications.  The `launcher` is a program followed by its arguments
+    /// and is prepended to the command line spawned to run the editor, i.e.
+    /// the shell executing the editor command becomes launcher’s argument:
+    ///
+    /// ```
+    /// let (program, args) = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new("vi"))
+    ///     .with_launcher(&["flatpak-spawn", "--host"])
+    ///     .audit_command("/tmp/file")
+    ///     .unwrap();
+    /// assert_eq!("flatpak-spawn", program);
+    /// assert_eq!(
+    ///     ["--host", "/bin/sh", "-c", "vi \"$@\"", "sh", "/tmp/file"],
+    ///     args.as_slice()
+    /// );
+    /// ```
+    ///
+    /// No-op editor commands are still recognised and don’t launch anything.
+    /// Since the editor is executed by the launcher possibly in a different
+    /// environment, [`prepare`](`Self::prepare`) doesn’t check whether the
+    /// editor program exists.  Note that the path to edit must be accessible
+    /// to the launched editor and that the launcher may not forward
+    /// environment variables which [`with_path_var`](`Self::with_path_var`)
+    /// depends on.
+    ///
+    /// Calling the method with an empty slice removes the launcher.
+    pub fn with_launcher(
+        &mut self,
+        launcher: &[impl AsRef<OsStr>],
+    ) -> &mut Self {
+        self.launcher =
+            launcher.iter().map(|arg| arg.as_ref().into()).collect();
+        self
+    }
+
+    /// Specifies arguments passed to the editor after the path.
+    ///
+    /// See [`with_leading_args`](`Self::with_leading_args`) for more details.
+    /// Calling the method replaces any previously specified trailing arguments.
+    pub fn with_trailing_args(
+        &mut self,
+        args: &[impl AsRef<OsStr>],
+    ) -> &mut Self {
+        self.trailing_args =
+            args.iter().map(|arg| arg.as_ref().into()).collect();
+        self
+    }
+
+    /// Adds a transformation applied to buffers before they are edited.
+    ///
+    /// When editing a buffer (e.g. with [`buffer`](`Self::buffer`)), the data
+    /// is passed through all pre filters in the order they were added before
+    /// being written to the temporary file.  Once the user finishes editing,
+    /// the data read back is passed through all post filters (see
+    /// [`with_post_filter`](`Self::with_post_filter`)) in *reverse* order they
+    /// were added.  This allows adding filters in pairs, for example:
+    ///
+    /// ```no_run
+    /// # fn decompress(buf: Vec<u8>) -> std::io::Result<Vec<u8>> { Ok(buf) }
+    /// # fn compress(buf: Vec<u8>) -> std::io::Result<Vec<u8>> { Ok(buf) }
+    /// # fn pretty_print(buf: Vec<u8>) -> std::io::Result<Vec<u8>> { Ok(buf) }
+    /// # fn minify(buf: Vec<u8>) -> std::io::Result<Vec<u8>> { Ok(buf) }
+    /// # let data = Vec::new();
+    /// // Runs decompress → pretty_print → editor → minify → compress.
+    /// let data = run_editor::edit()
+    ///     .with_pre_filter(decompress)
+    ///     .with_post_filter(compress)
+    ///     .with_pre_filter(pretty_print)
+    ///     .with_post_filter(minify)
+    ///     .buffer(data);
+    /// ```
+    ///
+    /// If a filter fails, editing is aborted and the error returned.  Filters
+    /// are not run if the editor command is a no-op.
+    pub fn with_pre_filter(
+        &mut self,
+        filter: impl Fn(Vec<u8>) -> std::io::Result<Vec<u8>> + 'a,
+    ) -> &mut Self {
+        self.pre_filters.push(Box::new(filter));
+        self
+    }
+
+    /// Specifies function notified about progress of copying the source file
+    /// in [`file_copy`](`Self::file_copy`) and
+    /// [`file_copy_outcome`](`Self::file_copy_outcome`).
+    ///
+    /// The function is called with total number of bytes copied so far each
+    /// time a chunk of the source file is copied.  This lets applications show
+    /// a progress bar when copying large files before the editor is started.
+    ///
+    /// ```
+    /// # #[cfg(feature = "with_tempfile")] {
+    /// let mut edit = run_editor::edit();
+    /// edit.on_copy_progress(|copied| eprintln!("copied {copied} bytes"));
+    /// # }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn on_copy_progress(
+        &mut self,
+        progress: impl FnMut(u64) + 'a,
+    ) -> &mut Self {
+        self.copy_progress = Some(std::cell::RefCell::new(Box::new(progress)));
+        self
+    }
+
+    /// Adds a transformation applied to buffers after they are edited.
+    ///
+    /// Post filters are run in reverse order they were added.  See
+    /// [`with_pre_filter`](`Self::with_pre_filter`) for more details.
+    pub fn with_post_filter(
+        &mut self,
+        filter: impl Fn(Vec<u8>) -> std::io::Result<Vec<u8>> + 'a,
+    ) -> &mut Self {
+        self.post_filters.push(Box::new(filter));
+        self
+    }
+
+    /// Connects editor to the controlling terminal regardless of standard IO.
+    ///
+    /// By default the editor inherits standard input and output of the
+    /// application.  If those have been redirected (e.g. the application reads
+    /// data from a pipe), a terminal editor won’t be able to talk to the user.
+    /// With this option, the editor’s standard input and output are connected
+    /// to `/dev/tty`, i.e. controlling terminal of the process, which mirrors
+    /// how tools such as `sudo` or `git` prompt the user.
+    ///
+    /// If `/dev/tty` cannot be opened (e.g. because the process has no
+    /// controlling terminal), standard input and output are inherited as
+    /// usual.
+    ///
+    /// This is available on Unix systems only.
+    #[cfg(unix)]
+    #[inline]
+    pub fn use_dev_tty(&mut self, enable: bool) -> &mut Self {
+        self.use_dev_tty = enable;
+        self
+    }
+
+    /// Restores mode of the file if the editor changed it.
+    ///
+    /// Some editors change mode of a file when saving it, e.g. by writing a new
+    /// file in place of the old one, which may drop the executable bit of
+    /// a script.  With this option enabled, [`file`](`Self::file`) records mode
+    /// of the file before running the editor and once the editor terminates
+    /// (whether successfully or not) restores it if it differs.  Nothing is
+    /// done if the file didn’t exist before editing.
+    ///
+    /// By default the mode is left as the editor set it.
+    ///
+    /// ```no_run
+    /// run_editor::edit()
+    ///     .preserve_mode(true)
+    ///     .file("/home/lex/bin/backup.sh")
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This is available on Unix systems only.
+    #[cfg(unix)]
+    #[inline]
+    pub fn preserve_mode(&mut self, enable: bool) -> &mut Self {
+        self.preserve_mode = enable;
+        self
+    }
+
+    /// Treats termination of the editor by given signals as success.
+    ///
+    /// By default, if the editor is terminated by a signal, editing fails with
+    /// an error.  When the ‘editor’ is a long-running viewer which the user
+    /// closes with Ctrl-C, it may be preferable to treat that as a normal
+    /// completion.  With this option, termination by any of the `signals` (for
+    /// example `SIGINT` which is 2) is treated as if the editor exited
+    /// successfully.
+    ///
+    /// Note that the signal must terminate the shell running the editor
+    /// command.  If the shell merely reports that the editor has been killed,
+    /// it exits with a non-zero status which is still treated as failure.
+    ///
+    /// This is available on Unix systems only.
+    #[cfg(unix)]
+    #[inline]
+    pub fn with_ok_signals(&mut self, signals: &'a [i32]) -> &mut Self {
+        self.ok_signals = signals;
+        self
+    }
+
+    /// Specifies function deciding whether the editor terminated successfully.
+    ///
+    /// By default, editing succeeds if the editor exits with zero status (or,
+    /// on Unix, is terminated by one of signals given with
+    /// [`with_ok_signals`](`Self::with_ok_signals`)).  This replaces that rule
+    /// with arbitrary logic.  The predicate is given exit status of the shell
+    /// running the editor command and if it returns `false`, editing fails
+    /// with an error.  Signals configured with `with_ok_signals` are ignored
+    /// when a predicate is set.
+    ///
+    /// ```
+    /// let mut edit = run_editor::edit();
+    /// // Treat everything but exit code 2 as success.
+    /// edit.with_success_predicate(|status| status.code() != Some(2));
+    /// ```
+    pub fn with_success_predicate(
+        &mut self,
+        predicate: impl Fn(&std::process::ExitStatus) -> bool + 'a,
+    ) -> &mut Self {
+        self.success_predicate = Some(Box::new(predicate));
+        self
+    }
+
+    /// Limits how long the editor may run.
+    ///
+    /// If the editor doesn’t terminate within `timeout`, it’s killed and
+    /// editing fails.  This is mostly useful with non-interactive commands used
+    /// as editors.  Note that the process which is killed is the shell running
+    /// the editor command; programs it started in the background may survive.
+    ///
+    /// By default there is no limit.  See also
+    /// [`try_with_timeout`](`Self::try_with_timeout`) which rejects nonsensical
+    /// values.
+    #[inline]
+    pub fn with_timeout(&mut self, timeout: std::time::Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Like [`with_timeout`](`Self::with_timeout`) but rejects nonsensical
+    /// values.
+    ///
+    /// Returns an error if `timeout` is zero or longer than a year.
+    ///
+    /// ```
+    /// let timeout = std::time::Duration::from_secs(60);
+    /// let mut edit = run_editor::edit();
+    /// assert!(edit.try_with_timeout(timeout).is_ok());
+    /// assert!(edit.try_with_timeout(std::time::Duration::ZERO).is_err());
+    /// ```
+    pub fn try_with_timeout(
+        &mut self,
+        timeout: std::time::Duration,
+    ) -> Result<&mut Self, BuildError> {
+        const YEAR: std::time::Duration =
+            std::time::Duration::from_secs(365 * 24 * 60 * 60);
+        if timeout.is_zero() {
+            Err(BuildError("timeout must be positive"))
+        } else if timeout > YEAR {
+            Err(BuildError("timeout must not exceed a year"))
+        } else {
+            Ok(self.with_timeout(timeout))
+        }
+    }
+
+    /// Specifies function called if the editor runs longer than `timeout`.
+    ///
+    /// Unlike [`with_timeout`](`Self::with_timeout`), the editor is not
+    /// disrupted in any way.  Once `timeout` elapses, `callback` is called
+    /// (from the thread which runs the editor) and the editor keeps running.
+    /// This is meant for observability, e.g. logging that the user is still
+    /// editing.  The callback is called at most once per editor run and not at
+    /// all if the editor terminates before the time elapses.  Nor is it called
+    /// for in-process editors (see
+    /// [`with_inprocess_editor`](`Self::with_inprocess_editor`)).
+    ///
+    /// This may be combined with [`with_timeout`](`Self::with_timeout`) in
+    /// which case the soft timeout is normally shorter.
+    ///
+    /// ```no_run
+    /// run_editor::edit()
+    ///     .with_soft_timeout(std::time::Duration::from_secs(600), || {
+    ///         eprintln!("still editing...");
+    ///     })
+    ///     .file("/home/lex/.shellrc")
+    ///     .unwrap();
+    /// ```
+    pub fn with_soft_timeout(
+        &mut self,
+        timeout: std::time::Duration,
+        callback: impl Fn() + 'a,
+    ) -> &mut Self {
+        self.soft_timeout = Some((timeout, Box::new(callback)));
+        self
+    }
+
+    /// Specifies whether to fall back to `vi` if no editor is configured.
+    ///
+    /// If neither the application nor the user configured the editor command
+    /// (see [`editor`](`Self::editor`) for all the places it’s looked up in),
+    /// `vi` is used by default.  With this option disabled, editing fails with
+    /// an error of kind [`ErrorKind::NotConfigured`] instead which lets the
+    /// application ask the user to configure their editor rather than dropping
+    /// them into an editor they may not know how to exit.  See also
+    /// [`try_editor`](`Self::try_editor`).
+    ///
+    /// Note that [`editor`](`Self::editor`) is infallible and returns `vi`
+    /// regardless of this option.
+    #[inline]
+    pub fn use_default_editor(&mut self, enable: bool) -> &mut Self {
+        self.use_default_editor = enable;
+        self
+    }
+
+    /// Validates quoting of the editor command before running it.
+    ///
+    /// An editor command with unbalanced quotes (e.g. `EDITOR="code --wait`)
+    /// results in shell syntax error which may be confusing to the user.  With
+    /// this option enabled, the command is checked before any files are
+    /// created and editing fails with an error describing the problem if the
+    /// command has an unterminated quote or a trailing backslash.  The check
+    /// follows POSIX shell quoting rules and doesn’t validate any other shell
+    /// syntax.
+    ///
+    /// By default the command is passed to the shell as is.
+    #[inline]
+    pub fn strict_command(&mut self, enable: bool) -> &mut Self {
+        self.strict_command = enable;
+        self
+    }
+
+    /// Executes the editor directly rather than through a shell.
+    ///
+    /// Normally the editor command is passed to `/bin/sh -c` which means
+    /// that it may use arbitrary shell syntax.  If the command may be
+    /// influenced by an attacker, that’s a risk.  With this option enabled,
+    /// the command is split into words following shell quoting rules and the
+    /// program is executed directly with the words, leading arguments, the
+    /// path and trailing arguments as its arguments.  No shell is ever run.
+    ///
+    /// Commands which use shell syntax beyond quoting (pipes, redirections,
+    /// `&&`, variable expansions, globs and the like) are rejected with an
+    /// error of kind [`ErrorKind::ShellForbidden`] and commands with
+    /// unbalanced quotes with an error of kind
+    /// [`ErrorKind::MalformedCommand`].  The shell fallback (see
+    /// [`with_shell_fallback`](`Self::with_shell_fallback`)) is ignored and
+    /// the path is always passed as an argument, i.e.
+    /// [`with_path_var`](`Self::with_path_var`) has no effect.  Remote editing
+    /// (which runs the command through `ssh`) is not affected.
+    ///
+    /// ```
+    /// let (program, args) = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new("'my editor' -x"))
+    ///     .no_shell(true)
+    ///     .audit_command("/tmp/file")
+    ///     .unwrap();
+    /// assert_eq!("my editor", program);
+    /// assert_eq!(["-x", "/tmp/file"], args.as_slice());
+    /// ```
+    ///
+    /// By default the editor is executed through a shell.
+    #[inline]
+    pub fn no_shell(&mut self, enable: bool) -> &mut Self {
+        self.no_shell = enable;
+        self
+    }
+
+    /// Specifies `argv[0]` the editor is executed with.
+    ///
+    /// Some editors change their behaviour depending on the name they are
+    /// invoked as, e.g. `vim` invoked as `view` opens files read-only.  This
+    /// sets `argv[0]` of the editor process independently of the program which
+    /// is executed (as with [`CommandExt::arg0`]).
+    ///
+    /// The option only has effect if the editor is executed directly, i.e.
+    /// with [`no_shell`](`Self::no_shell`) option enabled, and no launcher is
+    /// set (see [`with_launcher`](`Self::with_launcher`)).  Otherwise
+    /// `argv[0]` would apply to the shell or the launcher rather than the
+    /// editor so it’s ignored.
+    ///
+    /// ```no_run
+    /// use std::ffi::OsStr;
+    ///
+    /// run_editor::edit()
+    ///     .with(OsStr::new("vim"))
+    ///     .no_shell(true)
+    ///     .with_arg0(OsStr::new("view"))
+    ///     .file("/etc/hosts")
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This is available on Unix systems only.
+    ///
+    /// [`CommandExt::arg0`]: std::os::unix::process::CommandExt::arg0
+    #[cfg(unix)]
+    #[inline]
+    pub fn with_arg0(&mut self, arg0: &'a OsStr) -> &mut Self {
+        self.arg0 = Some(arg0);
+        self
+    }
+
+    /// Refuses to run programs which can display but not save files.
+    ///
+    /// Users sometimes set `EDITOR` to a pager, e.g. `EDITOR=less`, in which
+    /// case editing silently does nothing since the program cannot save any
+    /// changes.  With this option enabled, if the resolved editor command runs
+    /// such a program, an error of kind [`ErrorKind::ReadonlyEditor`] is
+    /// returned rather than the program being launched.  By default, `bat`,
+    /// `cat`, `less` and `more` are recognised; the list can be replaced with
+    /// [`with_readonly_editors`](`Self::with_readonly_editors`).  Programs are
+    /// matched by basename (like in
+    /// [`with_editor_args`](`Self::with_editor_args`)) so `/usr/bin/less -R`
+    /// is recognised as well.
+    ///
+    /// By default the check is not performed.
+    ///
+    /// ```no_run
+    /// let res = run_editor::edit()
+    ///     .warn_readonly_editor(true)
+    ///     .file("/home/lex/.shellrc");
+    /// if let Err(err) = res {
+    ///     eprintln!("{err}; set EDITOR to a text editor");
+    /// }
+    /// ```
+    #[inline]
+    pub fn warn_readonly_editor(&mut self, enable: bool) -> &mut Self {
+        self.warn_readonly_editor = enable;
+        self
+    }
+
+    /// Specifies programs considered unable to save files.
+    ///
+    /// Replaces the built-in list of programs recognised by
+    /// [`warn_readonly_editor`](`Self::warn_readonly_editor`) with given
+    /// basenames.  This doesn’t enable the check by itself.
+    ///
+    /// ```
+    /// # use std::ffi::OsStr;
+    /// let err = run_editor::edit()
+    ///     .with(OsStr::new("view"))
+    ///     .warn_readonly_editor(true)
+    ///     .with_readonly_editors(["less", "view"])
+    ///     .file("/tmp/file")
+    ///     .unwrap_err();
+    /// assert_eq!(run_editor::ErrorKind::ReadonlyEditor, err.kind());
     /// ```
+    pub fn with_readonly_editors(
+        &mut self,
+        names: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> &mut Self {
+        let names = names
+            .into_iter()
+            .map(|name| profile::normalize(name.as_ref()).into_owned());
+        self.readonly_editors = Some(names.collect());
+        self
+    }
+
+    /// Specifies function which may rewrite or reject the editor command.
     ///
-    /// Normally, you just want to use [`file`](`Self::file`) method instead
-    /// which handles all that for you.
-    pub fn editor(&self) -> OsString {
-        self.editor_variable
-            .and_then(|var| std::env::var_os(var))
-            .or_else(|| self.editor_command.map(OsString::from))
-            .or_else(|| std::env::var_os("VISUAL"))
-            .or_else(|| std::env::var_os("EDITOR"))
-            .unwrap_or_else(|| OsString::from("vi"))
+    /// This provides a single choke point for enforcing editor policy, e.g.
+    /// forbidding certain editors or replacing `vi` with `nvim`.  The filter is
+    /// called with the command resolved as described in
+    /// [`editor`](`Self::editor`) whenever a file is about to be edited.  It’s
+    /// called before checking whether the command is a no-op and before
+    /// constructing the process to execute.  If it returns a command, that
+    /// command is used instead.  If it returns an error, editing is aborted
+    /// and that error is returned.  [`Error::editor_rejected`] constructs an
+    /// error suitable for rejecting a command.
+    ///
+    /// Note that [`editor`](`Self::editor`) and other methods which only
+    /// inspect the configuration do not apply the filter.
+    ///
+    /// ```no_run
+    /// let mut edit = run_editor::edit();
+    /// edit.with_editor_filter(|editor| {
+    ///     if editor == "vi" {
+    ///         Ok("nvim".into())
+    ///     } else if editor == "emacs" {
+    ///         Err(run_editor::Error::editor_rejected("emacs is not allowed"))
+    ///     } else {
+    ///         Ok(editor)
+    ///     }
+    /// });
+    /// edit.file("/home/lex/.shellrc").unwrap();
+    /// ```
+    pub fn with_editor_filter(
+        &mut self,
+        filter: impl Fn(OsString) -> Result<OsString, Error> + 'a,
+    ) -> &mut Self {
+        self.editor_filter = Some(Box::new(filter));
+        self
     }
 
-    /// Returns the editor command or `None` if the command is a nop.
+    /// Makes [`file`](`Self::file`) replace the file atomically.
     ///
-    /// Works like [`get`](`Self::get`) except that it returns `None` if editor
-    /// command user specified is `":"` or `"true"`.  If the command is set to
-    /// one of those, the file won’t be edited so there’s no need to execute the
-    /// editor.
-    fn editor_unless_nop(&self) -> Option<OsString> {
-        let editor = self.editor();
-        if editor == ":" || editor == "true" {
-            None
-        } else {
-            Some(editor)
+    /// By default, the editor is run directly on the file and it’s up to the
+    /// editor to save it safely.  With this option, the file is first copied
+    /// to a temporary file in the same directory, the user edits the copy and
+    /// once the editor terminates successfully the copy is flushed to disk
+    /// (with `fsync`) and renamed over the original.  If editing fails, the
+    /// original file is not modified.  Files which don’t exist yet are edited
+    /// directly.
+    ///
+    /// Since renaming replaces the inode, attributes of the original file are
+    /// copied to the new one before the swap.  The permissions are always
+    /// preserved.  On Unix, the owner and group are preserved as well, however
+    /// changing them requires appropriate privileges (usually being root)
+    /// unless they match the user running the editor; if they cannot be
+    /// changed, the new file will be owned by the current user.  Other
+    /// attributes (e.g. extended attributes, ACLs or hard links) are not
+    /// preserved.
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    #[inline]
+    pub fn atomic(&mut self, enable: bool) -> &mut Self {
+        self.atomic = enable;
+        self
+    }
+
+    /// Specifies how [`buffer_changed`](`Self::buffer_changed`) decides whether
+    /// buffer has been changed.
+    ///
+    /// Editors often make insignificant changes to files, such as removing
+    /// white space at the end of lines or adding a new line at the end of the
+    /// file.  With [`Compare::IgnoreTrailingWs`] such differences are not
+    /// treated as changes.  By default, [`Compare::Exact`] is used.
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    #[inline]
+    pub fn change_compare(&mut self, compare: Compare) -> &mut Self {
+        self.change_compare = compare;
+        self
+    }
+
+    /// Specifies permissions of files created by
+    /// [`file_copy`](`Self::file_copy`).
+    ///
+    /// The destination is written by renaming a temporary file over it and,
+    /// by default, a newly created destination gets permissions of that
+    /// temporary file which are usually restrictive (`0o600`).  With this
+    /// option, `mode` is applied if the destination didn’t exist before.  If
+    /// `always` is true, it’s applied even if the destination is replaced.
+    /// The permissions are set before the file is moved into place so the
+    /// destination never has different mode.
+    ///
+    /// This is available on Unix systems only and requires `with_tempfile`
+    /// Cargo feature to be enabled.  That feature is enabled by default.
+    #[cfg(all(unix, feature = "with_tempfile"))]
+    #[inline]
+    pub fn with_result_mode(&mut self, mode: u32, always: bool) -> &mut Self {
+        self.result_mode = Some((mode, always));
+        self
+    }
+
+    /// Specifies function returning directory to create temporary files in.
+    ///
+    /// By default, temporary files holding buffers being edited (e.g. by
+    /// [`buffer`](`Self::buffer`)) are created in directory returned by
+    /// [`std::env::temp_dir`] which on Unix honours `TMPDIR` environment
+    /// variable.  With this option, the function is called instead each time
+    /// such a temporary file is needed and the `TMPDIR` variable is ignored.
+    /// This lets applications compute the location dynamically, e.g. put the
+    /// files in per-user runtime directory.  The function is not called when
+    /// no temporary file is needed, e.g. when editing a file directly with
+    /// [`file`](`Self::file`).
+    ///
+    /// Methods which create temporary files next to their destination (such as
+    /// [`file_copy`](`Self::file_copy`) or
+    /// [`buffer_near`](`Self::buffer_near`)) are not affected.
+    ///
+    /// ```
+    /// # #[cfg(feature = "with_tempfile")] {
+    /// let mut edit = run_editor::edit();
+    /// edit.with_tempdir_fn(|| {
+    ///     std::env::var_os("XDG_RUNTIME_DIR")
+    ///         .map_or_else(std::env::temp_dir, std::path::PathBuf::from)
+    /// });
+    /// # }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn with_tempdir_fn(
+        &mut self,
+        tempdir: impl Fn() -> std::path::PathBuf + 'a,
+    ) -> &mut Self {
+        self.tempdir_fn = Some(Box::new(tempdir));
+        self
+    }
+
+    /// Keeps all temporary files created by the crate for debugging.
+    ///
+    /// When diagnosing why an edit produced unexpected content, it’s useful to
+    /// inspect the temporary files the editor has been run on.  With this
+    /// option enabled, temporary files created by methods such as
+    /// [`buffer`](`Self::buffer`) or [`file_copy`](`Self::file_copy`) are
+    /// never deleted, whether editing succeeds or fails.  Temporary files which
+    /// are persisted as the destination file are still moved into place.
+    ///
+    /// The crate doesn’t print anything.  To learn paths of the kept files,
+    /// install a hook with [`on_launch`](`Self::on_launch`) which is called
+    /// with path of each file the editor is run on.
+    ///
+    /// This is strictly a debugging aid and is disabled by default.  It
+    /// overrides [`keep_on_error`](`Self::keep_on_error`): with both options
+    /// enabled, temporary files are kept regardless of the outcome.
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    #[inline]
+    pub fn debug_keep_temps(&mut self, enable: bool) -> &mut Self {
+        self.debug_keep_temps = enable;
+        self
+    }
+
+    /// Keeps temporary files if editing fails.
+    ///
+    /// Normally, temporary files created by methods such as
+    /// [`buffer`](`Self::buffer`) or [`file_copy`](`Self::file_copy`) are
+    /// deleted once the operation finishes.  If it fails, e.g. because the
+    /// editor crashed or the result was rejected by a filter, user’s work is
+    /// lost with them.  With this option enabled, temporary files are deleted
+    /// only if the operation succeeds.  Otherwise they are left in place so
+    /// that editing can be continued with [`resume`](`Self::resume`).
+    ///
+    /// As with [`debug_keep_temps`](`Self::debug_keep_temps`), paths of the
+    /// files are not reported by the crate but can be learned with
+    /// [`on_launch`](`Self::on_launch`) hook.
+    ///
+    /// By default this option is disabled.
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    #[inline]
+    pub fn keep_on_error(&mut self, enable: bool) -> &mut Self {
+        self.keep_on_error = enable;
+        self
+    }
+
+    /// Specifies suffix of names of temporary files the editor is run on.
+    ///
+    /// Many editors pick syntax highlighting and other settings based on file
+    /// name extension, so when editing e.g. a TOML buffer it’s useful to name
+    /// the temporary file `something.toml`.  The suffix is appended to names
+    /// of temporary files created by methods such as
+    /// [`buffer`](`Self::buffer`) or [`file_copy`](`Self::file_copy`) verbatim
+    /// so it should normally start with a dot.
+    ///
+    /// By default temporary files have no suffix.
+    ///
+    /// ```
+    /// let mut edit = run_editor::edit();
+    /// edit.with_suffix(".toml");
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn with_suffix(&mut self, suffix: impl AsRef<OsStr>) -> &mut Self {
+        self.suffix = Some(suffix.as_ref().into());
+        self
+    }
+
+    /// Specifies editor command to use for temporary files with given suffix.
+    ///
+    /// If suffix set with [`with_suffix`](`Self::with_suffix`) equals `suffix`
+    /// (the comparison is exact so both should be written the same way, e.g.
+    /// `.toml`), `command` is used as the editor command.  It takes
+    /// precedence over the editor given with [`with`](`Self::with`) and user’s
+    /// preferences (i.e. `VISUAL` and `EDITOR` environment variables) but not
+    /// over the process-wide override set with [`set_global_editor`] or the
+    /// variable set with
+    /// [`with_editor_variable`](`Self::with_editor_variable`); see
+    /// [`editor`](`Self::editor`) for full description of the resolution
+    /// priorities.  Since the suffix is part of the
+    /// configuration, the command is used by all methods, including
+    /// [`file`](`Self::file`).  If the suffix doesn’t match any configured
+    /// filetype, the editor is resolved as usual.
+    ///
+    /// ```
+    /// let mut edit = run_editor::edit();
+    /// edit.with_filetype_editor(".toml", "taplo-edit")
+    ///     .with_filetype_editor(".md", "ghostwriter");
+    /// edit.with_suffix(".toml");
+    /// assert_eq!("taplo-edit", edit.editor());
+    /// ```
+    ///
+    /// Calling the method again for the same `suffix` replaces previously
+    /// specified command.
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn with_filetype_editor(
+        &mut self,
+        suffix: impl AsRef<OsStr>,
+        command: impl AsRef<OsStr>,
+    ) -> &mut Self {
+        let (suffix, command) = (suffix.as_ref(), command.as_ref().into());
+        match self.filetype_editors.iter_mut().find(|entry| entry.0 == suffix) {
+            Some(entry) => entry.1 = command,
+            None => self.filetype_editors.push((suffix.into(), command)),
         }
+        self
     }
 
-    /// Specifies environment variable to read user-preferred editor command
-    /// from.
+    /// Removes UTF-8 byte order mark from the start of edited buffers.
     ///
-    /// This is useful in conjunction with [`with`](`Self::with`) to allow easy
-    /// per-command overriding of the application configuration.  For example,
-    /// git supports `core.editor` option but allows that to be overridden with
-    /// `GIT_EDITOR` environment variable, e.g.: `GIT_EDITOR=nano git commit`.
+    /// Some editors, notably Notepad on Windows, may prepend a byte order mark
+    /// (bytes `EF BB BF`) when saving a file which usually confuses parsers
+    /// expecting plain UTF-8.  With this option enabled, if data read back
+    /// after editing by [`buffer`](`Self::buffer`) and similar methods starts
+    /// with the mark, it’s removed before post filters are applied.
     ///
-    /// If the variable is set, its value overrides any other methods of
-    /// determining the editor command.  See [`editor`](`Self::editor`) for full
-    /// description of the resolution priorities.
+    /// By default the data is returned as saved by the editor, i.e. the byte
+    /// order mark is preserved.
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
     #[inline]
-    pub fn with_editor_variable(&mut self, variable: &'a OsStr) -> &mut Self {
-        self.editor_variable = Some(variable);
+    pub fn strip_bom(&mut self, enable: bool) -> &mut Self {
+        self.strip_bom = enable;
+        self
+    }
+
+    /// Writes edited buffers to the editor’s standard input as well.
+    ///
+    /// Normally the editor is given path to a temporary file holding the buffer
+    /// and its standard input is inherited from the application.  With this
+    /// option enabled, [`buffer`](`Self::buffer`) (and other methods editing
+    /// data through a temporary file) additionally pipe the initial contents of
+    /// the buffer to the editor’s standard input.  This is mostly useful with
+    /// non-interactive ‘filter’ editors which read data from standard input
+    /// and write the result into the file, e.g. `sed s/foo/bar/ >`.  It’s fine
+    /// if the editor doesn’t read the data.
+    ///
+    /// Interactive text-mode editors (such as vi) expect their standard input
+    /// to be a terminal so they won’t work with this option enabled, which is
+    /// why it’s disabled by default.  The option overrides
+    /// [`use_dev_tty`](`Self::use_dev_tty`) for the standard input.
+    ///
+    /// ```
+    /// # #[cfg(unix)] {
+    /// let buf = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new("tr a-z A-Z >"))
+    ///     .feed_stdin(true)
+    ///     .buffer(b"foo\n".to_vec())
+    ///     .unwrap();
+    /// assert_eq!(b"FOO\n", buf.as_slice());
+    /// # }
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    #[inline]
+    pub fn feed_stdin(&mut self, enable: bool) -> &mut Self {
+        self.feed_stdin = enable;
+        self
+    }
+
+    /// Specifies external command validating edited buffers.
+    ///
+    /// Once the editor terminates, [`buffer`](`Self::buffer`) (and other
+    /// methods editing data through a temporary file) run the command
+    /// (directly, without a shell) with path to the temporary file appended to
+    /// `command`, e.g. `&["shellcheck", "--"]`.  If it exits with non-zero
+    /// status, the editor is run again with whatever the command wrote to its
+    /// standard error inserted at the start of the file as comments (each line
+    /// prefixed with `# `).  Comment lines left at the start of the file
+    /// unchanged are removed before the command is run again.  This is
+    /// repeated up to the number of times configured with
+    /// [`with_max_attempts`](`Self::with_max_attempts`) after which error of
+    /// kind [`ErrorKind::Invalid`] with the command’s error output is
+    /// returned.
+    ///
+    /// This is an external-process analogue of validation performed by
+    /// [`edit_or_default`](`Self::edit_or_default`).  The command sees the
+    /// file as saved by the editor, i.e. before post filters are applied.
+    /// Note that the temporary file has a random name; see
+    /// [`with_suffix`](`Self::with_suffix`) for validators which care about
+    /// its extension.
+    ///
+    /// Calling the method with an empty slice removes the validator.
+    ///
+    /// ```no_run
+    /// let script = run_editor::edit()
+    ///     .with_suffix(".sh")
+    ///     .with_post_command(&["shellcheck", "--"])
+    ///     .buffer(b"#!/bin/sh\n".to_vec())
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn with_post_command(
+        &mut self,
+        command: &[impl AsRef<OsStr>],
+    ) -> &mut Self {
+        self.post_command =
+            command.iter().map(|arg| arg.as_ref().into()).collect();
+        self
+    }
+
+    /// Specifies filter run by [`apply`](`Self::apply`).
+    ///
+    /// The filter is executed directly (without a shell) with the expression
+    /// and path to the file appended to `filter`.  It must write the new
+    /// contents of the file to standard output.  By default `sed -e` is used.
+    /// Calling the method with an empty slice restores the default.
+    ///
+    /// ```no_run
+    /// run_editor::edit()
+    ///     .with_apply_filter(&["awk"])
+    ///     .apply("/home/lex/.shellrc", "!/^alias ls=/")
+    ///     .unwrap();
+    /// ```
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn with_apply_filter(
+        &mut self,
+        filter: &[impl AsRef<OsStr>],
+    ) -> &mut Self {
+        self.apply_filter =
+            filter.iter().map(|arg| arg.as_ref().into()).collect();
+        self
+    }
+
+    /// Specifies function to edit files with instead of spawning the editor.
+    ///
+    /// This is meant for tests of applications using this crate which need to
+    /// exercise code paths involving the editor without any user interaction
+    /// and without depending on programs installed on the system.  Whenever
+    /// the editor would be run on a file, `editor` is called with path to that
+    /// file instead.  It can read and modify the file as it sees fit.  If it
+    /// returns an error, editing fails.
+    ///
+    /// With in-process editor set, the editor command is still resolved (it’s
+    /// used in error messages) but it’s never treated as a no-op, the filter
+    /// set with [`with_editor_filter`](`Self::with_editor_filter`) is not
+    /// applied and the timeout is ignored.  When used with
+    /// [`buffer_capture_stdout`](`Self::buffer_capture_stdout`), contents of
+    /// the file after editing are treated as the editor’s output.
+    ///
+    /// ```
+    /// # #[cfg(feature = "with_tempfile")] {
+    /// let buf = run_editor::edit()
+    ///     .with_inprocess_editor(|path| std::fs::write(path, "bar"))
+    ///     .buffer(b"foo".to_vec())
+    ///     .unwrap();
+    /// assert_eq!(b"bar", buf.as_slice());
+    /// # }
+    /// ```
+    ///
+    /// See also `RecordingEditor` type available with `test-util` Cargo
+    /// feature.
+    pub fn with_inprocess_editor(
+        &mut self,
+        editor: impl Fn(&std::path::Path) -> std::io::Result<()> + 'a,
+    ) -> &mut Self {
+        self.inprocess_editor = Some(Box::new(editor));
+        self
+    }
+
+    /// Specifies function called right before the editor is run on a file.
+    ///
+    /// `hook` is called with path of the file the editor is about to be run
+    /// on.  This is always the path which is passed to the editor: for
+    /// methods which let user edit a temporary file, such as
+    /// [`buffer`](`Self::buffer`), [`file_copy`](`Self::file_copy`) or
+    /// [`file`](`Self::file`) with [`atomic`](`Self::atomic`) option enabled,
+    /// it’s path of that temporary file rather than of the logical destination.
+    /// The file exists and holds the contents the user is about to see when the
+    /// hook is called, so the hook may for example run a linter on it.  If
+    /// the editor is run on multiple files at once (see
+    /// [`buffers_named`](`Self::buffers_named`)), the hook is called for each
+    /// of them.
+    ///
+    /// Hooks are called for in-process editors as well (see
+    /// [`with_inprocess_editor`](`Self::with_inprocess_editor`)) but not when
+    /// the editor command is a no-op or when editing remote files.
+    ///
+    /// ```
+    /// # #[cfg(feature = "with_tempfile")] {
+    /// let buf = run_editor::edit()
+    ///     .with(std::ffi::OsStr::new(":"))
+    ///     .on_launch(|path| eprintln!("editing {}", path.display()))
+    ///     .buffer(b"foo".to_vec())
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn on_launch(
+        &mut self,
+        hook: impl Fn(&std::path::Path) + 'a,
+    ) -> &mut Self {
+        self.on_launch = Some(Box::new(hook));
+        self
+    }
+
+    /// Specifies function called right after the editor terminates.
+    ///
+    /// `hook` is called with path of the file the editor has been run on once
+    /// the editor terminates, before the file is read back or persisted.  The
+    /// hook is called whatever the outcome, i.e. also if the editor failed,
+    /// was killed or couldn’t be started, such that each call to the hook set
+    /// with [`on_launch`](`Self::on_launch`) is matched by a call to this
+    /// hook.  Like with `on_launch`, the path is that of the actual file passed
+    /// to the editor (which may be a temporary file) and that file still
+    /// exists when the hook is called, so the hook can for example take
+    /// a snapshot of it.
+    pub fn on_exit(
+        &mut self,
+        hook: impl Fn(&std::path::Path) + 'a,
+    ) -> &mut Self {
+        self.on_exit = Some(Box::new(hook));
+        self
+    }
+
+    /// Reads the edited content from standard input if it’s not a terminal.
+    ///
+    /// A full-screen editor is of no use if the program runs in a pipeline or
+    /// on a CI system.  With this option enabled, if standard input is not
+    /// a terminal, rather than running the editor, standard input is read
+    /// until end of file and whatever has been read replaces contents of the
+    /// file being edited.  This makes it possible to use the same code
+    /// interactively and in pipelines, e.g. `echo new | tool`: with
+    /// [`buffer`](`Self::buffer`), the result is exactly the piped data.
+    ///
+    /// Since standard input is consumed by the first edit, any further edits
+    /// (including re-edits after validation failures, see
+    /// [`edit_or_default`](`Self::edit_or_default`)) get empty content.  As
+    /// when running the editor, no-op editor commands leave the file
+    /// unchanged without reading anything and an in-process editor (see
+    /// [`with_inprocess_editor`](`Self::with_inprocess_editor`)) takes
+    /// precedence over this option.
+    ///
+    /// By default the editor is run regardless of whether standard input is
+    /// a terminal.
+    #[inline]
+    pub fn fallback_line_editor(&mut self, enable: bool) -> &mut Self {
+        self.stdin_fallback = enable;
+        self
+    }
+
+    /// Specifies interpreter to run the editor command with if `/bin/sh` is
+    /// missing.
+    ///
+    /// On minimal systems, such as slimmed-down container images, the shell
+    /// may not be installed even though another interpreter is.  With this
+    /// option, if starting the shell fails with
+    /// [`std::io::ErrorKind::NotFound`] error, the editor command is run with
+    /// `interpreter` instead.  The interpreter is split into words as a shell
+    /// would so it may include arguments, e.g. `busybox sh`.  It’s passed the
+    /// same arguments as the shell would be, i.e. `-c`, the command, `sh` and
+    /// path to the file.
+    ///
+    /// Other errors starting the shell do not trigger the fallback.  Note that
+    /// [`audit_command`](`Self::audit_command`) always reports the shell.
+    #[inline]
+    pub fn with_shell_fallback(&mut self, interpreter: &'a OsStr) -> &mut Self {
+        self.shell_fallback = Some(interpreter);
+        self
+    }
+
+    /// Specifies editor command to run on remote hosts; see
+    /// [`remote`](`Self::remote`).
+    ///
+    /// By default, the local editor command is used which may not be available
+    /// on the remote host.
+    ///
+    /// This requires `ssh` Cargo feature to be enabled.
+    #[cfg(feature = "ssh")]
+    #[inline]
+    pub fn with_remote_editor(&mut self, editor: &'a OsStr) -> &mut Self {
+        self.remote_editor = Some(editor);
         self
     }
 
@@ -234,4 +3922,133 @@ impl<'a> Edit<'a> {
         self.editor_command = Some(editor_command);
         self
     }
+
+    /// Specifies reader to read editor command to edit the file with from.
+    ///
+    /// This is meant for sandboxed applications which receive configuration
+    /// through a pipe or other file descriptor rather than the environment.
+    /// The reader is read lazily, the first time the editor command is
+    /// needed, and only up to the end of its first line.  The trailing new
+    /// line is stripped and the command is then remembered so the reader is
+    /// never read again.
+    ///
+    /// The reader is consulted only if no command has been given with
+    /// [`with`](`Self::with`), i.e. an explicitly set command takes priority,
+    /// but it takes priority over `VISUAL` and `EDITOR` environment variables.
+    /// If the first line is blank or reading fails, the reader is ignored and
+    /// editor command is resolved as if it was never set.  See
+    /// [`editor`](`Self::editor`) for full description of the resolution
+    /// priorities.
+    ///
+    /// ```
+    /// # if std::env::var_os("FOO_EDITOR").is_none() {
+    /// let config = &b"nano -R\nother configuration\n"[..];
+    /// let mut edit = run_editor::edit();
+    /// edit.with_editor_variable(std::ffi::OsStr::new("FOO_EDITOR"))
+    ///     .with_command_from_reader(config);
+    /// assert_eq!("nano -R", edit.editor());
+    /// # }
+    /// ```
+    pub fn with_command_from_reader(
+        &mut self,
+        reader: impl std::io::Read + 'a,
+    ) -> &mut Self {
+        let reader = CommandReader::Pending(Box::new(reader));
+        self.command_reader = Some(std::cell::RefCell::new(reader));
+        self
+    }
+
+    /// Specifies editor command to edit the file with if one is given.
+    ///
+    /// Calling this with `Some` is equivalent to calling [`with`](`Self::with`)
+    /// while `None` is a no-op leaving any previously set command in place.
+    /// This lets optional configuration be passed in without branching:
+    ///
+    /// ```
+    /// let core_editor: Option<std::ffi::OsString> = None;
+    ///
+    /// let mut edit = run_editor::edit();
+    /// edit.with_opt(core_editor.as_deref());
+    /// # assert_eq!(run_editor::edit().editor(), edit.editor());
+    /// ```
+    #[inline]
+    pub fn with_opt(&mut self, editor_command: Option<&'a OsStr>) -> &mut Self {
+        if let Some(editor_command) = editor_command {
+            self.editor_command = Some(editor_command);
+        }
+        self
+    }
+}
+
+
+/// Interface for letting users view files in CLI applications.
+///
+/// This is a read-only counterpart of [`Edit`] which runs user’s preferred
+/// pager.  The object is constructed with [`page`] function.  For example:
+///
+/// ```no_run
+/// run_editor::page().file("/var/log/app.log").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Page<'a> {
+    /// Command to use in preference to those determined by default methods of
+    /// getting user preferences.
+    pager_command: Option<&'a OsStr>,
+}
+
+/// Constructs default [`Page`] object.
+pub const fn page<'a>() -> Page<'a> { Page { pager_command: None } }
+
+impl<'a> Page<'a> {
+    /// Executes pager letting user view the file.
+    ///
+    /// The pager command is resolved as described in [`pager`](`Self::pager`)
+    /// and run the same way [`Edit::file`] runs the editor.  Note that with
+    /// some pagers, the user may still be able to start an editor from within
+    /// the pager.
+    pub fn file(&self, path: impl AsRef<std::path::Path>) -> Result<(), Error> {
+        imp::edit_file(&edit(), self.pager(), path.as_ref())
+    }
+
+    /// Writes contents of a buffer to temporary file to let user view it.
+    ///
+    /// The temporary file is deleted once the pager terminates.  Nothing is
+    /// read back from it.
+    ///
+    /// This requires `with_tempfile` Cargo feature to be enabled.  That feature
+    /// is enabled by default.
+    #[cfg(feature = "with_tempfile")]
+    pub fn buffer(&self, buf: &[u8]) -> Result<(), Error> {
+        imp::page_buffer(self.pager(), buf)
+    }
+
+    /// Returns the pager command to use to let user view files.
+    ///
+    /// The resolution of the pager command goes as follows:
+    /// 1. If pager command has been provided via [`with`](`Self::with`) method,
+    ///    use that command.
+    /// 2. Otherwise, use value of the `PAGER` environment variable.
+    /// 3. If that is not set, use `"less"` if it can be found in `PATH` (the
+    ///    same way [`Edit::prepare`] looks up editors) or `"more"` otherwise.
+    ///
+    /// Like [`Edit::editor`], the returned string is a *command* which needs
+    /// to be executed through a shell.
+    pub fn pager(&self) -> OsString {
+        self.pager_command
+            .map(OsString::from)
+            .or_else(|| std::env::var_os("PAGER"))
+            .unwrap_or_else(|| {
+                let has_less = imp::which(OsStr::new("less")).is_some();
+                OsString::from(if has_less { "less" } else { "more" })
+            })
+    }
+
+    /// Specifies pager command to view the file with.
+    ///
+    /// If this option is set, it overrides the `PAGER` environment variable.
+    #[inline]
+    pub fn with(&mut self, pager_command: &'a OsStr) -> &mut Self {
+        self.pager_command = Some(pager_command);
+        self
+    }
 }
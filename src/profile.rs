@@ -0,0 +1,168 @@
+//! Built-in knowledge about popular editors.
+
+use std::ffi::OsStr;
+
+
+/// Description of a known editor.
+pub(super) struct Profile {
+    /// Basename of the editor’s executable.
+    pub name: &'static str,
+    /// Whether the editor runs inside of a terminal.  `None` if that depends
+    /// on the options the editor is run with.
+    pub terminal: Option<bool>,
+    /// Options which make the editor wait for the file to be closed.  Empty if
+    /// the editor always waits.
+    pub wait: &'static [&'static str],
+    /// Whether the editor requires a running server.
+    pub server: bool,
+}
+
+/// Base for profiles in the registry.
+const BASE: Profile =
+    Profile { name: "", terminal: None, wait: &[], server: false };
+
+/// Options used by most graphical editors to wait for the file to be closed.
+const WAIT: &[&str] = &["--wait", "-w"];
+
+/// Registry of known editors.
+static PROFILES: &[Profile] = &[
+    Profile { name: "atom", terminal: Some(false), wait: WAIT, ..BASE },
+    Profile { name: "code", terminal: Some(false), wait: WAIT, ..BASE },
+    Profile { name: "codium", terminal: Some(false), wait: WAIT, ..BASE },
+    Profile { name: "ed", terminal: Some(true), ..BASE },
+    Profile { name: "emacs", terminal: None, ..BASE },
+    Profile { name: "emacsclient", terminal: None, server: true, ..BASE },
+    Profile { name: "ex", terminal: Some(true), ..BASE },
+    Profile { name: "gedit", terminal: Some(false), wait: WAIT, ..BASE },
+    Profile {
+        name: "gvim",
+        terminal: Some(false),
+        wait: &["--nofork", "-f"],
+        ..BASE
+    },
+    Profile { name: "hx", terminal: Some(true), ..BASE },
+    Profile { name: "joe", terminal: Some(true), ..BASE },
+    Profile { name: "kak", terminal: Some(true), ..BASE },
+    Profile {
+        name: "kate",
+        terminal: Some(false),
+        wait: &["--block", "-b"],
+        ..BASE
+    },
+    Profile { name: "mate", terminal: Some(false), wait: WAIT, ..BASE },
+    Profile { name: "mg", terminal: Some(true), ..BASE },
+    Profile { name: "micro", terminal: Some(true), ..BASE },
+    Profile { name: "mousepad", terminal: Some(false), ..BASE },
+    Profile { name: "nano", terminal: Some(true), ..BASE },
+    Profile { name: "notepad", terminal: Some(false), ..BASE },
+    Profile { name: "notepad++", terminal: Some(false), ..BASE },
+    Profile { name: "nvim", terminal: Some(true), ..BASE },
+    Profile { name: "pico", terminal: Some(true), ..BASE },
+    Profile { name: "subl", terminal: Some(false), wait: WAIT, ..BASE },
+    Profile { name: "vi", terminal: Some(true), ..BASE },
+    Profile { name: "vim", terminal: Some(true), ..BASE },
+    Profile { name: "zed", terminal: Some(false), wait: WAIT, ..BASE },
+];
+
+/// Programs which display files but cannot save them; see
+/// [`crate::Edit::warn_readonly_editor`].
+pub(super) const READONLY_EDITORS: &[&str] = &["bat", "cat", "less", "more"];
+
+/// Looks up profile of the program executed by given editor command.
+pub(super) fn find(command: &OsStr) -> Option<&'static Profile> {
+    let name = program_name(command)?;
+    PROFILES.iter().find(|profile| profile.name == name)
+}
+
+/// Returns normalised basename of the program executed by given editor
+/// command.
+pub(super) fn program_name(command: &OsStr) -> Option<String> {
+    let program = crate::shell::program(command)?;
+    let name = std::path::Path::new(&program).file_name()?.to_str()?;
+    Some(normalize(name).into_owned())
+}
+
+/// Returns quirks of the editor executed by given editor command; see
+/// [`crate::Edit::editor_quirks`].
+pub(super) fn quirks(command: &OsStr) -> crate::EditorQuirks {
+    let profile = match find(command) {
+        Some(profile) => profile,
+        None => return crate::EditorQuirks::default(),
+    };
+    let has_wait =
+        || {
+            let words = crate::shell::split(&crate::shell::to_bytes(command));
+            words.unwrap_or_default().iter().skip(1).any(|word| {
+                profile.wait.iter().any(|wait| word == wait.as_bytes())
+            })
+        };
+    crate::EditorQuirks {
+        needs_wait: !profile.wait.is_empty() && !has_wait(),
+        needs_server: profile.server,
+        is_gui: profile.terminal == Some(false),
+    }
+}
+
+/// Normalises name of a program for comparison with known names.
+///
+/// On Windows, file names are case-insensitive and executables have `.exe`
+/// extension so the name is converted to lower case and the extension is
+/// stripped.  On other systems the name is returned unchanged.
+pub(super) fn normalize(name: &str) -> std::borrow::Cow<'_, str> {
+    #[cfg(windows)]
+    {
+        let name = name.to_lowercase();
+        return match name.strip_suffix(".exe") {
+            Some(stem) => stem.to_owned().into(),
+            None => name.into(),
+        };
+    }
+    #[allow(unreachable_code)]
+    name.into()
+}
+
+
+#[test]
+fn test_find() {
+    fn name(command: &str) -> Option<&'static str> {
+        find(OsStr::new(command)).map(|profile| profile.name)
+    }
+
+    assert_eq!(Some("vi"), name("vi"));
+    assert_eq!(Some("vim"), name("/usr/bin/vim -p"));
+    assert_eq!(Some("code"), name("'code' --wait"));
+    assert_eq!(None, name("my-editor"));
+    assert_eq!(None, name(""));
+    #[cfg(not(windows))]
+    assert_eq!(None, name("Notepad.exe"));
+}
+
+#[test]
+fn test_quirks() {
+    fn test(command: &str, want: (bool, bool, bool)) {
+        let got = quirks(OsStr::new(command));
+        assert_eq!(want, (got.needs_wait, got.needs_server, got.is_gui));
+    }
+
+    test("code", (true, false, true));
+    test("code --wait", (false, false, true));
+    test("/usr/bin/code -n -w", (false, false, true));
+    test("emacsclient", (false, true, false));
+    test("emacsclient -c", (false, true, false));
+    test("gvim -f", (false, false, true));
+    test("vi", (false, false, false));
+    test("my-editor", (false, false, false));
+}
+
+#[test]
+#[cfg(windows)]
+fn test_find_windows() {
+    fn name(command: &str) -> Option<&'static str> {
+        find(OsStr::new(command)).map(|profile| profile.name)
+    }
+
+    assert_eq!(Some("notepad"), name("Notepad.exe"));
+    assert_eq!(Some("notepad"), name("NOTEPAD"));
+    assert_eq!(Some("code"), name("Code.EXE --wait"));
+    assert_eq!("true", normalize("True.exe"));
+}
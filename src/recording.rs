@@ -0,0 +1,80 @@
+//! Scripted in-process editor for testing applications using the crate.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+
+/// Single run of a [`RecordingEditor`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invocation {
+    /// Contents of the file when the editor was run.
+    pub input: Vec<u8>,
+    /// Contents the editor saved into the file.
+    pub output: Vec<u8>,
+}
+
+/// In-process editor which replies with scripted responses and records what
+/// it has seen.
+///
+/// Each time the editor is run, it reads the file being edited, replaces its
+/// contents with the next scripted response and records both in its log.
+/// This allows tests to drive multi-edit flows (such as validation loops of
+/// [`Edit::edit_or_default`](`crate::Edit::edit_or_default`))
+/// deterministically and check what the user would have seen in each round.
+/// If the editor is run more times than there are responses, it fails with
+/// an error.
+///
+/// The editor is installed with
+/// [`Edit::with_inprocess_editor`](`crate::Edit::with_inprocess_editor`):
+///
+/// ```
+/// # #[cfg(feature = "with_tempfile")] {
+/// let recorder = run_editor::RecordingEditor::new([b"bar".to_vec()]);
+/// let buf = run_editor::edit()
+///     .with_inprocess_editor(|path| recorder.edit(path))
+///     .buffer(b"foo".to_vec())
+///     .unwrap();
+/// assert_eq!(b"bar", buf.as_slice());
+/// assert_eq!(b"foo", recorder.log()[0].input.as_slice());
+/// # }
+/// ```
+///
+/// This requires `test-util` Cargo feature to be enabled.
+#[derive(Debug, Default)]
+pub struct RecordingEditor {
+    /// Responses to save in subsequent runs of the editor.
+    responses: RefCell<VecDeque<Vec<u8>>>,
+    /// Log of the runs so far.
+    log: RefCell<Vec<Invocation>>,
+}
+
+impl RecordingEditor {
+    /// Constructs editor which saves given responses in order.
+    pub fn new(responses: impl IntoIterator<Item = Vec<u8>>) -> Self {
+        Self {
+            responses: RefCell::new(responses.into_iter().collect()),
+            log: RefCell::default(),
+        }
+    }
+
+    /// Runs the editor on given file.
+    ///
+    /// This is meant to be passed to
+    /// [`Edit::with_inprocess_editor`](`crate::Edit::with_inprocess_editor`).
+    pub fn edit(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let output =
+            self.responses.borrow_mut().pop_front().ok_or_else(|| {
+                std::io::Error::other("no more scripted editor responses")
+            })?;
+        let input = std::fs::read(path)?;
+        std::fs::write(path, &output)?;
+        self.log.borrow_mut().push(Invocation { input, output });
+        Ok(())
+    }
+
+    /// Returns log of the editor runs so far.
+    pub fn log(&self) -> Vec<Invocation> { self.log.borrow().clone() }
+
+    /// Returns number of scripted responses which haven’t been used yet.
+    pub fn remaining(&self) -> usize { self.responses.borrow().len() }
+}
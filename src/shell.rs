@@ -0,0 +1,322 @@
+//! Helpers for dealing with shell command strings.
+
+use std::ffi::{OsStr, OsString};
+
+
+/// Splits a command into words following POSIX shell quoting rules.
+///
+/// Only quoting is interpreted, i.e. backslash escapes as well as single and
+/// double quotes.  Expansions, operators and other shell syntax is left as is
+/// such that for example `vi | cat` is split into three words.
+///
+/// Returns `None` if the command has an unterminated quote or a trailing
+/// backslash.
+pub(super) fn split(command: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut words = Vec::new();
+    let mut word: Option<Vec<u8>> = None;
+    let mut bytes = command.iter().copied();
+    while let Some(ch) = bytes.next() {
+        match ch {
+            ch if is_blank(ch) => words.extend(word.take()),
+            b'\\' => word.get_or_insert_with(Vec::new).push(bytes.next()?),
+            b'\'' => {
+                let word = word.get_or_insert_with(Vec::new);
+                loop {
+                    match bytes.next()? {
+                        b'\'' => break,
+                        ch => word.push(ch),
+                    }
+                }
+            }
+            b'"' => {
+                let word = word.get_or_insert_with(Vec::new);
+                loop {
+                    match bytes.next()? {
+                        b'"' => break,
+                        b'\\' => match bytes.next()? {
+                            ch @ (b'"' | b'\\' | b'$' | b'`') => word.push(ch),
+                            b'\n' => (),
+                            ch => word.extend_from_slice(&[b'\\', ch]),
+                        },
+                        ch => word.push(ch),
+                    }
+                }
+            }
+            ch => word.get_or_insert_with(Vec::new).push(ch),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// Splits a command into its first word and the rest without interpreting
+/// quoting.
+///
+/// Leading white space is skipped and the returned first word is exactly as
+/// written in the command (i.e. any quotes are kept).  Returns `None` if the
+/// command is empty or malformed; see [`split`].
+pub(super) fn split_first(command: &[u8]) -> Option<(&[u8], &[u8])> {
+    let start = command.iter().position(|ch| !is_blank(*ch))?;
+    let command = &command[start..];
+    let mut quote = None;
+    let mut idx = 0;
+    while idx < command.len() {
+        let ch = command[idx];
+        match (quote, ch) {
+            (None, ch) if is_blank(ch) => break,
+            (None, b'\\') | (Some(b'"'), b'\\') => idx += 1,
+            (None, b'\'' | b'"') => quote = Some(ch),
+            (Some(q), ch) if q == ch => quote = None,
+            _ => (),
+        }
+        idx += 1;
+    }
+    if quote.is_some() || idx > command.len() {
+        None
+    } else {
+        Some(command.split_at(idx))
+    }
+}
+
+/// Returns whether a command uses shell syntax beyond simple words.
+///
+/// The command is assumed to be well-formed; see [`split`].  It uses shell
+/// syntax if it contains, outside of single quotes, operators (such as pipes,
+/// redirections or `&&`), expansions (such as `$HOME`, `*` or `~`), comments,
+/// new lines or variable assignments.  Such commands cannot be executed
+/// without a shell.
+pub(super) fn needs_shell(command: &[u8]) -> bool {
+    let mut quote = None;
+    let mut word_start = true;
+    let mut first_word = true;
+    let mut idx = 0;
+    while idx < command.len() {
+        let ch = command[idx];
+        match (quote, ch) {
+            (Some(b'\''), b'\'') | (Some(b'"'), b'"') => quote = None,
+            (Some(b'\''), _) => (),
+            (Some(_), b'$' | b'`') => return true,
+            (Some(_), b'\\') => idx += 1,
+            (Some(_), _) => (),
+            (None, b'\n') => return true,
+            (None, ch) if is_blank(ch) => {
+                first_word &= word_start;
+                word_start = true;
+                idx += 1;
+                continue;
+            }
+            (None, b'\\') => idx += 1,
+            (None, b'\'' | b'"') => quote = Some(ch),
+            (None, b'|' | b'&' | b';' | b'<' | b'>' | b'(' | b')') => {
+                return true
+            }
+            (None, b'$' | b'`' | b'*' | b'?' | b'[') => return true,
+            (None, b'#' | b'~' | b'!') if word_start => return true,
+            (None, b'=') if first_word => return true,
+            (None, _) => (),
+        }
+        word_start = false;
+        idx += 1;
+    }
+    false
+}
+
+/// Returns whether character separates words in a shell.
+fn is_blank(ch: u8) -> bool { matches!(ch, b' ' | b'\t' | b'\n') }
+
+/// Returns the program, i.e. the first word, of a shell command.
+///
+/// Returns `None` if the command is empty or malformed; see [`split`].
+pub(super) fn program(command: &OsStr) -> Option<OsString> {
+    let words = split(&to_bytes(command))?;
+    words.into_iter().next().map(from_bytes)
+}
+
+/// Quotes a word so that a shell interprets it literally.
+///
+/// The word is enclosed in single quotes with any single quotes inside it
+/// replaced by `'\''`.
+pub(super) fn quote(word: &[u8]) -> Vec<u8> {
+    let mut quoted = Vec::with_capacity(word.len() + 2);
+    quoted.push(b'\'');
+    for &ch in word {
+        if ch == b'\'' {
+            quoted.extend_from_slice(b"'\\''");
+        } else {
+            quoted.push(ch);
+        }
+    }
+    quoted.push(b'\'');
+    quoted
+}
+
+/// Joins words into a single line meant for displaying to humans.
+///
+/// Words which contain characters other than letters, digits and a few safe
+/// punctuation characters are quoted as with [`quote`].  Invalid UTF-8
+/// sequences are replaced by U+FFFD REPLACEMENT CHARACTER and control
+/// characters (such as new lines) are escaped (e.g. `\n`) so that the result
+/// always fits a single line.
+pub(super) fn display<'a>(
+    words: impl IntoIterator<Item = &'a OsStr>,
+) -> String {
+    let is_safe =
+        |ch: &u8| ch.is_ascii_alphanumeric() || b"%+,-./:=@_".contains(ch);
+    let mut line = String::new();
+    for word in words {
+        let word = to_bytes(word);
+        let word = if !word.is_empty() && word.iter().all(is_safe) {
+            word
+        } else {
+            std::borrow::Cow::Owned(quote(&word))
+        };
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        for ch in String::from_utf8_lossy(&word).chars() {
+            if ch.is_control() {
+                line.extend(ch.escape_default());
+            } else {
+                line.push(ch);
+            }
+        }
+    }
+    line
+}
+
+
+/// Returns bytes of an [`OsStr`].
+///
+/// On systems where [`OsStr`] is not a sequence of bytes, the string is
+/// converted lossily to UTF-8.
+pub(super) fn to_bytes(value: &OsStr) -> std::borrow::Cow<'_, [u8]> {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        return std::borrow::Cow::Borrowed(value.as_bytes());
+    }
+    #[allow(unreachable_code)]
+    match value.to_string_lossy() {
+        std::borrow::Cow::Borrowed(value) => {
+            std::borrow::Cow::Borrowed(value.as_bytes())
+        }
+        std::borrow::Cow::Owned(value) => {
+            std::borrow::Cow::Owned(value.into_bytes())
+        }
+    }
+}
+
+/// Converts bytes into an [`OsString`]; reverse of [`to_bytes`].
+pub(super) fn from_bytes(bytes: Vec<u8>) -> OsString {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        return OsString::from_vec(bytes);
+    }
+    #[allow(unreachable_code)]
+    OsString::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+
+#[test]
+fn test_split() {
+    fn test(want: Option<&[&str]>, command: &str) {
+        let got = split(command.as_bytes());
+        let got = got.as_ref().map(|words| {
+            words
+                .iter()
+                .map(|word| std::str::from_utf8(word).unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(want, got.as_deref(), "command: {command}");
+    }
+
+    test(Some(&[]), "");
+    test(Some(&[]), "  \t ");
+    test(Some(&["vi"]), "vi");
+    test(Some(&["code", "--wait"]), "  code   --wait ");
+    test(Some(&["my editor", "-x"]), "'my editor' -x");
+    test(Some(&["my editor", "-x"]), "\"my editor\" -x");
+    test(Some(&["my editor"]), "my\\ editor");
+    test(Some(&["a\"b", "a\\b", "$x"]), "\"a\\\"b\" 'a\\b' \"\\$x\"");
+    test(Some(&["foo", ""]), "foo ''");
+    test(Some(&["vi", "|", "cat"]), "vi | cat");
+    test(None, "'vi");
+    test(None, "\"vi");
+    test(None, "vi\\");
+}
+
+#[test]
+fn test_quote() {
+    fn test(want: &str, word: &str) {
+        let got = quote(word.as_bytes());
+        assert_eq!(want, std::str::from_utf8(&got).unwrap());
+        assert_eq!(Some(vec![word.as_bytes().to_vec()]), split(&got));
+    }
+
+    test("''", "");
+    test("'foo'", "foo");
+    test("'my file'", "my file");
+    test("'$HOME/\"x\"'", "$HOME/\"x\"");
+    test("'it'\\''s'", "it's");
+}
+
+#[test]
+fn test_display() {
+    fn test(want: &str, words: &[&str]) {
+        assert_eq!(want, display(words.iter().map(OsStr::new)));
+    }
+
+    test("", &[]);
+    test("/bin/sh -c 'vi \"$@\"' sh", &["/bin/sh", "-c", "vi \"$@\"", "sh"]);
+    test("vi '' 'it'\\''s'", &["vi", "", "it's"]);
+    test("vi 'a\\nb'", &["vi", "a\nb"]);
+}
+
+#[test]
+fn test_split_first() {
+    fn test(want: Option<(&str, &str)>, command: &str) {
+        let got = split_first(command.as_bytes()).map(|(first, rest)| {
+            let first = std::str::from_utf8(first).unwrap();
+            (first, std::str::from_utf8(rest).unwrap())
+        });
+        assert_eq!(want, got, "command: {command}");
+    }
+
+    test(None, "");
+    test(None, "  ");
+    test(Some(("vi", "")), "vi");
+    test(Some(("code", " --wait")), "  code --wait");
+    test(Some(("'my editor'", " -x")), "'my editor' -x");
+    test(Some(("\"my \\\" editor\"", " -x")), "\"my \\\" editor\" -x");
+    test(Some(("my\\ editor", " -x")), "my\\ editor -x");
+    test(None, "'vi -x");
+    test(None, "vi\\");
+}
+
+#[test]
+fn test_needs_shell() {
+    fn test(want: bool, command: &str) {
+        assert_eq!(want, needs_shell(command.as_bytes()), "command: {command}");
+    }
+
+    test(false, "");
+    test(false, "vi");
+    test(false, "  code --wait --new-window ");
+    test(false, "'my editor' -c 'set tw=72' \"a|b\" c\\|d");
+    test(false, "vi -c set\\ tw=72 a#b a~b");
+    test(false, "'$HOME/bin/vi'");
+    test(true, "vi | cat");
+    test(true, "vi && true");
+    test(true, "vi; rm -rf /");
+    test(true, "vi >/dev/null");
+    test(true, "vi $HOME/file");
+    test(true, "vi \"$HOME/file\"");
+    test(true, "vi `pwd`");
+    test(true, "vi *.txt");
+    test(true, "vi ~/file");
+    test(true, "vi # comment");
+    test(true, "vi\nrm file");
+    test(true, "TERM=xterm vi");
+    test(true, "(vi)");
+}
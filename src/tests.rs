@@ -1,4 +1,19 @@
 use std::ffi::{OsStr, OsString};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Lock serialising tests which change process-wide editor override.
+///
+/// Tests which resolve the editor need to hold it shared while tests which
+/// call [`super::set_global_editor`] need to hold it exclusively.
+static GLOBAL_LOCK: RwLock<()> = RwLock::new(());
+
+fn shared() -> RwLockReadGuard<'static, ()> {
+    GLOBAL_LOCK.read().unwrap_or_else(|err| err.into_inner())
+}
+
+fn exclusive() -> RwLockWriteGuard<'static, ()> {
+    GLOBAL_LOCK.write().unwrap_or_else(|err| err.into_inner())
+}
 
 /// Wrapper for setting environment variables and restoring them to old
 /// state once the object is dropped.
@@ -51,6 +66,7 @@ fn test_get_editor() {
         assert_eq!(want, got);
     }
 
+    let _lock = shared();
     let var = OsStr::new("FOO_EDITOR");
     let command = OsStr::new("command");
 
@@ -64,68 +80,2460 @@ fn test_get_editor() {
     test(super::edit().with_editor_variable(var).with(command), [
         "command", "command", "command", "foo",
     ]);
+
+    test(super::edit().with_opt(None).with_editor_variable_opt(None), [
+        "vi", "editor", "visual", "visual",
+    ]);
+    test(super::edit().with_opt(Some(command)), [
+        "command", "command", "command", "command",
+    ]);
+    test(super::edit().with_editor_variable_opt(Some(var)), [
+        "vi", "editor", "visual", "foo",
+    ]);
+    test(super::edit().with(command).with_opt(None), [
+        "command", "command", "command", "command",
+    ]);
 }
 
+#[test]
+fn test_editor_program() {
+    let _lock = shared();
+    let mut env = TestEnv::default();
+    env.del("VISUAL");
 
-/// Constructs an `Edit` object which changes `foo` on each line in the file
-/// with `bar`.
-// TODO(mina86): Do something more portable than `sed -i`.  However, keep in
-// mind this needs to be something that does atomic write so that we test
-// whether we correctly handle that.
-fn substitute_foo_bar<'a>() -> super::Edit<'a> {
+    env.set("EDITOR", "code --wait");
+    assert_eq!("code", super::edit().editor_program());
+    env.set("EDITOR", "'/opt/my editor' -w");
+    assert_eq!("/opt/my editor", super::edit().editor_program());
+    env.set("EDITOR", "'vim");
+    assert_eq!("'vim", super::edit().editor_program());
+}
+
+#[test]
+fn test_command_from_reader() {
+    let _lock = shared();
+    let mut env = TestEnv::default();
+    env.del("VISUAL");
+    env.set("EDITOR", "editor");
+
+    let test = |input: &'static [u8]| {
+        let mut edit = super::edit();
+        edit.with_command_from_reader(input);
+        edit.editor().into_string().unwrap()
+    };
+    assert_eq!("nano -R", test(b"nano -R"));
+    assert_eq!("nano -R", test(b"nano -R\n"));
+    assert_eq!("nano -R", test(b"nano -R\r\nvi\n"));
+    assert_eq!("editor", test(b""));
+    assert_eq!("editor", test(b" \nvi\n"));
+
+    // Explicitly set command takes priority.
+    let mut reader = &b"nano\n"[..];
     let mut edit = super::edit();
-    edit.with(OsStr::new("sed -i -e s/foo/bar/"));
-    edit
+    edit.with(OsStr::new("command")).with_command_from_reader(&mut reader);
+    assert_eq!("command", edit.editor());
+    drop(edit);
+    assert_eq!(b"nano\n", reader);
+
+    // The reader is read only once.
+    let mut reader = &b"nano\nvi\n"[..];
+    let mut edit = super::edit();
+    edit.with_command_from_reader(&mut reader);
+    assert_eq!("nano", edit.editor());
+    assert_eq!("nano", edit.editor());
+    drop(edit);
+    assert_eq!(b"vi\n", reader);
 }
 
+/// Tests whether process-wide override takes priority over everything else.
 #[test]
-fn test_edit_file() {
-    use std::io::Write;
+fn test_global_editor() {
+    let _lock = exclusive();
+    let mut env = TestEnv::default();
+    env.set("FOO_EDITOR", "foo");
+    env.set("VISUAL", "visual");
+
+    let var = OsStr::new("FOO_EDITOR");
+    let command = OsStr::new("command");
+
+    super::set_global_editor(OsString::from("global"));
+    assert_eq!("global", super::edit().editor());
+    assert_eq!(
+        "global",
+        super::edit().with_editor_variable(var).with(command).editor()
+    );
+
+    super::clear_global_editor();
+    assert_eq!("visual", super::edit().editor());
+    assert_eq!("command", super::edit().with(command).editor());
+}
 
+/// Tests whether `Page::pager` resolves pager command correctly.
+#[test]
+fn test_get_pager() {
+    let _lock = exclusive();
     let tmpdir = tempfile::tempdir().unwrap();
-    let path = tmpdir.path().join("temp-file");
+    let mut env = TestEnv::default();
+    env.del("PAGER");
+    env.set("PATH", tmpdir.path().to_str().unwrap());
+    assert_eq!("more", super::page().pager());
 
-    // Using ":" or "true" as editor should result in a no-op.  It’s
-    // actually not observable whether something happened or not but at
-    // least we’ll detect if handling of those special commands is broken.
-    super::edit().with(OsStr::new(":")).file(&path).unwrap();
-    assert!(!path.exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
 
-    std::fs::File::create(&path).unwrap().write_all(b"foo\n").unwrap();
-    substitute_foo_bar().file(&path).unwrap();
-    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+        let less = tmpdir.path().join("less");
+        std::fs::write(&less, "#!/bin/sh\n").unwrap();
+        let perms = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(&less, perms).unwrap();
+        assert_eq!("less", super::page().pager());
+    }
+    assert_eq!("more", super::page().with(OsStr::new("more")).pager());
+
+    env.set("PAGER", "most");
+    assert_eq!("most", super::page().pager());
+    assert_eq!("more", super::page().with(OsStr::new("more")).pager());
 }
 
 #[test]
-fn test_edit_buffer() {
-    let got =
-        super::edit().with(OsStr::new(":")).buffer(b"foo\n".to_vec()).unwrap();
+fn test_page() {
+    let tmpdir = tempfile::tempdir().unwrap();
+    let copy = tmpdir.path().join("copy");
+    let mut cmd = OsString::from("f() { cp \"$1\" ");
+    cmd.push(&copy);
+    cmd.push("; }; f");
+    let mut page = super::page();
+    page.with(&cmd);
+
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo").unwrap();
+    page.file(&path).unwrap();
+    assert_eq!(b"foo", std::fs::read(&copy).unwrap().as_slice());
+
+    page.buffer(b"bar").unwrap();
+    assert_eq!(b"bar", std::fs::read(&copy).unwrap().as_slice());
+}
+
+#[test]
+fn test_file_region() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    let data = "foo\n# BEGIN\nfoo\n# END\nfoo\n";
+    std::fs::write(&path, data).unwrap();
+
+    substitute_foo_bar().file_region(&path, "# BEGIN", "# END").unwrap();
+    assert_eq!(
+        "foo\n# BEGIN\nbar\n# END\nfoo\n",
+        std::fs::read_to_string(&path).unwrap()
+    );
+
+    // Missing new line at the end of region is added.
+    super::edit()
+        .with(OsStr::new("f() { printf baz >\"$1\"; }; f"))
+        .file_region(&path, "# BEGIN", "# END")
+        .unwrap();
+    assert_eq!(
+        "foo\n# BEGIN\nbaz\n# END\nfoo\n",
+        std::fs::read_to_string(&path).unwrap()
+    );
+
+    let err = substitute_foo_bar()
+        .file_region(&path, "# BEGIN", "# STOP")
+        .unwrap_err();
+    assert_eq!(super::ErrorKind::MissingMarker, err.kind());
+    assert!(err.to_string().ends_with(": region marker not found: # STOP"));
+}
+
+#[test]
+fn test_file_with_rotation() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    let backup = |n: usize| tmpdir.path().join(format!("file.{}", n));
+    let read = |path: &std::path::Path| std::fs::read_to_string(path).ok();
+    let mut edit = super::edit();
+    edit.with(OsStr::new("f() { echo x >>\"$1\"; }; f"));
+
+    std::fs::write(&path, "v\n").unwrap();
+    edit.file_with_rotation(&path, 2).unwrap();
+    assert_eq!(Some("v\nx\n"), read(&path).as_deref());
+    assert_eq!(Some("v\n"), read(&backup(1)).as_deref());
+    assert_eq!(None, read(&backup(2)));
+
+    edit.file_with_rotation(&path, 2).unwrap();
+    assert_eq!(Some("v\nx\nx\n"), read(&path).as_deref());
+    assert_eq!(Some("v\nx\n"), read(&backup(1)).as_deref());
+    assert_eq!(Some("v\n"), read(&backup(2)).as_deref());
+
+    // The oldest backup is dropped.
+    edit.file_with_rotation(&path, 2).unwrap();
+    assert_eq!(Some("v\nx\nx\n"), read(&backup(1)).as_deref());
+    assert_eq!(Some("v\nx\n"), read(&backup(2)).as_deref());
+    assert_eq!(None, read(&backup(3)));
+
+    // Unchanged file and failed editor don’t rotate.
+    edit.with(OsStr::new("true #")).file_with_rotation(&path, 2).unwrap();
+    edit.with(OsStr::new("false")).file_with_rotation(&path, 2).unwrap_err();
+    assert_eq!(Some("v\nx\nx\n"), read(&backup(1)).as_deref());
+}
+
+#[test]
+fn test_buffer_ranges() {
+    let _lock = shared();
+    let (buf, ranges) =
+        substitute_foo_bar().buffer_ranges(b"a foo b\n".to_vec()).unwrap();
+    assert_eq!(b"a bar b\n", buf.as_slice());
+    assert_eq!(vec![2..5], ranges);
+
+    let (buf, ranges) =
+        substitute_foo_bar().buffer_ranges(b"a baz b\n".to_vec()).unwrap();
+    assert_eq!(b"a baz b\n", buf.as_slice());
+    assert_eq!(Vec::<super::ByteRange>::new(), ranges);
+}
+
+#[test]
+fn test_buffer_append_to() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let dst = tmpdir.path().join("log");
+
+    // Missing file is created.
+    substitute_foo_bar().buffer_append_to(b"foo 1\n".to_vec(), &dst).unwrap();
+    assert_eq!(b"bar 1\n", std::fs::read(&dst).unwrap().as_slice());
+
+    substitute_foo_bar().buffer_append_to(b"foo 2\n".to_vec(), &dst).unwrap();
+    assert_eq!(b"bar 1\nbar 2\n", std::fs::read(&dst).unwrap().as_slice());
+
+    // Failed edit leaves the file as is.
+    super::edit()
+        .with(OsStr::new("false"))
+        .buffer_append_to(b"foo 3\n".to_vec(), &dst)
+        .unwrap_err();
+    assert_eq!(b"bar 1\nbar 2\n", std::fs::read(&dst).unwrap().as_slice());
+}
+
+#[test]
+fn test_preview_then_edit() {
+    let _lock = exclusive();
+    let mut env = TestEnv::default();
+
+    env.set("PAGER", ":");
+    let got = super::edit()
+        .with(OsStr::new(":"))
+        .preview_then_edit(b"foo\n".to_vec())
+        .unwrap();
     assert_eq!(b"foo\n", got.as_slice());
 
-    let got = substitute_foo_bar().buffer(b"foo\n".to_vec()).unwrap();
-    assert_eq!(b"bar\n", got.as_slice());
+    // Missing pager is skipped.
+    env.set("PAGER", "/nonexistent/pager");
+    let got = substitute_foo_bar().preview_then_edit(b"foo\n".to_vec());
+    assert_eq!(b"bar\n", got.unwrap().as_slice());
+
+    #[cfg(unix)]
+    {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let pager = tmpdir.path().join("pager");
+        let copy = tmpdir.path().join("copy");
+        write_script(&pager, &format!("cp \"$1\" '{}'", copy.display()));
+        env.set("PAGER", pager.to_str().unwrap());
+        let got = substitute_foo_bar().preview_then_edit(b"foo\n".to_vec());
+        assert_eq!(b"bar\n", got.unwrap().as_slice());
+        assert_eq!(b"foo\n", std::fs::read(&copy).unwrap().as_slice());
+    }
 }
 
 #[test]
-fn test_edit_file_copy() {
+fn test_to_owned() {
+    let _lock = shared();
+    let mut env = TestEnv::default();
+    env.del("FOO_EDITOR");
+
+    let variable = OsString::from("FOO_EDITOR");
+    let command = OsString::from("command");
+    let owned = super::edit()
+        .with_editor_variable(&variable)
+        .with(&command)
+        .with_max_attempts(5)
+        .to_owned();
+    drop((variable, command));
+
+    let edit = owned.edit();
+    assert_eq!(5, edit.max_attempts);
+    assert_eq!("command", edit.editor());
+    env.set("FOO_EDITOR", "foo");
+    assert_eq!("foo", edit.editor());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "editor filter")]
+fn test_to_owned_editor_filter() {
+    super::edit().with_editor_filter(Ok).to_owned();
+}
+
+
+#[test]
+fn test_is_terminal_editor() {
+    let _lock = shared();
+    let test = |command: &str| {
+        super::edit().with(OsStr::new(command)).is_terminal_editor()
+    };
+
+    assert_eq!(Some(true), test("vi"));
+    assert_eq!(Some(true), test("/usr/bin/nvim -p"));
+    assert_eq!(Some(false), test("code --wait"));
+    assert_eq!(None, test("emacs"));
+    assert_eq!(None, test("my-editor"));
+}
+
+
+#[test]
+fn test_audit_command() {
+    let _lock = shared();
+    let path = std::path::Path::new("/tmp/some file");
+    let got =
+        super::edit().with(OsStr::new("vi -p")).audit_command(path).unwrap();
+    let want = ["-c", "vi -p \"$@\"", "sh", "/tmp/some file"];
+    let want = want.iter().map(OsString::from).collect::<Vec<_>>();
+    assert_eq!((OsString::from("/bin/sh"), want), got);
+
+    let got = super::edit()
+        .with(OsStr::new("'my vi' -p"))
+        .path_position(super::PathPosition::First)
+        .audit_command(path)
+        .unwrap();
+    assert_eq!("'my vi' \"$@\" -p", got.1[1]);
+
+    let got = super::edit()
+        .with(OsStr::new("vi"))
+        .with_editor_filter(|_| Ok("nvim".into()))
+        .audit_command(path)
+        .unwrap();
+    assert_eq!("nvim \"$@\"", got.1[1]);
+
+    let err = super::edit()
+        .with(OsStr::new("vi"))
+        .with_editor_filter(|_| Err(super::Error::editor_rejected("no")))
+        .audit_command(path)
+        .unwrap_err();
+    assert_eq!(super::ErrorKind::EditorRejected, err.kind());
+}
+
+#[test]
+fn test_try_editor() {
+    let _lock = shared();
+    let mut env = TestEnv::default();
+    env.del("VISUAL");
+    env.del("EDITOR");
+
+    let mut edit = super::edit();
+    assert_eq!("vi", edit.try_editor().unwrap());
+    edit.use_default_editor(false);
+    assert_eq!("vi", edit.editor());
+    let err = edit.try_editor().unwrap_err();
+    assert_eq!(crate::ErrorKind::NotConfigured, err.kind());
+    let err = edit.file("/nonexistent/file").unwrap_err();
+    assert_eq!(crate::ErrorKind::NotConfigured, err.kind());
+
+    env.set("EDITOR", " \t");
+    let err = edit.try_editor().unwrap_err();
+    assert_eq!(crate::ErrorKind::EmptyCommand, err.kind());
+    let err = edit.file("/nonexistent/file").unwrap_err();
+    assert_eq!(crate::ErrorKind::EmptyCommand, err.kind());
+
+    env.set("EDITOR", "nano");
+    assert_eq!("nano", edit.try_editor().unwrap());
+    assert_eq!(
+        "command",
+        edit.with(OsStr::new("command")).try_editor().unwrap()
+    );
+}
+
+#[test]
+fn test_filetype_editor() {
+    let _lock = shared();
+    let names = std::cell::RefCell::new(Vec::new());
+    let mut edit = substitute_foo_bar();
+    edit.with_filetype_editor(".toml", "sed -i -e s/foo/toml/")
+        .on_launch(|path| names.borrow_mut().push(path.to_path_buf()));
+
+    edit.with_suffix(".toml");
+    assert_eq!(b"toml\n", edit.buffer(b"foo\n".to_vec()).unwrap().as_slice());
+    edit.with_suffix(".md");
+    assert_eq!(b"bar\n", edit.buffer(b"foo\n".to_vec()).unwrap().as_slice());
+
+    drop(edit);
+    let names = names.into_inner();
+    assert_eq!(Some(OsStr::new("toml")), names[0].extension());
+    assert_eq!(Some(OsStr::new("md")), names[1].extension());
+}
+
+#[test]
+fn test_no_shell() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
+
+    let mut edit = substitute_foo_bar();
+    edit.no_shell(true);
+    let (program, args) = edit.audit_command(&path).unwrap();
+    assert_eq!("sed", program);
+    assert_eq!(["-i", "-e", "s/foo/bar/"], &args[..3]);
+    edit.file(&path).unwrap();
+    assert_eq!("bar\n", std::fs::read_to_string(&path).unwrap());
+
+    for editor in ["sed -i -e s/foo/bar/ | cat", "sed 's/a/b/", "$EDITOR"] {
+        let err = edit.with(OsStr::new(editor)).file(&path).unwrap_err();
+        assert_ne!(crate::ErrorKind::SpawnFailed, err.kind(), "{}", editor);
+    }
+    let err = edit.with(OsStr::new("vi | cat")).file(&path).unwrap_err();
+    assert_eq!(crate::ErrorKind::ShellForbidden, err.kind());
+    assert_eq!("vi | cat: editor command requires a shell", err.to_string());
+}
+
+#[test]
+fn test_warn_readonly_editor() {
+    let _lock = exclusive();
+    let mut env = TestEnv::default();
+    env.set("EDITOR", "less -R");
+    env.del("VISUAL");
+
+    // In-process editor takes precedence over the editor command.
+    super::edit()
+        .with_inprocess_editor(|_| Ok(()))
+        .warn_readonly_editor(true)
+        .file("file")
+        .unwrap();
+
+    let mut edit = super::edit();
+    edit.warn_readonly_editor(true);
+    let err = edit.file("file").unwrap_err();
+    assert_eq!(crate::ErrorKind::ReadonlyEditor, err.kind());
+    assert_eq!(
+        "less -R: not a text editor; it cannot save files",
+        err.to_string()
+    );
+    let err = edit.with(OsStr::new("/bin/cat")).file("file").unwrap_err();
+    assert_eq!(crate::ErrorKind::ReadonlyEditor, err.kind());
+
+    edit.with_readonly_editors(["view"]);
+    edit.with(OsStr::new("view")).buffer(Vec::new()).unwrap_err();
+    let buf = edit.with(OsStr::new("cat")).buffer(b"foo".to_vec()).unwrap();
+    assert_eq!(b"foo", buf.as_slice());
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_with_arg0() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let read = || std::fs::read_to_string(&path).unwrap();
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new(
+        "sh -c 'tr \"\\0\" \"\\n\" </proc/$$/cmdline | head -n1 >\"$0\"'",
+    ))
+    .no_shell(true);
+    edit.file(&path).unwrap();
+    assert_eq!("sh\n", read());
+
+    edit.with_arg0(OsStr::new("view")).file(&path).unwrap();
+    assert_eq!("view\n", read());
+
+    // With a shell, argv[0] is not changed.
+    edit.no_shell(false).file(&path).unwrap();
+    assert_ne!("view\n", read());
+}
+
+#[test]
+fn test_is_noop() {
+    let _lock = exclusive();
+    let mut env = TestEnv::default();
+    env.set("VISUAL", "true");
+
+    let mut edit = super::edit();
+    assert!(edit.is_noop());
+    assert!(edit.with(OsStr::new(":")).is_noop());
+    assert!(!edit.with(OsStr::new("vi")).is_noop());
+    assert!(!edit.with(OsStr::new(": ;")).is_noop());
+    edit.with_editor_filter(|_| Ok(OsString::from(":")));
+    assert!(edit.is_noop());
+    edit.with_inprocess_editor(|_| Ok(()));
+    assert!(!edit.is_noop());
+}
+
+#[test]
+fn test_editor_args() {
+    let _lock = shared();
+    let mut edit = super::edit();
+    edit.with_editor_args("vim", &["-p"])
+        .with_editor_args("code", &["--wait"])
+        .with_leading_args(&["-R"])
+        .with_trailing_args(&["+1"]);
+
+    fn args<'a>(edit: &mut super::Edit<'a>, editor: &'a str) -> Vec<OsString> {
+        let (_, args) =
+            edit.with(OsStr::new(editor)).audit_command("f").unwrap();
+        args.into_iter().skip(3).collect()
+    }
+    assert_eq!(["-R", "-p", "f", "+1"], args(&mut edit, "vim").as_slice());
+    assert_eq!(
+        ["-R", "-p", "f", "+1"],
+        args(&mut edit, "/usr/bin/vim -u x").as_slice()
+    );
+    assert_eq!(["-R", "f", "+1"], args(&mut edit, "nano").as_slice());
+    assert_eq!(["-R", "f", "+1"], args(&mut edit, "vim.tiny").as_slice());
+
+    // Path variable is referenced after editor-specific arguments.
+    edit.with_path_var(OsStr::new("FILE"));
+    let (_, args) = edit.with(OsStr::new("vim")).audit_command("f").unwrap();
+    assert_eq!("vim \"${1}\" \"${2}\" \"$FILE\" \"${3}\"", args[1]);
+}
+
+#[test]
+fn test_command_display() {
+    let _lock = shared();
+    let line = super::edit()
+        .with(OsStr::new("vi -p"))
+        .command_display("/tmp/some file")
+        .unwrap();
+    assert!(line.contains("vi -p"), "{}", line);
+    assert!(line.ends_with(" '/tmp/some file'"), "{}", line);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let path = std::path::Path::new(OsStr::from_bytes(b"/tmp/\xff\n"));
+        let line =
+            super::edit().with(OsStr::new("vi")).command_display(path).unwrap();
+        assert!(line.ends_with(" '/tmp/\u{FFFD}\\n'"), "{}", line);
+    }
+}
+
+#[test]
+fn test_is_interactive() {
+    // Rerun the test binary with piped standard IO and have it report.
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "tests::is_interactive_child", "--ignored"])
+        .args(["--nocapture", "--test-threads=1"])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .output()
+        .unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+    assert!(output.contains("is_interactive: false\n"), "{}", output);
+}
+
+/// Helper for [`test_is_interactive`] run in a subprocess.
+#[test]
+#[ignore]
+fn is_interactive_child() {
+    println!("is_interactive: {}", super::is_interactive());
+}
+
+#[test]
+fn test_fallback_line_editor() {
     use std::io::Write;
 
+    // Rerun the test binary with piped standard input and have it report.
+    let mut child =
+        std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "tests::fallback_line_editor_child", "--ignored"])
+            .args(["--nocapture", "--test-threads=1"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+    child.stdin.take().unwrap().write_all(b"new\ncontent").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+    assert!(output.contains("edited: \"new\\ncontent\"\n"), "{}", output);
+    assert!(output.contains("again: \"\"\n"), "{}", output);
+}
+
+/// Helper for [`test_fallback_line_editor`] run in a subprocess.
+#[test]
+#[ignore]
+fn fallback_line_editor_child() {
+    let mut edit = super::edit();
+    edit.with(OsStr::new("false")).fallback_line_editor(true);
+    let buf = String::from_utf8(edit.buffer(b"old".to_vec()).unwrap());
+    println!("edited: {:?}", buf.unwrap());
+    let buf = String::from_utf8(edit.buffer(b"old".to_vec()).unwrap());
+    println!("again: {:?}", buf.unwrap());
+}
+
+#[test]
+fn test_launcher() {
+    let _lock = shared();
     let tmpdir = tempfile::tempdir().unwrap();
-    let src = tmpdir.path().join("src");
-    let dst = tmpdir.path().join("dst");
-    std::fs::File::create(&src).unwrap().write_all(b"foo\n").unwrap();
-    std::fs::File::create(&dst).unwrap().write_all(b"oof\n").unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
 
-    // Test destination is not changed on failure.
-    let res = super::edit().with(OsStr::new("false")).file_copy(&src, &dst);
+    let mut edit = substitute_foo_bar();
+    edit.with_launcher(&["env", "FOO=bar"]);
+    let (program, args) = edit.audit_command(&path).unwrap();
+    assert_eq!("env", program);
+    assert_eq!(["FOO=bar", "/bin/sh", "-c"], args[..3]);
+    edit.file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+
+    // No-op editor doesn’t run the launcher.
+    edit.with(OsStr::new(":")).with_launcher(&["false"]);
+    edit.file(&path).unwrap();
+    edit.with(OsStr::new("true #")).file(&path).unwrap_err();
+
+    edit.with_launcher(&[] as &[&str]);
+    edit.file(&path).unwrap();
+}
+
+#[test]
+fn test_shell_quote() {
+    fn test(want: &str, value: &str) {
+        assert_eq!(want, super::shell_quote(OsStr::new(value)));
+    }
+
+    test("'/tmp/some file'", "/tmp/some file");
+    test("'/tmp/it'\\''s'", "/tmp/it's");
+    test("'$HOME/$(rm -rf)'", "$HOME/$(rm -rf)");
+    test("''", "");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+        let got = super::shell_quote(OsStr::from_bytes(b"\xff'\xfe"));
+        assert_eq!(b"'\xff'\\''\xfe'".to_vec(), got.into_vec());
+    }
+
+    // The quoted string passed through a shell yields the original value.
+    let value = "it's $HOME \\ \"x\"";
+    let mut cmd = OsString::from("printf %s ");
+    cmd.push(super::shell_quote(OsStr::new(value)));
+    let out = std::process::Command::new("/bin/sh").arg("-c").arg(cmd).output();
+    assert_eq!(value.as_bytes(), out.unwrap().stdout.as_slice());
+}
+
+#[test]
+#[cfg(feature = "ssh")]
+fn test_remote_command() {
+    let _lock = shared();
+    let got = super::edit()
+        .with(OsStr::new("vi -p"))
+        .remote_command("example.org", "/etc/it's here");
+    let want = ["-t", "--", "example.org", "vi -p '/etc/it'\\''s here'"];
+    assert_eq!((OsString::from("ssh"), want.map(OsString::from).to_vec()), got);
+
+    let got = super::edit()
+        .with(OsStr::new("vi -p"))
+        .with_remote_editor(OsStr::new("nano"))
+        .remote_command("example.org", "/etc/motd");
+    let want = ["-t", "--", "example.org", "nano '/etc/motd'"];
+    assert_eq!((OsString::from("ssh"), want.map(OsString::from).to_vec()), got);
+}
+
+#[test]
+fn test_with_path_var() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, b"foo\n").unwrap();
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new("f() { sed -i -e s/foo/$#/ \"$WRAPPER_FILE\"; }; f"))
+        .with_path_var(OsStr::new("WRAPPER_FILE"));
+
+    let (_, args) = edit.audit_command(&path).unwrap();
     assert_eq!(
-        "false: terminated with exit status: 1",
-        res.unwrap_err().to_string()
+        "f() { sed -i -e s/foo/$#/ \"$WRAPPER_FILE\"; }; f \"$WRAPPER_FILE\"",
+        args[1]
     );
-    assert_eq!(b"foo\n", std::fs::read(&src).unwrap().as_slice());
-    assert_eq!(b"oof\n", std::fs::read(&dst).unwrap().as_slice());
+    assert_eq!(3, args.len());
 
-    substitute_foo_bar().file_copy(&src, &dst).unwrap();
-    assert_eq!(b"foo\n", std::fs::read(&src).unwrap().as_slice());
-    assert_eq!(b"bar\n", std::fs::read(&dst).unwrap().as_slice());
+    // The function is called with one argument so `$#` is 1.
+    edit.file(&path).unwrap();
+    assert_eq!(b"1\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_preserve_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("script");
+    let mode = |path| std::fs::metadata(path).unwrap().permissions().mode();
+    let reset = || {
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        let perms = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(&path, perms).unwrap();
+    };
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new("chmod a-x"));
+    reset();
+    edit.file(&path).unwrap();
+    assert_eq!(0o644, mode(&path) & 0o777);
+
+    reset();
+    edit.preserve_mode(true).file(&path).unwrap();
+    assert_eq!(0o755, mode(&path) & 0o777);
+}
+
+#[test]
+fn test_feed_stdin() {
+    let _lock = shared();
+    let mut edit = super::edit();
+    edit.with(OsStr::new("sed -e s/foo/bar/ >")).feed_stdin(true);
+    assert_eq!(b"bar\n", edit.buffer(b"foo\n".to_vec()).unwrap().as_slice());
+
+    // Editor which doesn’t read its standard input doesn’t block.
+    let buf = vec![b'x'; 1 << 20];
+    let got = super::edit()
+        .with(OsStr::new(":"))
+        .feed_stdin(true)
+        .buffer(buf.clone())
+        .unwrap();
+    assert_eq!(buf, got);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_post_command() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let marker = dir.path().join("marker");
+    let script = format!(
+        "test -e '{}' || {{ touch '{0}'; echo 'line 1: bad' >&2; exit 1; }}",
+        marker.display()
+    );
+
+    let seen = std::cell::RefCell::new(Vec::new());
+    let mut edit = super::edit();
+    edit.with_inprocess_editor(|path| {
+        let data = std::fs::read_to_string(path)?;
+        if seen.borrow().is_empty() {
+            std::fs::write(path, "value\n")?;
+        }
+        seen.borrow_mut().push(data);
+        Ok(())
+    })
+    .with_retry_delay(std::time::Duration::ZERO)
+    .with_post_command(&["sh", "-c", &script]);
+    let buf = edit.buffer(b"seed\n".to_vec()).unwrap();
+    assert_eq!(b"value\n", buf.as_slice());
+    assert_eq!(["seed\n", "# line 1: bad\nvalue\n"], seen.take().as_slice());
+
+    // Validator which keeps failing.
+    let err = edit
+        .with_max_attempts(2)
+        .with_post_command(&["sh", "-c", "exit 1"])
+        .buffer(b"seed\n".to_vec())
+        .unwrap_err();
+    assert_eq!(crate::ErrorKind::Invalid, err.kind());
+    assert_eq!(2, seen.borrow().len());
+
+    // Fed editor is fed on retry as well and header lines it left are
+    // stripped even if it deleted some of them.
+    let marker = dir.path().join("marker-fed");
+    let script = format!(
+        "test -e '{}' || {{ touch '{0}'; printf 'a\\nb\\n' >&2; exit 1; }}",
+        marker.display()
+    );
+    let buf = super::edit()
+        .with(OsStr::new("sed -e '/^# a$/d' >"))
+        .feed_stdin(true)
+        .with_retry_delay(std::time::Duration::ZERO)
+        .with_post_command(&["sh", "-c", &script])
+        .buffer(b"seed\n".to_vec())
+        .unwrap();
+    assert_eq!("seed\n", String::from_utf8_lossy(&buf));
+}
+
+#[test]
+fn test_edit_as() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    let dst = dir.path().join("dst");
+    std::fs::write(&src, "foo\n").unwrap();
+
+    let edit = substitute_foo_bar();
+    edit.edit_as(&src, &dst).unwrap();
+    assert_eq!("foo\n", std::fs::read_to_string(&src).unwrap());
+    assert_eq!("bar\n", std::fs::read_to_string(&dst).unwrap());
+
+    // On failure destination is not touched.
+    std::fs::write(&src, "baz\n").unwrap();
+    super::edit().with(OsStr::new("false")).edit_as(&src, &dst).unwrap_err();
+    assert_eq!("bar\n", std::fs::read_to_string(&dst).unwrap());
+    let err = edit.edit_as(dir.path().join("missing"), &dst).unwrap_err();
+    assert_eq!(Some(std::io::ErrorKind::NotFound), err.io_error_kind());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_edit_as_in_place() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
+    let perms = std::fs::Permissions::from_mode(0o640);
+    std::fs::set_permissions(&path, perms).unwrap();
+    let inode = |path| {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).unwrap().ino()
+    };
+    let before = inode(&path);
+
+    let edit = substitute_foo_bar();
+    edit.edit_as(&path, &path).unwrap();
+    assert_eq!("bar\n", std::fs::read_to_string(&path).unwrap());
+    let metadata = std::fs::metadata(&path).unwrap();
+    assert_eq!(0o640, metadata.permissions().mode() & 0o777);
+    // The file has been replaced rather than modified.
+    let after = inode(&path);
+    assert_ne!(before, after);
+
+    // Nothing to change so the file is not rewritten.
+    edit.edit_as(&path, &path).unwrap();
+    assert_eq!(after, inode(&path));
+    assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_apply() {
+    use std::os::unix::fs::MetadataExt;
+
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "foo\nbaz\n").unwrap();
+    let inode = std::fs::metadata(&path).unwrap().ino();
+
+    super::edit().apply(&path, "s/foo/bar/").unwrap();
+    assert_eq!("bar\nbaz\n", std::fs::read_to_string(&path).unwrap());
+    // The file has been replaced rather than modified in place.
+    assert_ne!(inode, std::fs::metadata(&path).unwrap().ino());
+
+    // On failure, the file is not touched.
+    let err = super::edit()
+        .with_apply_filter(&["sh", "-c", "echo qux; exit 1", "sh"])
+        .apply(&path, "s/bar/qux/")
+        .unwrap_err();
+    assert_eq!(crate::ErrorKind::EditorFailed, err.kind());
+    assert_eq!("bar\nbaz\n", std::fs::read_to_string(&path).unwrap());
+    assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
+}
+
+#[test]
+fn test_transact() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("jobs");
+    std::fs::write(&path, "4\n").unwrap();
+    let read = || std::fs::read_to_string(&path).unwrap();
+
+    let attempts = std::cell::Cell::new(0);
+    let live = std::cell::Cell::new("4\n");
+    let mut edit = super::edit();
+    edit.with_inprocess_editor(|file| {
+        attempts.set(attempts.get() + 1);
+        // The live file is not touched until the result is valid.
+        assert_eq!(live.get(), read());
+        let value = if attempts.get() == 1 { "many\n" } else { "8\n" };
+        std::fs::write(file, value)
+    })
+    .with_retry_delay(std::time::Duration::ZERO);
+    let parse = |buf: &[u8]| String::from_utf8_lossy(buf).trim().parse::<u32>();
+    assert_eq!(8, edit.transact(&path, parse).unwrap());
+    assert_eq!(2, attempts.get());
+    assert_eq!("8\n", read());
+
+    attempts.set(0);
+    live.set("8\n");
+    let err = edit.with_max_attempts(1).transact(&path, parse).unwrap_err();
+    assert_eq!(crate::ErrorKind::Invalid, err.kind());
+    assert_eq!("8\n", read());
+}
+
+#[test]
+fn test_resume() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let tempdir = dir.path().to_path_buf();
+
+    // The user saved some changes but the editor failed.
+    let mut edit = super::edit();
+    edit.with_tempdir_fn(move || tempdir.clone()).keep_on_error(true);
+    edit.with(OsStr::new("f() { sed -i -e s/foo/bar/ \"$1\"; false; }; f"))
+        .buffer(b"foo baz\n".to_vec())
+        .unwrap_err();
+    let kept = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(1, kept.len());
+    assert_eq!("bar baz\n", std::fs::read_to_string(&kept[0]).unwrap());
+
+    // Resuming continues from the saved changes.
+    let mut edit = super::edit();
+    edit.with(OsStr::new("sed -i -e s/baz/qux/"));
+    assert_eq!(b"bar qux\n", edit.resume(&kept[0]).unwrap().as_slice());
+    assert!(!kept[0].exists());
+
+    let err = edit.resume(&kept[0]).unwrap_err();
+    assert_eq!(Some(std::io::ErrorKind::NotFound), err.io_error_kind());
+    let err = edit.resume(dir.path()).unwrap_err();
+    assert_eq!(Some(std::io::ErrorKind::InvalidInput), err.io_error_kind());
+
+    // Validator runs on the resumed file as well.
+    std::fs::write(&kept[0], b"bar baz\n").unwrap();
+    let mut edit = super::edit();
+    edit.with(OsStr::new("sed -i -e s/baz/qux/"))
+        .with_post_command(&["grep", "-q", "quux"]);
+    let err = edit.resume(&kept[0]).unwrap_err();
+    assert_eq!(crate::ErrorKind::Invalid, err.kind());
+    assert!(kept[0].exists());
+}
+
+#[test]
+fn test_relevant_env_vars() {
+    let vars = super::relevant_env_vars();
+    assert!(vars.contains(&OsStr::new("VISUAL")));
+    assert!(vars.contains(&OsStr::new("EDITOR")));
+
+    let mut edit = super::edit();
+    assert_eq!(vars, edit.relevant_env_vars());
+    edit.with_editor_variable(OsStr::new("FOO_EDITOR"));
+    let mut want = vec![OsStr::new("FOO_EDITOR")];
+    want.extend(vars);
+    assert_eq!(want, edit.relevant_env_vars());
+}
+
+#[test]
+fn test_with_label() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+
+    super::edit()
+        .with(OsStr::new("f() { printf %s \"$RUN_EDITOR_LABEL\" >\"$1\"; }; f"))
+        .with_label(OsStr::new("commit message"))
+        .to_owned()
+        .edit()
+        .file(&path)
+        .unwrap();
+    assert_eq!("commit message", std::fs::read_to_string(&path).unwrap());
+}
+
+/// Writes an executable shell script with given body to given path.
+#[cfg(unix)]
+fn write_script(path: &std::path::Path, body: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::write(path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_path_position() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let script = tmpdir.path().join("editor");
+    let path = tmpdir.path().join("file");
+    let args = tmpdir.path().join("args");
+    write_script(
+        &script,
+        &format!("printf '%s\\n' \"$@\" >'{}'", args.display()),
+    );
+
+    let mut editor = script.into_os_string();
+    editor.push(" --flag");
+    let mut edit = super::edit();
+    edit.with(&editor);
+
+    edit.file(&path).unwrap();
+    let want = format!("--flag\n{}\n", path.display());
+    assert_eq!(want, std::fs::read_to_string(&args).unwrap());
+
+    edit.path_position(super::PathPosition::First).file(&path).unwrap();
+    let want = format!("{}\n--flag\n", path.display());
+    assert_eq!(want, std::fs::read_to_string(&args).unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_leading_trailing_args() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let script = tmpdir.path().join("editor");
+    let path = tmpdir.path().join("file");
+    let args = tmpdir.path().join("args");
+    write_script(
+        &script,
+        &format!("printf '%s\\n' \"$@\" >'{}'", args.display()),
+    );
+
+    let mut editor = script.into_os_string();
+    editor.push(" --flag");
+    let mut edit = super::edit();
+    edit.with(&editor)
+        .with_leading_args(&["-a", "b c"])
+        .with_trailing_args(&["$d"]);
+
+    edit.file(&path).unwrap();
+    let want = format!("--flag\n-a\nb c\n{}\n$d\n", path.display());
+    assert_eq!(want, std::fs::read_to_string(&args).unwrap());
+
+    edit.path_position(super::PathPosition::First).file(&path).unwrap();
+    let want = format!("-a\nb c\n{}\n$d\n--flag\n", path.display());
+    assert_eq!(want, std::fs::read_to_string(&args).unwrap());
+
+    edit.with_path_var(OsStr::new("FILE")).file(&path).unwrap();
+    assert_eq!(want, std::fs::read_to_string(&args).unwrap());
+    let (_, got) = edit.audit_command(&path).unwrap();
+    let command = got[1].to_str().unwrap();
+    let want = " \"${1}\" \"${2}\" \"$FILE\" \"${3}\" --flag";
+    assert!(command.ends_with(want), "{}", command);
+    assert_eq!(["sh", "-a", "b c", "$d"].map(OsString::from), got[2..]);
+}
+
+/// Tests that `Edit::audit_command` returns what `Edit::file` spawns by having
+/// the editor dump its shell’s command line.
+#[test]
+#[cfg(target_os = "linux")]
+fn test_audit_command_matches_file() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    let editor = OsStr::new("tr '\\0' '\\n' </proc/$$/cmdline >");
+
+    let check = |edit: &super::Edit| {
+        edit.file(&path).unwrap();
+        let (program, args) = edit.audit_command(&path).unwrap();
+        let mut want = program.into_string().unwrap();
+        for arg in args {
+            want.push('\n');
+            want.push_str(arg.to_str().unwrap());
+        }
+        want.push('\n');
+        assert_eq!(want, std::fs::read_to_string(&path).unwrap());
+    };
+
+    let mut edit = super::edit();
+    check(edit.with(editor));
+
+    // The filter is applied to the audited command as it is to the spawned
+    // one.
+    let mut edit = super::edit();
+    edit.with(OsStr::new("vi")).with_editor_filter(|_| Ok(editor.to_owned()));
+    check(&edit);
+}
+
+
+/// Constructs an `Edit` object which changes `foo` on each line in the file
+/// with `bar`.
+// TODO(mina86): Do something more portable than `sed -i`.  However, keep in
+// mind this needs to be something that does atomic write so that we test
+// whether we correctly handle that.
+fn substitute_foo_bar<'a>() -> super::Edit<'a> {
+    let mut edit = super::edit();
+    edit.with(OsStr::new("sed -i -e s/foo/bar/"));
+    edit
+}
+
+#[test]
+fn test_edit_file() {
+    use std::io::Write;
+    let _lock = shared();
+
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("temp-file");
+
+    // Using ":" or "true" as editor should result in a no-op.  It’s
+    // actually not observable whether something happened or not but at
+    // least we’ll detect if handling of those special commands is broken.
+    super::edit().with(OsStr::new(":")).file(&path).unwrap();
+    assert!(!path.exists());
+
+    std::fs::File::create(&path).unwrap().write_all(b"foo\n").unwrap();
+    substitute_foo_bar().file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+/// Tests whether paths of third-party types are accepted without conversion.
+#[test]
+fn test_edit_file_utf8_path() {
+    // Stand-in for a UTF-8 path type such as `camino::Utf8PathBuf`.
+    struct Utf8PathBuf(String);
+
+    impl AsRef<std::path::Path> for Utf8PathBuf {
+        fn as_ref(&self) -> &std::path::Path { self.0.as_ref() }
+    }
+
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let dir = tmpdir.path().to_str().unwrap();
+    let src = Utf8PathBuf(format!("{dir}/src"));
+    let dst = Utf8PathBuf(format!("{dir}/dst"));
+
+    std::fs::write(&src, b"foo\n").unwrap();
+    substitute_foo_bar().file_copy(&src, &dst).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&dst).unwrap().as_slice());
+
+    substitute_foo_bar().file(&src).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&src).unwrap().as_slice());
+}
+
+#[test]
+fn test_file_parsed_once() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    let parse = |buf: &[u8]| std::str::from_utf8(buf).unwrap().trim().parse();
+
+    std::fs::write(&path, "42\n").unwrap();
+    let got =
+        super::edit().with(OsStr::new(":")).file_parsed_once(&path, parse);
+    assert_eq!(Ok(42u32), got.unwrap());
+
+    std::fs::write(&path, "foo\n").unwrap();
+    let mut count = 0;
+    let got = substitute_foo_bar()
+        .file_parsed_once(&path, |buf| {
+            count += 1;
+            parse(buf).map_err(|err: std::num::ParseIntError| err.to_string())
+        })
+        .unwrap();
+    assert_eq!(Err::<u32, _>("invalid digit found in string".into()), got);
+    assert_eq!(1, count);
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+fn test_shell_fallback() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
+
+    let mut edit = substitute_foo_bar();
+    edit.shell = Some(OsStr::new("/nonexistent/sh"));
+    let err = edit.file(&path).unwrap_err();
+    assert!(err.to_string().starts_with("sh: "), "{}", err);
+    assert_eq!(Some(std::io::ErrorKind::NotFound), err.io_error_kind());
+    assert_eq!(b"foo\n", std::fs::read(&path).unwrap().as_slice());
+
+    edit.with_shell_fallback(OsStr::new("/usr/bin/env sh"));
+    edit.file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+
+    // Errors other than missing shell don’t trigger the fallback.
+    std::fs::write(&path, "foo\n").unwrap();
+    edit.shell = Some(tmpdir.path().as_os_str());
+    let err = edit.file(&path).unwrap_err();
+    assert_eq!(Some(std::io::ErrorKind::PermissionDenied), err.io_error_kind());
+    assert_eq!(b"foo\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+fn test_copy_progress() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let src = tmpdir.path().join("src");
+    let dst = tmpdir.path().join("dst");
+    let data = (0..200_000u32).map(|n| n as u8).collect::<Vec<u8>>();
+    std::fs::write(&src, &data).unwrap();
+
+    let reports = std::cell::RefCell::new(Vec::new());
+    super::edit()
+        .with(OsStr::new("true"))
+        .on_copy_progress(|copied| reports.borrow_mut().push(copied))
+        .file_copy(&src, &dst)
+        .unwrap();
+    let reports = reports.into_inner();
+    assert_eq!(Some(&(data.len() as u64)), reports.last());
+    assert!(reports.windows(2).all(|w| w[0] < w[1]), "{:?}", reports);
+    assert_eq!(data, std::fs::read(&dst).unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_with_lock() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
+
+    // While the outer edit holds the lock, the inner one is run as the editor
+    // and must fail.
+    let mut inner = super::edit();
+    inner.with(OsStr::new(":")).with_lock(true);
+    let got = std::cell::RefCell::new(None);
+    super::edit()
+        .with_lock(true)
+        .with_inprocess_editor(|path| {
+            *got.borrow_mut() =
+                Some(inner.file(path).map_err(|err| err.kind()));
+            Ok(())
+        })
+        .file(&path)
+        .unwrap();
+    let got = got.into_inner().unwrap();
+    assert_eq!(Ok(()), got, "no-op editor doesn’t take the lock");
+
+    inner.with(OsStr::new("touch"));
+    let got = std::cell::RefCell::new(None);
+    super::edit()
+        .with_lock(true)
+        .with_inprocess_editor(|path| {
+            *got.borrow_mut() =
+                Some(inner.file(path).map_err(|err| err.kind()));
+            Ok(())
+        })
+        .file(&path)
+        .unwrap();
+    assert_eq!(Some(Err(super::ErrorKind::Locked)), got.into_inner());
+    assert!(!tmpdir.path().join("file.lock").exists());
+
+    // Once released, the lock can be taken again.
+    inner.file(&path).unwrap();
+
+    // Leftover lock file keeps the file locked.
+    std::fs::write(tmpdir.path().join("file.lock"), "").unwrap();
+    let err = inner.file(&path).unwrap_err();
+    assert_eq!(super::ErrorKind::Locked, err.kind());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_warn_unwritable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
+    let perms = std::fs::Permissions::from_mode(0o444);
+    std::fs::set_permissions(&path, perms).unwrap();
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new("false"));
+    assert_eq!(
+        super::ErrorKind::EditorFailed,
+        edit.file(&path).unwrap_err().kind()
+    );
+
+    edit.warn_unwritable(true);
+    let res = edit.file(&path);
+    // Privileged users can write read-only files.
+    if std::fs::OpenOptions::new().write(true).open(&path).is_err() {
+        let err = res.unwrap_err();
+        assert_eq!(super::ErrorKind::PermissionDenied, err.kind());
+        assert!(err.to_string().ends_with("file: file is not writable"));
+    }
+
+    edit.file(tmpdir.path().join("missing")).unwrap_err();
+
+    edit.with(OsStr::new("touch"));
+    let err = edit.file(tmpdir.path()).unwrap_err();
+    assert_eq!(super::ErrorKind::IsADirectory, err.kind());
+
+    // Opening FIFO for writing would block until someone reads from it.
+    let fifo = tmpdir.path().join("fifo");
+    let status = std::process::Command::new("mkfifo").arg(&fifo).status();
+    assert!(status.unwrap().success());
+    edit.file(&fifo).unwrap();
+}
+
+#[test]
+fn test_warn_above_size() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+
+    // Missing files are not affected.
+    super::edit()
+        .with(OsStr::new("touch"))
+        .warn_above_size(Some(0))
+        .file(&path)
+        .unwrap();
+    assert!(path.exists());
+
+    let mut edit = substitute_foo_bar();
+    edit.warn_above_size(Some(4));
+    std::fs::write(&path, b"foo\n").unwrap();
+    edit.file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+
+    std::fs::write(&path, b"foo foo\n").unwrap();
+    let err = edit.file(&path).unwrap_err().to_string();
+    let want = format!(
+        "{}: file too large (8 bytes, limit is 4 bytes)",
+        path.display()
+    );
+    assert_eq!(want, err);
+    assert_eq!(b"foo foo\n", std::fs::read(&path).unwrap().as_slice());
+
+    edit.warn_above_size(None).file(&path).unwrap();
+    assert_eq!(b"bar foo\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+fn test_editor_filter() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, b"foo\n").unwrap();
+
+    let mut edit = super::edit();
+    edit.with_editor_filter(|editor| {
+        if editor == "vi" {
+            Ok("sed -i -e s/foo/bar/".into())
+        } else {
+            let editor = editor.to_string_lossy();
+            Err(super::Error::editor_rejected(format!(
+                "{editor} is not allowed"
+            )))
+        }
+    });
+
+    let err = edit.with(OsStr::new("emacs")).file(&path).unwrap_err();
+    assert_eq!("editor rejected: emacs is not allowed", err.to_string());
+    assert_eq!(b"foo\n", std::fs::read(&path).unwrap().as_slice());
+
+    edit.with(OsStr::new("vi")).file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+fn test_strict_command() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new("sed -i -e 's/foo/bar/"));
+    let err = edit.strict_command(true).file(&path).unwrap_err();
+    assert_eq!(
+        "sed -i -e 's/foo/bar/: malformed editor command: unterminated quote \
+         or trailing backslash",
+        err.to_string()
+    );
+    let err = edit.buffer(Vec::new()).unwrap_err();
+    assert!(err.to_string().contains("malformed editor command"));
+
+    edit.with(OsStr::new("sed -i -e 's/foo/bar/'"));
+    edit.file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+fn test_prepare() {
+    let _lock = shared();
+    let prepare = |editor: &str| {
+        super::edit()
+            .with(OsStr::new(editor))
+            .prepare()
+            .map(|prepared| prepared.editor().map(OsStr::to_os_string))
+            .map_err(|err| err.to_string())
+    };
+
+    assert_eq!(Ok(None), prepare(":"));
+    assert_eq!(
+        Ok(Some("sed -e s/foo/bar/".into())),
+        prepare("sed -e s/foo/bar/")
+    );
+    assert_eq!(Ok(Some("/bin/sh -c :".into())), prepare("/bin/sh -c :"));
+    assert_eq!(
+        Err("no-such-editor: editor not found".into()),
+        prepare("no-such-editor --wait")
+    );
+    assert_eq!(
+        Err("/no/such/editor: editor not found".into()),
+        prepare("/no/such/editor")
+    );
+    assert_eq!(Err("/dev/null: editor not found".into()), prepare("/dev/null"));
+
+    let edit = substitute_foo_bar();
+    let got = edit.prepare().unwrap().buffer(b"foo\n".to_vec()).unwrap();
+    assert_eq!(b"bar\n", got.as_slice());
+}
+
+/// Tests whether `Edit::use_dev_tty` works whether or not the terminal is
+/// available.
+///
+/// When running under `cargo test` from a terminal, the editor has its
+/// standard input and output connected to it.  In environments without
+/// controlling terminal (e.g. CI), this checks the fallback to inherited
+/// standard IO.  To verify manually that the terminal is used, run
+/// `cargo run --example edit -- --tty <file> </dev/null` from a terminal with
+/// `vi` as the editor and observe that the editor works normally even though
+/// standard input is not a terminal.
+#[test]
+#[cfg(unix)]
+fn test_use_dev_tty() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, b"foo\n").unwrap();
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new("sed -i -e s/foo/bar/")).use_dev_tty(true);
+    edit.file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_ok_signals() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    let mut edit = super::edit();
+    edit.with(OsStr::new("kill -INT $$ #"));
+
+    let err = edit.file(&path).unwrap_err();
+    assert_eq!(
+        "kill -INT $$ #: terminated by signal: 2 (SIGINT)",
+        err.to_string()
+    );
+
+    edit.with_ok_signals(&[15, 2]).file(&path).unwrap();
+    edit.with_ok_signals(&[15]).file(&path).unwrap_err();
+}
+
+#[test]
+fn test_success_predicate() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    let mut edit = super::edit();
+    edit.with_success_predicate(|status| status.code() == Some(2));
+
+    edit.with(OsStr::new("exit 2 #")).file(&path).unwrap();
+    let err = edit.with(OsStr::new("exit 1 #")).file(&path).unwrap_err();
+    assert_eq!("exit 1 #: terminated with exit status: 1", err.to_string());
+    let err = edit.with(OsStr::new("exit 0 #")).file(&path).unwrap_err();
+    assert_eq!(super::ErrorKind::EditorFailed, err.kind());
+}
+
+#[test]
+fn test_timeout() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, b"foo\n").unwrap();
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new("exec sleep 10 #"));
+
+    let err = edit.try_with_timeout(std::time::Duration::ZERO).err();
+    assert_eq!("timeout must be positive", err.unwrap().to_string());
+    let err = edit.try_with_timeout(std::time::Duration::MAX).err();
+    assert_eq!("timeout must not exceed a year", err.unwrap().to_string());
+
+    let timeout = std::time::Duration::from_millis(100);
+    let start = std::time::Instant::now();
+    let err = edit.try_with_timeout(timeout).unwrap().file(&path).unwrap_err();
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!(
+        "exec sleep 10 #: killed after timeout of 100ms",
+        err.to_string()
+    );
+
+    // Editor which finishes in time is not affected.
+    substitute_foo_bar()
+        .with_timeout(std::time::Duration::from_secs(60))
+        .file(&path)
+        .unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_atomic() {
+    use std::os::unix::fs::PermissionsExt;
+    let _lock = shared();
+
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    let mode = |path: &std::path::Path| {
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o7777
+    };
+    let reset = || {
+        std::fs::write(&path, b"foo\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o751))
+            .unwrap();
+    };
+
+    // The editor replaces the file with a new one thus dropping its mode.
+    let mut edit = super::edit();
+    edit.with(OsStr::new(
+        "f() { sed -e s/foo/bar/ \"$1\" >\"$1.new\" && chmod 600 \"$1.new\" \
+         && mv \"$1.new\" \"$1\"; }; f",
+    ));
+
+    reset();
+    edit.file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+    assert_eq!(0o600, mode(&path));
+
+    reset();
+    edit.atomic(true).file(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+    assert_eq!(0o751, mode(&path));
+
+    // On failure the file is not touched and no temporary files are left.
+    reset();
+    edit.with(OsStr::new("f() { echo baz >\"$1\"; false; }; f"));
+    edit.file(&path).unwrap_err();
+    assert_eq!(b"foo\n", std::fs::read(&path).unwrap().as_slice());
+    assert_eq!(1, std::fs::read_dir(tmpdir.path()).unwrap().count());
+}
+
+#[test]
+fn test_edit_buffer() {
+    let _lock = shared();
+
+    let got =
+        super::edit().with(OsStr::new(":")).buffer(b"foo\n".to_vec()).unwrap();
+    assert_eq!(b"foo\n", got.as_slice());
+
+    let got = substitute_foo_bar().buffer(b"foo\n".to_vec()).unwrap();
+    assert_eq!(b"bar\n", got.as_slice());
+}
+
+#[test]
+fn test_buffer_opt() {
+    let _lock = shared();
+    let got = super::edit()
+        .with(OsStr::new("f() { : >\"$1\"; }; f"))
+        .buffer_opt(b"foo".to_vec())
+        .unwrap();
+    assert_eq!(None, got);
+
+    let got = substitute_foo_bar().buffer_opt(b"foo".to_vec()).unwrap();
+    assert_eq!(Some(b"bar".to_vec()), got);
+}
+
+#[test]
+fn test_buffer_changed() {
+    let _lock = shared();
+    let mut edit = super::edit();
+    edit.with(OsStr::new("f() { printf 'foo  \\nbar\\n\\n' >\"$1\"; }; f"));
+
+    let got = edit.buffer_changed(b"foo\nbar".to_vec()).unwrap();
+    assert_eq!((b"foo  \nbar\n\n".to_vec(), true), got);
+    let got = edit.buffer_changed(b"foo  \nbar\n\n".to_vec()).unwrap();
+    assert_eq!((b"foo  \nbar\n\n".to_vec(), false), got);
+
+    edit.change_compare(super::Compare::IgnoreTrailingWs);
+    let got = edit.buffer_changed(b"foo\nbar".to_vec()).unwrap();
+    assert_eq!((b"foo  \nbar\n\n".to_vec(), false), got);
+    let got = edit.buffer_changed(b"foo\n\nbar".to_vec()).unwrap();
+    assert_eq!((b"foo  \nbar\n\n".to_vec(), true), got);
+    let got = edit.buffer_changed(b" foo\nbar".to_vec()).unwrap();
+    assert_eq!((b"foo  \nbar\n\n".to_vec(), true), got);
+}
+
+#[test]
+fn test_edit_buffer_to() {
+    let _lock = shared();
+    let mut out = Vec::new();
+    substitute_foo_bar().buffer_to(b"foo\n".to_vec(), &mut out).unwrap();
+    assert_eq!(b"bar\n", out.as_slice());
+
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("out");
+    let file = std::fs::File::create(&path).unwrap();
+    substitute_foo_bar().buffer_to(b"foo\n".to_vec(), file).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+
+    let mut out = Vec::new();
+    super::edit()
+        .with(OsStr::new(":"))
+        .buffer_to(b"foo\n".to_vec(), &mut out)
+        .unwrap();
+    assert_eq!(b"foo\n", out.as_slice());
+
+    let out = &mut [0u8; 2][..];
+    let err = substitute_foo_bar().buffer_to(b"foo\n".to_vec(), out);
+    assert_eq!(
+        "output: failed to write whole buffer",
+        err.unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_buffer_hashed() {
+    let _lock = shared();
+    let (a, hash_a) =
+        substitute_foo_bar().buffer_hashed(b"foo".to_vec()).unwrap();
+    let (b, hash_b) = super::edit()
+        .with(OsStr::new(":"))
+        .buffer_hashed(b"bar".to_vec())
+        .unwrap();
+    let (c, hash_c) =
+        substitute_foo_bar().buffer_hashed(b"baz".to_vec()).unwrap();
+    assert_eq!((&b"bar"[..], &b"bar"[..]), (a.as_slice(), b.as_slice()));
+    assert_eq!(hash_a, hash_b);
+    assert_eq!(b"baz", c.as_slice());
+    assert_ne!(hash_a, hash_c);
+}
+
+#[test]
+fn test_strip_bom() {
+    let _lock = shared();
+    let mut edit = super::edit();
+    edit.with(OsStr::new("f() { printf '\\357\\273\\277foo' >\"$1\"; }; f"));
+
+    let got = edit.buffer(Vec::new()).unwrap();
+    assert_eq!(b"\xEF\xBB\xBFfoo", got.as_slice());
+
+    let got = edit.strip_bom(true).buffer(Vec::new()).unwrap();
+    assert_eq!(b"foo", got.as_slice());
+
+    edit.with(OsStr::new("f() { printf '\\357\\273\\277foo'; }; f"));
+    let got = edit.buffer_capture_stdout(Vec::new()).unwrap();
+    assert_eq!(b"foo", got.as_slice());
+}
+
+#[test]
+fn test_edit_buffer_string_lossy() {
+    let _lock = shared();
+
+    let got = substitute_foo_bar().buffer_string_lossy("foo\n".into()).unwrap();
+    assert_eq!("bar\n", got);
+
+    let got = super::edit()
+        .with(OsStr::new("f() { printf 'a\\377b\\n' >\"$1\"; }; f"))
+        .buffer_string_lossy("foo\n".into())
+        .unwrap();
+    assert_eq!("a\u{FFFD}b\n", got);
+}
+
+#[test]
+fn test_prompt() {
+    let _lock = shared();
+    let got = substitute_foo_bar()
+        .prompt("Enter your name:\nfoo", "  foo baz \n")
+        .unwrap();
+    assert_eq!("bar baz", got);
+
+    let got = super::edit()
+        .with(OsStr::new("f() { echo '# Enter your name:' >\"$1\"; }; f"))
+        .prompt("Enter your name:", "foo")
+        .unwrap();
+    assert_eq!("", got);
+}
+
+#[test]
+fn test_edit_buffer_from_lines() {
+    let _lock = shared();
+    let lines = (0..300).map(|n| format!("foo {n}"));
+    let got = substitute_foo_bar().buffer_from_lines(lines).unwrap();
+    let want = (0..300).map(|n| format!("bar {n}")).collect::<Vec<_>>();
+    assert_eq!(want, got);
+
+    let got = super::edit()
+        .with(OsStr::new("f() { printf 'foo\\r\\n\\nbar' >\"$1\"; }; f"))
+        .buffer_from_lines(Vec::new())
+        .unwrap();
+    assert_eq!(["foo", "", "bar"], got.as_slice());
+}
+
+#[test]
+fn test_edit_buffer_capture_stdout() {
+    let _lock = shared();
+
+    let got = super::edit()
+        .with(OsStr::new("sed -e s/foo/bar/"))
+        .buffer_capture_stdout(b"foo\nfoo foo\n".to_vec())
+        .unwrap();
+    assert_eq!(b"bar\nbar foo\n", got.as_slice());
+
+    // Large output doesn’t dead-lock.
+    let buf = b"foo\n".repeat(100_000);
+    let got = super::edit()
+        .with(OsStr::new("cat"))
+        .buffer_capture_stdout(buf.clone())
+        .unwrap();
+    assert_eq!(buf, got);
+
+    let err = super::edit()
+        .with(OsStr::new("f() { cat \"$1\"; false; }; f"))
+        .buffer_capture_stdout(b"foo\n".to_vec())
+        .unwrap_err();
+    assert!(err.to_string().ends_with("terminated with exit status: 1"));
+}
+
+#[test]
+fn test_filters() {
+    let _lock = shared();
+    let filter = |suffix: &'static str| {
+        move |mut buf: Vec<u8>| {
+            buf.extend_from_slice(suffix.as_bytes());
+            Ok(buf)
+        }
+    };
+
+    let got = substitute_foo_bar()
+        .with_pre_filter(filter("pre1 foo\n"))
+        .with_post_filter(filter("post1\n"))
+        .with_pre_filter(filter("pre2 foo\n"))
+        .with_post_filter(filter("post2\n"))
+        .buffer(b"foo\n".to_vec())
+        .unwrap();
+    let want = "bar\npre1 bar\npre2 bar\npost2\npost1\n";
+    assert_eq!(want, std::str::from_utf8(&got).unwrap());
+
+    // Filters are not run for no-op editors.
+    let got = super::edit()
+        .with(OsStr::new(":"))
+        .with_pre_filter(filter("pre\n"))
+        .buffer(b"foo\n".to_vec())
+        .unwrap();
+    assert_eq!(b"foo\n", got.as_slice());
+
+    let err = substitute_foo_bar()
+        .with_post_filter(|_| Err(std::io::Error::other("bad data")))
+        .buffer(b"foo\n".to_vec())
+        .unwrap_err();
+    assert_eq!("filter: bad data", err.to_string());
+}
+
+#[test]
+fn test_reopen_retry() {
+    let _lock = shared();
+
+    // The editor moves the file away and puts it back only after a moment
+    // which simulates a slow atomic rename.
+    let editor = OsStr::new(
+        "f() { sed -e s/foo/bar/ \"$1\" >\"$1.x\"; rm \"$1\"; (sleep 0.1; mv \
+         \"$1.x\" \"$1\") & }; f",
+    );
+
+    let got = super::edit()
+        .with(editor)
+        .reopen_retry(100, std::time::Duration::from_millis(20))
+        .buffer(b"foo\n".to_vec())
+        .unwrap();
+    assert_eq!(b"bar\n", got.as_slice());
+}
+
+#[test]
+fn test_edit_or_default() {
+    let _lock = shared();
+    let parse = |buf: &[u8]| std::str::from_utf8(buf).unwrap().trim().parse();
+
+    // Empty save yields the default.
+    let got = super::edit()
+        .with(OsStr::new("f() { : >\"$1\"; }; f"))
+        .edit_or_default(b"42\n".to_vec(), parse, || 7u32)
+        .unwrap();
+    assert_eq!(7, got);
+
+    // Filled save yields parsed value.
+    let got = substitute_foo_bar()
+        .edit_or_default(b" 42\n".to_vec(), parse, || 7u32)
+        .unwrap();
+    assert_eq!(42, got);
+
+    // Content which never parses results in an error once attempts are
+    // exhausted.
+    let mut count = 0;
+    let got = super::edit()
+        .with(OsStr::new(":"))
+        .with_max_attempts(2)
+        .edit_or_default(
+            b"foo\n".to_vec(),
+            |buf| {
+                count += 1;
+                parse(buf)
+            },
+            || 7u32,
+        )
+        .unwrap_err();
+    assert_eq!(2, count);
+    assert_eq!(
+        "invalid content: invalid digit found in string",
+        got.to_string()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_file_existed() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let mut edit = super::edit();
+    edit.with(OsStr::new("echo foo >>"));
+
+    assert!(!edit.file_existed(&path).unwrap());
+    assert_eq!("foo\n", std::fs::read_to_string(&path).unwrap());
+    assert!(edit.file_existed(&path).unwrap());
+    assert_eq!("foo\nfoo\n", std::fs::read_to_string(&path).unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_create_parents() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("newapp/sub/config");
+
+    let mut edit = super::edit();
+    edit.with(OsStr::new("echo foo >"));
+    edit.file(&path).unwrap_err();
+    assert!(!path.parent().unwrap().exists());
+
+    edit.with_create_parents(true).file(&path).unwrap();
+    assert_eq!("foo\n", std::fs::read_to_string(&path).unwrap());
+}
+
+#[test]
+fn test_dedup_paths() {
+    let _lock = exclusive();
+    let dir = tempfile::tempdir().unwrap();
+    let cwd = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+    std::fs::write("a", "foo").unwrap();
+
+    let opened = std::cell::RefCell::new(Vec::new());
+    let mut edit = super::edit();
+    edit.with_inprocess_editor(|path| {
+        opened.borrow_mut().push(path.to_path_buf());
+        Ok(())
+    });
+    let res = edit.files(["./a", "a", "./b", "b", "x/../b"]);
+    let all = opened.replace(Vec::new());
+    let res = res.and_then(|()| edit.dedup_paths(true).files(["./a", "a"]));
+    let deduped = opened.replace(Vec::new());
+    let res = res.and_then(|()| edit.files(["./b", "b", "x/../b"]));
+    let missing = opened.replace(Vec::new());
+    std::env::set_current_dir(cwd).unwrap();
+    res.unwrap();
+
+    assert_eq!(5, all.len());
+    assert_eq!(vec![std::path::PathBuf::from("./a")], deduped);
+    assert_eq!(vec![std::path::PathBuf::from("./b")], missing);
+}
+
+#[test]
+fn test_files_changed() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let foo = dir.path().join("foo");
+    let bar = dir.path().join("bar");
+    std::fs::write(&foo, "foo\n").unwrap();
+    std::fs::write(&bar, "bar\n").unwrap();
+
+    let changed = substitute_foo_bar().files_changed([&foo, &bar]).unwrap();
+    assert_eq!(vec![(foo.clone(), true), (bar, false)], changed);
+    assert_eq!("bar\n", std::fs::read_to_string(&foo).unwrap());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_file_polling() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    std::fs::write(&path, "foo").unwrap();
+
+    let mut calls = 0;
+    let start = std::time::Instant::now();
+    let err = super::edit()
+        .with(OsStr::new("sleep 10; echo bar >"))
+        .file_polling(&path, || {
+            calls += 1;
+            calls < 3
+        })
+        .unwrap_err();
+    assert_eq!(crate::ErrorKind::Cancelled, err.kind());
+    assert_eq!(3, calls);
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    assert_eq!("foo", std::fs::read_to_string(&path).unwrap());
+
+    let mut calls = 0;
+    super::edit()
+        .with(OsStr::new("echo bar >"))
+        .file_polling(&path, || {
+            calls += 1;
+            true
+        })
+        .unwrap();
+    assert_eq!("bar\n", std::fs::read_to_string(&path).unwrap());
+}
+
+#[test]
+fn test_buffers_named() {
+    let _lock = shared();
+    let mut bufs = std::collections::BTreeMap::new();
+    bufs.insert("a.txt".to_string(), b"foo a\n".to_vec());
+    bufs.insert("b/c".to_string(), b"foo b\n".to_vec());
+
+    let names = std::cell::RefCell::new(Vec::new());
+    let got = super::edit()
+        .with(OsStr::new("sed -i -e s/foo/bar/"))
+        .with_pre_filter(Ok)
+        .buffers_named(bufs.clone())
+        .unwrap();
+    assert_eq!(Some(&b"bar a\n".to_vec()), got.get("a.txt"));
+    assert_eq!(Some(&b"bar b\n".to_vec()), got.get("b/c"));
+    assert_eq!(2, got.len());
+
+    super::edit()
+        .with_inprocess_editor(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+            names.borrow_mut().push(name);
+            Ok(())
+        })
+        .buffers_named(bufs)
+        .unwrap();
+    assert_eq!(vec!["a.txt", "b_c"], names.into_inner());
+}
+
+#[test]
+fn test_buffer_from_lines_cr() {
+    let _lock = shared();
+    let lines = |content: &'static [u8]| {
+        super::edit()
+            .with_inprocess_editor(move |path| std::fs::write(path, content))
+            .buffer_from_lines(vec!["foo".to_string()])
+            .unwrap()
+    };
+    assert_eq!(["foo", "bar", "baz"], lines(b"foo\rbar\rbaz\r").as_slice());
+    assert_eq!(["foo", "bar", "baz"], lines(b"foo\r\nbar\rbaz").as_slice());
+
+    // Buffers are passed through as is.
+    let got = super::edit()
+        .with_inprocess_editor(|path| {
+            let buf = std::fs::read(path)?;
+            std::fs::write(path, [buf.as_slice(), b"baz\r"].concat())
+        })
+        .buffer(b"foo\rbar\r".to_vec())
+        .unwrap();
+    assert_eq!(b"foo\rbar\rbaz\r", got.as_slice());
+}
+
+#[test]
+fn test_buffer_lines_tracked() {
+    use super::LineChange::*;
+
+    let _lock = shared();
+    let lines = vec!["foo".to_string(), "bar".to_string(), "baz".to_string()];
+    let got = super::edit()
+        .with(OsStr::new("sed -i -e s/bar/BAR/ -e '$a qux'"))
+        .buffer_lines_tracked(lines)
+        .unwrap();
+    assert_eq!(
+        vec![
+            Kept { old: 0, line: "foo".to_string() },
+            Modified { old: 1, line: "BAR".to_string() },
+            Kept { old: 2, line: "baz".to_string() },
+            Added { line: "qux".to_string() },
+        ],
+        got
+    );
+}
+
+#[test]
+fn test_tempdir_fn() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let calls = std::cell::Cell::new(0);
+    let seen = std::cell::RefCell::new(None);
+    let mut edit = super::edit();
+    edit.with_tempdir_fn(|| {
+        calls.set(calls.get() + 1);
+        tmpdir.path().to_path_buf()
+    })
+    .with_inprocess_editor(|path| {
+        *seen.borrow_mut() = Some(path.to_path_buf());
+        Ok(())
+    });
+
+    let path = tmpdir.path().join("file");
+    edit.file(&path).unwrap();
+    assert_eq!(0, calls.get());
+
+    edit.buffer(b"foo".to_vec()).unwrap();
+    assert_eq!(1, calls.get());
+    let temp = seen.borrow_mut().take().unwrap();
+    assert_eq!(Some(tmpdir.path()), temp.parent());
+}
+
+#[test]
+fn test_debug_keep_temps() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let src = tmpdir.path().join("src");
+    let dst = tmpdir.path().join("dst");
+    std::fs::write(&src, b"foo\n").unwrap();
+
+    let run = |keep: bool, keep_on_error: bool, fail: bool| {
+        let seen = std::cell::RefCell::new(None);
+        let mut edit = super::edit();
+        edit.debug_keep_temps(keep)
+            .keep_on_error(keep_on_error)
+            .with_inprocess_editor(|path| {
+                *seen.borrow_mut() = Some(path.to_path_buf());
+                if fail {
+                    Err(std::io::Error::other("fail"))
+                } else {
+                    Ok(())
+                }
+            });
+        assert_eq!(!fail, edit.buffer(b"foo\n".to_vec()).is_ok());
+        let buffer_temp = seen.borrow_mut().take().unwrap();
+        assert_eq!(!fail, edit.file_copy(&src, &dst).is_ok());
+        let copy_temp = seen.borrow_mut().take().unwrap();
+        (buffer_temp, copy_temp)
+    };
+
+    for keep_on_error in [false, true] {
+        let (buffer_temp, copy_temp) = run(false, keep_on_error, false);
+        assert!(!buffer_temp.exists());
+        assert!(!copy_temp.exists());
+    }
+
+    let (buffer_temp, copy_temp) = run(false, false, true);
+    assert!(!buffer_temp.exists());
+    assert!(!copy_temp.exists());
+
+    for keep_on_error in [false, true] {
+        let (buffer_temp, copy_temp) = run(true, keep_on_error, false);
+        assert_eq!(b"foo\n", std::fs::read(&buffer_temp).unwrap().as_slice());
+        assert!(!copy_temp.exists(), "persisted as destination");
+        std::fs::remove_file(buffer_temp).unwrap();
+    }
+
+    for keep in [false, true] {
+        let (buffer_temp, copy_temp) = run(keep, true, true);
+        assert_eq!(b"foo\n", std::fs::read(&buffer_temp).unwrap().as_slice());
+        assert_eq!(b"foo\n", std::fs::read(&copy_temp).unwrap().as_slice());
+        std::fs::remove_file(buffer_temp).unwrap();
+        std::fs::remove_file(copy_temp).unwrap();
+    }
+}
+
+#[test]
+fn test_soft_timeout() {
+    let _lock = shared();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("file");
+    let fired = std::cell::Cell::new(0);
+
+    super::edit()
+        .with(OsStr::new("sleep 0.3; echo foo >"))
+        .with_soft_timeout(std::time::Duration::from_millis(50), || {
+            fired.set(fired.get() + 1)
+        })
+        .file(&path)
+        .unwrap();
+    assert_eq!(1, fired.get());
+    // The editor hasn’t been disrupted.
+    assert_eq!("foo\n", std::fs::read_to_string(&path).unwrap());
+
+    super::edit()
+        .with(OsStr::new("echo bar >"))
+        .with_soft_timeout(std::time::Duration::from_secs(5), || {
+            fired.set(fired.get() + 1)
+        })
+        .file(&path)
+        .unwrap();
+    assert_eq!(1, fired.get());
+    assert_eq!("bar\n", std::fs::read_to_string(&path).unwrap());
+}
+
+#[test]
+fn test_retry_delay() {
+    let _lock = shared();
+    let delay = std::time::Duration::from_millis(50);
+    let runs = std::cell::RefCell::new(Vec::new());
+    let got = super::edit()
+        .with_inprocess_editor(|_| {
+            runs.borrow_mut().push(std::time::Instant::now());
+            Ok(())
+        })
+        .with_max_attempts(3)
+        .with_retry_delay(delay)
+        .edit_or_default(b"foo\n".to_vec(), |_| Err("nope"), || 7u32)
+        .unwrap_err();
+    assert_eq!("invalid content: nope", got.to_string());
+    let runs = runs.into_inner();
+    assert_eq!(3, runs.len());
+    for pair in runs.windows(2) {
+        let elapsed = pair[1] - pair[0];
+        assert!(elapsed >= delay, "{:?} < {:?}", elapsed, delay);
+    }
+}
+
+#[test]
+fn test_hooks() {
+    let _lock = shared();
+    let events = std::cell::RefCell::new(Vec::new());
+    let got = substitute_foo_bar()
+        .on_launch(|path| {
+            let buf = std::fs::read_to_string(path).unwrap();
+            events.borrow_mut().push(("launch", path.to_path_buf(), buf));
+        })
+        .on_exit(|path| {
+            let buf = std::fs::read_to_string(path).unwrap();
+            events.borrow_mut().push(("exit", path.to_path_buf(), buf));
+        })
+        .buffer(b"foo\n".to_vec())
+        .unwrap();
+    assert_eq!(b"bar\n", got.as_slice());
+
+    let events = events.into_inner();
+    assert_eq!(2, events.len());
+    assert_eq!(("launch", "foo\n"), (events[0].0, events[0].2.as_str()));
+    assert_eq!(("exit", "bar\n"), (events[1].0, events[1].2.as_str()));
+    assert_eq!(events[0].1, events[1].1);
+    assert!(events[0].1.starts_with(std::env::temp_dir()));
+    // The temporary file is gone once editing finishes.
+    assert!(!events[0].1.exists());
+}
+
+#[test]
+fn test_buffer_guarded() {
+    let _lock = shared();
+    let value = std::sync::Mutex::new(b"foo\n".to_vec());
+    let active = std::sync::atomic::AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for line in ["bar\n", "baz\n"] {
+            let (value, active) = (&value, &active);
+            scope.spawn(move || {
+                super::edit()
+                    .with_inprocess_editor(|path| {
+                        use std::sync::atomic::Ordering;
+                        assert_eq!(0, active.fetch_add(1, Ordering::SeqCst));
+                        std::thread::sleep(std::time::Duration::from_millis(
+                            50,
+                        ));
+                        let mut buf = std::fs::read(path)?;
+                        buf.extend_from_slice(line.as_bytes());
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        std::fs::write(path, buf)
+                    })
+                    .buffer_guarded(|| value.lock().unwrap())
+                    .unwrap();
+            });
+        }
+    });
+    let value = String::from_utf8(value.into_inner().unwrap()).unwrap();
+    assert!(
+        value == "foo\nbar\nbaz\n" || value == "foo\nbaz\nbar\n",
+        "{:?}",
+        value
+    );
+
+    // Failed edit leaves the buffer unchanged.
+    let value = std::sync::Mutex::new(b"foo\n".to_vec());
+    super::edit()
+        .with_inprocess_editor(|_| Err(std::io::ErrorKind::Other.into()))
+        .buffer_guarded(|| value.lock().unwrap())
+        .unwrap_err();
+    assert_eq!(b"foo\n", value.into_inner().unwrap().as_slice());
+}
+
+#[test]
+fn test_buffer_utf8() {
+    let _lock = shared();
+    let inputs = std::cell::RefCell::new(Vec::new());
+    let editor = |path: &std::path::Path| {
+        let buf = std::fs::read(path)?;
+        let first = inputs.borrow().is_empty();
+        inputs.borrow_mut().push(buf.clone());
+        if first {
+            std::fs::write(path, b"foo \xff bar\n")
+        } else {
+            let buf = String::from_utf8_lossy(&buf).replace('\u{FFFD}', "baz");
+            std::fs::write(path, buf)
+        }
+    };
+
+    let got = super::edit()
+        .with_inprocess_editor(editor)
+        .with_max_attempts(2)
+        .with_retry_delay(std::time::Duration::ZERO)
+        .buffer_utf8("foo\n".into())
+        .unwrap();
+    assert_eq!("foo baz bar\n", got);
+    let inputs = inputs.into_inner();
+    assert_eq!(2, inputs.len());
+    assert_eq!(b"foo\n", inputs[0].as_slice());
+    assert!(inputs[1].starts_with(b"# error: invalid utf-8"));
+    assert!(inputs[1].ends_with(b"\nfoo \xff bar\n"));
+
+    // Content which stays invalid is an error once attempts are exhausted.
+    let err = super::edit()
+        .with_inprocess_editor(|path| std::fs::write(path, b"\xff"))
+        .with_retry_delay(std::time::Duration::ZERO)
+        .buffer_utf8("foo".into())
+        .unwrap_err();
+    assert_eq!(crate::ErrorKind::Invalid, err.kind());
+}
+
+#[test]
+fn test_buffer_display() {
+    let _lock = shared();
+    let responses = std::cell::RefCell::new(vec!["42\n", "forty two\n"]);
+    let inputs = std::cell::RefCell::new(Vec::new());
+    let got = super::edit()
+        .with_inprocess_editor(|path| {
+            inputs.borrow_mut().push(std::fs::read_to_string(path)?);
+            std::fs::write(path, responses.borrow_mut().pop().unwrap())
+        })
+        .buffer_display(&7u32, |text| text.parse::<u32>())
+        .unwrap();
+    assert_eq!(42, got);
+    assert_eq!(vec!["7\n", "forty two\n"], inputs.into_inner());
+
+    // A no-op editor leaves the value unchanged.
+    let got = super::edit()
+        .with(OsStr::new(":"))
+        .buffer_display(&7u32, |text| text.parse::<u32>())
+        .unwrap();
+    assert_eq!(7, got);
+}
+
+#[test]
+#[cfg(feature = "test-util")]
+fn test_recording_editor() {
+    let _lock = shared();
+    let parse = |buf: &[u8]| std::str::from_utf8(buf).unwrap().trim().parse();
+
+    let recorder = super::RecordingEditor::new([
+        b"forty two\n".to_vec(),
+        b"42\n".to_vec(),
+    ]);
+    let got = super::edit()
+        .with_inprocess_editor(|path| recorder.edit(path))
+        .edit_or_default(b"# jobs\n".to_vec(), parse, || 7u32)
+        .unwrap();
+    assert_eq!(42, got);
+    assert_eq!(
+        vec![
+            super::Invocation {
+                input: b"# jobs\n".to_vec(),
+                output: b"forty two\n".to_vec(),
+            },
+            super::Invocation {
+                input: b"forty two\n".to_vec(),
+                output: b"42\n".to_vec(),
+            },
+        ],
+        recorder.log()
+    );
+    assert_eq!(0, recorder.remaining());
+
+    // Running out of responses fails editing.
+    let got = super::edit()
+        .with_inprocess_editor(|path| recorder.edit(path))
+        .buffer(b"foo".to_vec());
+    assert!(got.is_err());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_edit_buffer_like() {
+    use std::os::unix::fs::PermissionsExt;
+    let _lock = shared();
+
+    let tmpdir = tempfile::tempdir().unwrap();
+    let like = tmpdir.path().join("like");
+    let out = tmpdir.path().join("mode");
+    std::fs::write(&like, b"").unwrap();
+    std::fs::set_permissions(&like, std::fs::Permissions::from_mode(0o751))
+        .unwrap();
+
+    let mut editor = OsString::from("f() { stat -c %a \"$1\" >");
+    editor.push(&out);
+    editor.push("; }; f");
+    let got = super::edit()
+        .with(&editor)
+        .buffer_like(b"foo\n".to_vec(), &like)
+        .unwrap();
+    assert_eq!(b"foo\n", got.as_slice());
+    assert_eq!(b"751\n", std::fs::read(&out).unwrap().as_slice());
+}
+
+#[test]
+fn test_file_guarded() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo\n").unwrap();
+
+    substitute_foo_bar().file_guarded(&path).unwrap();
+    assert_eq!(b"bar\n", std::fs::read(&path).unwrap().as_slice());
+
+    // Simulate another program modifying the file while user is editing it.
+    let mut cmd = OsString::from("f() { echo baz >\"$1\"; echo qux >>");
+    cmd.push(&path);
+    cmd.push("; }; f");
+    let err = super::edit().with(&cmd).file_guarded(&path).unwrap_err();
+    assert_eq!(b"bar\nqux\n", std::fs::read(&path).unwrap().as_slice());
+    let edited = match &err.0 {
+        super::error::Inner::ConcurrentModification { edited, .. } => edited,
+        _ => panic!("unexpected error: {}", err),
+    };
+    assert_eq!(Some(tmpdir.path()), edited.parent());
+    assert_eq!(b"baz\n", std::fs::read(edited).unwrap().as_slice());
+}
+
+#[test]
+fn test_edit_buffer_near() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let near = tmpdir.path().join("near");
+
+    let got = super::edit()
+        .with(OsStr::new("f() { printf %s \"$1\" >\"$1\"; }; f"))
+        .buffer_near(Vec::new(), &near)
+        .unwrap();
+    let got = std::path::PathBuf::from(String::from_utf8(got).unwrap());
+    assert_eq!(Some(tmpdir.path()), got.parent());
+    assert!(!got.exists());
+    assert!(!near.exists());
+}
+
+#[test]
+fn test_file_review() {
+    use super::DiffLine::*;
+
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let path = tmpdir.path().join("file");
+    std::fs::write(&path, "foo\nqux\n").unwrap();
+
+    let got = super::edit().with(OsStr::new("touch")).file_review(&path);
+    assert_eq!(None, got.unwrap());
+
+    let got = substitute_foo_bar().file_review(&path).unwrap();
+    let want = vec![
+        Removed(b"foo\n".to_vec()),
+        Added(b"bar\n".to_vec()),
+        Unchanged(b"qux\n".to_vec()),
+    ];
+    assert_eq!(Some(want), got);
+    assert_eq!(b"bar\nqux\n", std::fs::read(&path).unwrap().as_slice());
+    let files = std::fs::read_dir(tmpdir.path()).unwrap().count();
+    assert_eq!(1, files);
+}
+
+#[test]
+fn test_edit_file_copy() {
+    use std::io::Write;
+    let _lock = shared();
+
+    let tmpdir = tempfile::tempdir().unwrap();
+    let src = tmpdir.path().join("src");
+    let dst = tmpdir.path().join("dst");
+    std::fs::File::create(&src).unwrap().write_all(b"foo\n").unwrap();
+    std::fs::File::create(&dst).unwrap().write_all(b"oof\n").unwrap();
+
+    // Test destination is not changed on failure.
+    let res = super::edit().with(OsStr::new("false")).file_copy(&src, &dst);
+    assert_eq!(
+        "false: terminated with exit status: 1",
+        res.unwrap_err().to_string()
+    );
+    assert_eq!(b"foo\n", std::fs::read(&src).unwrap().as_slice());
+    assert_eq!(b"oof\n", std::fs::read(&dst).unwrap().as_slice());
+
+    substitute_foo_bar().file_copy(&src, &dst).unwrap();
+    assert_eq!(b"foo\n", std::fs::read(&src).unwrap().as_slice());
+    assert_eq!(b"bar\n", std::fs::read(&dst).unwrap().as_slice());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_with_result_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let src = tmpdir.path().join("src");
+    let dst = tmpdir.path().join("dst");
+    std::fs::write(&src, "foo\n").unwrap();
+    let mode = |path| std::fs::metadata(path).unwrap().permissions().mode();
+
+    let mut edit = substitute_foo_bar();
+    edit.with_result_mode(0o644, false).file_copy(&src, &dst).unwrap();
+    assert_eq!(0o644, mode(&dst) & 0o7777);
+
+    // Existing destination is not affected unless always is set.
+    edit.with_result_mode(0o640, false).file_copy(&src, &dst).unwrap();
+    assert_eq!(0o600, mode(&dst) & 0o7777);
+    edit.with_result_mode(0o640, true).file_copy(&src, &dst).unwrap();
+    assert_eq!(0o640, mode(&dst) & 0o7777);
+}
+
+#[test]
+fn test_edit_file_copy_to_root() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let src = tmpdir.path().join("src");
+    std::fs::write(&src, b"foo\n").unwrap();
+
+    let err = substitute_foo_bar().file_copy(&src, "/").unwrap_err();
+    assert!(matches!(
+        &err.0,
+        crate::error::Inner::IsADirectory { path } if path.as_os_str() == "/"
+    ));
+    assert_eq!("/: is a directory", err.to_string());
+}
+
+#[test]
+fn test_edit_file_copy_outcome() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let src = tmpdir.path().join("src");
+    let dst = tmpdir.path().join("dst");
+    std::fs::write(&src, b"foo\n").unwrap();
+
+    let got = super::edit()
+        .with(OsStr::new(":"))
+        .file_copy_outcome(&src, &dst)
+        .unwrap();
+    assert_eq!(super::CopyOutcome { created: true, changed: false }, got);
+    assert_eq!(b"foo\n", std::fs::read(&dst).unwrap().as_slice());
+
+    let got = substitute_foo_bar().file_copy_outcome(&src, &dst).unwrap();
+    assert_eq!(super::CopyOutcome { created: false, changed: true }, got);
+    assert_eq!(b"bar\n", std::fs::read(&dst).unwrap().as_slice());
+}
+
+#[test]
+fn test_edit_file_copy_capture() {
+    let _lock = shared();
+    let tmpdir = tempfile::tempdir().unwrap();
+    let src = tmpdir.path().join("src");
+    let dst = tmpdir.path().join("dst");
+    std::fs::write(&src, b"foo\n").unwrap();
+
+    let (before, after) =
+        substitute_foo_bar().file_copy_capture(&src, &dst).unwrap();
+    assert_eq!(b"foo\n", before.as_slice());
+    assert_eq!(b"bar\n", after.as_slice());
+    assert_eq!(after, std::fs::read(&dst).unwrap());
+    assert_eq!(b"foo\n", std::fs::read(&src).unwrap().as_slice());
 }